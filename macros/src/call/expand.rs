@@ -1,16 +1,16 @@
-use super::parse::CallDef;
+use super::parse::{CallDef, CallReceiver};
 use quote::quote;
 
 /// See the `fn call` docs at the `lib.rs` of this crate for a high level definition.
 pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
-	let CallDef { pallet_struct, methods } = def;
+	let CallDef { pallet_struct, methods, errors } = def;
 
 	// This is a vector of all the callable function names.
 	let fn_name = methods.iter().map(|method| &method.name).collect::<Vec<_>>();
 
 	// This is a nested vector of all the arguments for each of the functions in `fn_name`. It does
-	// not include the `self` or `caller: T::AccountId` parameter, which we always assume are the
-	// first two parameters to these calls.
+	// not include the `self`/`runtime` or `origin: T::RuntimeOrigin` parameter, which we always
+	// assume are the first two parameters to these calls.
 	let args_name = methods
 		.iter()
 		.map(|method| method.args.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>())
@@ -23,8 +23,37 @@ pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
 		.map(|method| method.args.iter().map(|(_, type_)| type_.clone()).collect::<Vec<_>>())
 		.collect::<Vec<_>>();
 
+	// This is a vector of the expressions used to call each of the functions in `fn_name`: either
+	// on the pallet instance owned by the runtime, or directly with the runtime.
+	let fn_call = methods
+		.iter()
+		.map(|method| {
+			let name = &method.name;
+			match method.receiver {
+				CallReceiver::SelfRef => quote! {
+					crate::support::GetPallet::<#pallet_struct<T>>::pallet_mut(runtime).#name
+				},
+				CallReceiver::Runtime => quote! { Self::#name },
+			}
+		})
+		.collect::<Vec<_>>();
+
+	// This is `runtime, ` for the functions in `fn_name` which take the runtime as their first
+	// argument, and nothing for the ones called on the pallet itself.
+	let fn_runtime = methods
+		.iter()
+		.map(|method| match method.receiver {
+			CallReceiver::SelfRef => quote! {},
+			CallReceiver::Runtime => quote! { runtime, },
+		})
+		.collect::<Vec<_>>();
+
+	// This is a vector of the index of each of the functions in `fn_name`, which is used to
+	// identify the function when encoding a call. See `CallVariantDef::index`.
+	let fn_index = methods.iter().map(|method| method.index).collect::<Vec<_>>();
+
 	// This quote block creates an `enum Call` which contains all the calls exposed by our pallet,
-	// and the `Dispatch` trait logic to route a `caller` to access those functions.
+	// and the `PalletDispatch` trait logic to route an `origin` to access those functions.
 	let dispatch_impl = quote! {
 		// The callable functions exposed by this pallet.
 		//
@@ -37,28 +66,170 @@ pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
 		}
 
 		// Dispatch logic at the pallet level, mapping each of the items in the `Call` enum to the
-		// appropriate function call with all arguments, including the `caller`.
-		impl<T: Config> crate::support::Dispatch for #pallet_struct<T> {
-			type Caller = T::AccountId;
+		// appropriate function call with all arguments, including the `origin`.
+		//
+		// The pallet is reached through the `runtime` which owns it, since some of the functions
+		// need access to the whole runtime.
+		impl<T: Config> crate::support::PalletDispatch<T> for #pallet_struct<T>
+		where
+			T: crate::support::GetPallet<#pallet_struct<T>>,
+		{
+			type Caller = T::RuntimeOrigin;
 			type Call = Call<T>;
 
-			fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> crate::support::DispatchResult {
+			fn dispatch(
+				runtime: &mut T,
+				origin: Self::Caller,
+				call: Self::Call,
+			) -> crate::support::DispatchResultWithPostInfo {
+				// Functions can either return a `DispatchResult`, or a `DispatchResultWithPostInfo`
+				// to report the weight they actually took.
 				match call {
 					#(
 						Call::#fn_name { #( #args_name ),* } => {
-							self.#fn_name(
-								// Note that we assume the first argument of every call is the `caller`.
-								caller,
+							#fn_call(
+								#fn_runtime
+								// Note that we assume the first argument of every call after the
+								// `self` or `runtime` is the `origin`.
+								origin,
 								#( #args_name ),*
-							)?;
+							)
+							.map(Into::into)
+						},
+					)*
+				}
+			}
+		}
+	};
+
+	// This is a vector of the weight of each of the functions in `fn_name`: the expression given
+	// with `#[weight(...)]`, or `support::DEFAULT_WEIGHT` for the functions without one.
+	let fn_weight = methods
+		.iter()
+		.map(|method| match &method.weight {
+			Some(weight) => quote! { #weight },
+			None => quote! { crate::support::DEFAULT_WEIGHT },
+		})
+		.collect::<Vec<_>>();
+
+	// This quote block implements `GetDispatchInfo` for the `enum Call`. The weight of a function
+	// can depend on its arguments, which are available by reference.
+	let dispatch_info_impl = quote! {
+		impl<T: Config> crate::support::GetDispatchInfo for Call<T> {
+			fn get_dispatch_info(&self) -> crate::support::DispatchInfo {
+				match self {
+					#(
+						#[allow(unused_variables)]
+						Call::#fn_name { #( #args_name ),* } => {
+							crate::support::DispatchInfo { weight: #fn_weight }
+						},
+					)*
+				}
+			}
+		}
+	};
+
+	// This quote block implements `Encode` for the `enum Call`: the index of the function followed
+	// by each of its arguments.
+	let encode_impl = quote! {
+		impl<T: Config> crate::support::codec::Encode for Call<T>
+		where
+			#( #( #args_type: crate::support::codec::Encode, )* )*
+		{
+			fn encode_to(&self, dest: &mut Vec<u8>) {
+				match self {
+					#(
+						Call::#fn_name { #( #args_name ),* } => {
+							dest.push(#fn_index);
+							#( #args_name.encode_to(dest); )*
 						},
 					)*
 				}
-				Ok(())
+			}
+		}
+	};
+
+	// This quote block implements `Decode` for the `enum Call`, reading what `Encode` writes.
+	let decode_impl = quote! {
+		impl<T: Config> crate::support::codec::Decode for Call<T>
+		where
+			#( #( #args_type: crate::support::codec::Decode, )* )*
+		{
+			fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+				match <u8 as crate::support::codec::Decode>::decode_from(input)? {
+					#(
+						#fn_index => Ok(Call::#fn_name {
+							#( #args_name: crate::support::codec::Decode::decode_from(input)?, )*
+						}),
+					)*
+					_ => Err("invalid call index"),
+				}
+			}
+		}
+	};
+
+	// This quote block implements `Clone` for the `enum Call`, e.g. for the transaction pool to
+	// hand out copies of its extrinsics. It is not derived since that would require `T: Clone`.
+	let clone_impl = quote! {
+		impl<T: Config> Clone for Call<T>
+		where
+			#( #( #args_type: Clone, )* )*
+		{
+			fn clone(&self) -> Self {
+				match self {
+					#(
+						Call::#fn_name { #( #args_name ),* } => {
+							Call::#fn_name { #( #args_name: #args_name.clone() ),* }
+						},
+					)*
+				}
+			}
+		}
+	};
+
+	// This is a vector of the names of all the callable functions, as strings.
+	let fn_name_str = fn_name.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+	// This is a nested vector of the names of the arguments of each function, as strings.
+	let args_name_str = args_name
+		.iter()
+		.map(|names| names.iter().map(|name| name.to_string()).collect::<Vec<_>>())
+		.collect::<Vec<_>>();
+
+	// This quote block implements `PalletCalls` for the pallet, describing each of the functions
+	// and their arguments, whose types are the ones of the runtime `T`.
+	let metadata_impl = quote! {
+		impl<T: Config> crate::support::metadata::PalletCalls for #pallet_struct<T> {
+			fn calls() -> Vec<crate::support::metadata::VariantMetadata> {
+				vec![
+					#(
+						crate::support::metadata::VariantMetadata {
+							name: #fn_name_str,
+							index: #fn_index,
+							fields: vec![
+								#(
+									crate::support::metadata::FieldMetadata::new::<#args_type>(
+										#args_name_str,
+									),
+								)*
+							],
+						},
+					)*
+				]
+			}
+
+			fn errors() -> Vec<&'static str> {
+				vec![ #( #errors ),* ]
 			}
 		}
 	};
 
 	// Return the generated code.
-	dispatch_impl.into()
+	quote! {
+		#dispatch_impl
+		#dispatch_info_impl
+		#encode_impl
+		#decode_impl
+		#clone_impl
+		#metadata_impl
+	}
 }
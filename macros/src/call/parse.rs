@@ -4,7 +4,7 @@ use syn::spanned::Spanned;
 // Custom keywords we match to when parsing the calls in a pallet.
 mod keyword {
 	syn::custom_keyword!(T);
-	syn::custom_keyword!(AccountId);
+	syn::custom_keyword!(RuntimeOrigin);
 }
 
 /// This object will collect all the information we need to keep while parsing the callable
@@ -16,6 +16,9 @@ pub struct CallDef {
 	pub pallet_struct: syn::Ident,
 	/// This is a list of the callable functions exposed by this pallet. See `CallVariantDef`.
 	pub methods: Vec<CallVariantDef>,
+	/// The errors the callable functions can fail with, declared with `#[errors(...)]` on the
+	/// `impl`. See `take_errors_attr`.
+	pub errors: Vec<String>,
 }
 
 /// This is the metadata we keep about each callable function in our pallet.
@@ -23,12 +26,31 @@ pub struct CallDef {
 pub struct CallVariantDef {
 	/// The function name.
 	pub name: syn::Ident,
+	/// What the function is called on. See `CallReceiver`.
+	pub receiver: CallReceiver,
 	/// Information on args of the function: `(name, type)`.
 	pub args: Vec<(syn::Ident, Box<syn::Type>)>,
+	/// The expression given with `#[weight(...)]`, if any. It can use the args of the function.
+	pub weight: Option<syn::Expr>,
+	/// The index the function is encoded with: the one given with `#[call_index(n)]`, or its
+	/// position among the callable functions without it.
+	pub index: u8,
+}
+
+/// The first argument of a callable function.
+#[derive(Debug, PartialEq)]
+pub enum CallReceiver {
+	/// Some variant of `self`: the function only needs the state of its own pallet.
+	SelfRef,
+	/// `runtime: &mut T`: the function needs access to the whole runtime, for example to access
+	/// other pallets or to dispatch another call.
+	Runtime,
 }
 
 impl CallDef {
-	pub fn try_from(item: syn::Item) -> syn::Result<Self> {
+	/// Parse the callable functions. The attributes of the macro on the functions, like
+	/// `#[weight(...)]`, are removed from `item`, since they are not real attributes.
+	pub fn try_from(item: &mut syn::Item) -> syn::Result<Self> {
 		// First we check that we are parsing an `impl`.
 		let item_impl = if let syn::Item::Impl(item) = item {
 			item
@@ -43,38 +65,53 @@ impl CallDef {
 			_ => panic!("not supported tokens"),
 		};
 
-		// Here is where we will store all the callable functions.
+		let errors = take_errors_attr(&mut item_impl.attrs)?;
+
+		// Here is where we will store all the callable functions, and the errors they return
+		// themselves.
 		let mut methods = vec![];
-		for item in item_impl.items {
+		let mut returned_errors = vec![];
+		for item in item_impl.items.iter_mut() {
 			if let syn::ImplItem::Fn(method) = item {
 				// Here is where we will store all the args for each callable functions.
 				let mut args = vec![];
 
-				// First argument should be some variant of `self`.
-				match method.sig.inputs.first() {
-					Some(syn::FnArg::Receiver(_)) => {},
+				// First argument should be some variant of `self`, or `runtime: &mut T`.
+				let receiver = match method.sig.inputs.first() {
+					Some(syn::FnArg::Receiver(_)) => CallReceiver::SelfRef,
+					Some(syn::FnArg::Typed(arg)) if is_runtime_arg(arg) => CallReceiver::Runtime,
 					_ => {
-						let msg = "Invalid call, first argument must be a variant of self";
+						let msg = "Invalid call, first argument must be a variant of self or \
+							`runtime: &mut T`";
 						return Err(syn::Error::new(method.sig.span(), msg))
 					},
-				}
+				};
 
-				// The second argument should be the `caller: T::AccountId` argument.
-				match method.sig.inputs.iter().skip(1).next() {
+				// The second argument should be the `origin: T::RuntimeOrigin` argument.
+				match method.sig.inputs.iter().nth(1) {
 					Some(syn::FnArg::Typed(arg)) => {
 						// Here we specifically check that this argument is as we expect for
-						// `caller: T::AccountId`.
-						check_caller_arg(arg)?;
+						// `origin: T::RuntimeOrigin`.
+						check_origin_arg(arg)?;
 					},
 					_ => {
-						let msg = "Invalid call, second argument should be `caller: T::AccountId`";
+						let msg = "Invalid call, second argument should be `origin: T::RuntimeOrigin`";
 						return Err(syn::Error::new(method.sig.span(), msg))
 					},
 				}
 
 				let fn_name = method.sig.ident.clone();
+				let weight = take_weight_attr(&mut method.attrs)?;
+				let index = match take_call_index_attr(&mut method.attrs)? {
+					Some(index) => index,
+					None => u8::try_from(methods.len()).map_err(|_| {
+						let msg = "Invalid call, too many functions for a `u8` call index";
+						syn::Error::new(fn_name.span(), msg)
+					})?,
+				};
+				collect_errors(method.block.to_token_stream(), &mut returned_errors);
 
-				// Parsing the rest of the args. Skipping 2 for `self` and `caller`.
+				// Parsing the rest of the args. Skipping 2 for `self` and `origin`.
 				for arg in method.sig.inputs.iter().skip(2) {
 					// All arguments should be typed.
 					let arg = if let syn::FnArg::Typed(arg) = arg {
@@ -96,42 +133,150 @@ impl CallDef {
 				}
 
 				// Store all the function name and the arg data for the function.
-				methods.push(CallVariantDef { name: fn_name, args });
+				methods.push(CallVariantDef { name: fn_name, receiver, args, weight, index });
 			}
 		}
 
+		// The indices are checked once the attributes of every function are removed, so that
+		// they are not also reported as unknown attributes.
+		check_call_indices(&methods)?;
+		// The errors which the functions return themselves must be declared.
+		if let Some(error) = returned_errors.iter().find(|error| !errors.contains(&error.value())) {
+			let msg = format!(
+				"Invalid call, error {:?} is not declared in `#[errors(...)]`",
+				error.value()
+			);
+			return Err(syn::Error::new(error.span(), msg))
+		}
+
 		// Return all callable functions for this pallet.
-		Ok(Self { pallet_struct, methods })
+		Ok(Self { pallet_struct, methods, errors })
+	}
+}
+
+/// Collect the errors returned in `tokens` as string literals, with `Err("...")` or
+/// `.ok_or("...")`, since the errors of the pallets are `&'static str`.
+fn collect_errors(tokens: proc_macro2::TokenStream, errors: &mut Vec<syn::LitStr>) {
+	let mut tokens = tokens.into_iter().peekable();
+	while let Some(token) = tokens.next() {
+		match token {
+			proc_macro2::TokenTree::Ident(ident) if ident == "Err" || ident == "ok_or" => {
+				let Some(proc_macro2::TokenTree::Group(group)) = tokens.peek() else { continue };
+				if let Ok(error) = syn::parse2::<syn::LitStr>(group.stream()) {
+					errors.push(error);
+				}
+			},
+			proc_macro2::TokenTree::Group(group) => collect_errors(group.stream(), errors),
+			_ => {},
+		}
+	}
+}
+
+/// Remove the `#[errors("...", ...)]` attribute from `attrs`, and return its errors, without
+/// duplicates. There are none without the attribute.
+fn take_errors_attr(attrs: &mut Vec<syn::Attribute>) -> syn::Result<Vec<String>> {
+	let mut errors = None;
+	for attr in attrs.iter().filter(|attr| attr.path().is_ident("errors")) {
+		if errors.is_some() {
+			let msg = "Invalid call, expected a single `#[errors(...)]` attribute";
+			return Err(syn::Error::new(attr.span(), msg))
+		}
+		let literals = attr.parse_args_with(
+			syn::punctuated::Punctuated::<syn::LitStr, syn::Token![,]>::parse_terminated,
+		)?;
+		let mut declared: Vec<String> = Vec::new();
+		for error in literals {
+			if declared.contains(&error.value()) {
+				let msg = format!("Invalid call, error {:?} is declared twice", error.value());
+				return Err(syn::Error::new(error.span(), msg))
+			}
+			declared.push(error.value());
+		}
+		errors = Some(declared);
+	}
+
+	attrs.retain(|attr| !attr.path().is_ident("errors"));
+	Ok(errors.unwrap_or_default())
+}
+
+/// Remove the `#[weight(...)]` attribute from `attrs`, and return its expression if there was one.
+fn take_weight_attr(attrs: &mut Vec<syn::Attribute>) -> syn::Result<Option<syn::Expr>> {
+	let mut weight = None;
+	for attr in attrs.iter().filter(|attr| attr.path().is_ident("weight")) {
+		if weight.is_some() {
+			let msg = "Invalid call, expected a single `#[weight(...)]` attribute";
+			return Err(syn::Error::new(attr.span(), msg))
+		}
+		weight = Some(attr.parse_args::<syn::Expr>()?);
+	}
+
+	attrs.retain(|attr| !attr.path().is_ident("weight"));
+	Ok(weight)
+}
+
+/// Check that the indices of the callable functions are unique.
+fn check_call_indices(methods: &[CallVariantDef]) -> syn::Result<()> {
+	for (i, method) in methods.iter().enumerate() {
+		if let Some(other) = methods[..i].iter().find(|other| other.index == method.index) {
+			let msg = format!(
+				"Invalid call, call index {} is already used by `{}`",
+				method.index, other.name
+			);
+			return Err(syn::Error::new(method.name.span(), msg))
+		}
 	}
+	Ok(())
+}
+
+/// Remove the `#[call_index(n)]` attribute from `attrs`, and return its index, if any.
+fn take_call_index_attr(attrs: &mut Vec<syn::Attribute>) -> syn::Result<Option<u8>> {
+	let mut index = None;
+	for attr in attrs.iter().filter(|attr| attr.path().is_ident("call_index")) {
+		if index.is_some() {
+			let msg = "Invalid call, expected a single `#[call_index(n)]` attribute";
+			return Err(syn::Error::new(attr.span(), msg))
+		}
+		index = Some(attr.parse_args::<syn::LitInt>()?.base10_parse::<u8>()?);
+	}
+
+	attrs.retain(|attr| !attr.path().is_ident("call_index"));
+	Ok(index)
+}
+
+/// Check if the first arg is named `runtime`, which we expect to be `runtime: &mut T`.
+///
+/// The type itself is checked by the compiler, since the generated code passes the runtime to it.
+fn is_runtime_arg(arg: &syn::PatType) -> bool {
+	matches!(&*arg.pat, syn::Pat::Ident(pat) if pat.ident == "runtime")
 }
 
-/// Check caller arg is exactly: `caller: T::AccountId`.
+/// Check origin arg is exactly: `origin: T::RuntimeOrigin`.
 ///
 /// This is kept strict to keep the code simple.
-pub fn check_caller_arg(arg: &syn::PatType) -> syn::Result<()> {
+pub fn check_origin_arg(arg: &syn::PatType) -> syn::Result<()> {
 	pub struct CheckDispatchableFirstArg;
 	impl syn::parse::Parse for CheckDispatchableFirstArg {
 		fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
 			input.parse::<keyword::T>()?;
 			input.parse::<syn::Token![::]>()?;
-			input.parse::<keyword::AccountId>()?;
+			input.parse::<keyword::RuntimeOrigin>()?;
 			Ok(Self)
 		}
 	}
 
-	// This checks the arg name is `caller` or `_caller`.
+	// This checks the arg name is `origin` or `_origin`.
 	if let syn::Pat::Ident(ident) = &*arg.pat {
-		// We also support the name as `_caller` for when the variable is unused.
-		if &ident.ident != "caller" && &ident.ident != "_caller" {
-			let msg = "Invalid name for second parameter: expected `caller: T::AccountId`";
+		// We also support the name as `_origin` for when the variable is unused.
+		if &ident.ident != "origin" && &ident.ident != "_origin" {
+			let msg = "Invalid name for second parameter: expected `origin: T::RuntimeOrigin`";
 			return Err(syn::Error::new(ident.span(), msg))
 		}
 	}
 
-	// This checks the type is `T::AccountId` with `CheckDispatchableFirstArg`
+	// This checks the type is `T::RuntimeOrigin` with `CheckDispatchableFirstArg`
 	let ty = &arg.ty;
 	syn::parse2::<CheckDispatchableFirstArg>(ty.to_token_stream()).map_err(|e| {
-		let msg = "Invalid type for second parameter: expected `caller: T::AccountId`";
+		let msg = "Invalid type for second parameter: expected `origin: T::RuntimeOrigin`";
 		let mut err = syn::Error::new(ty.span(), msg);
 		err.combine(e);
 		err
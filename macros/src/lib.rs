@@ -1,6 +1,27 @@
 mod call;
 mod runtime;
 
+/// Expand the callable functions of a pallet.
+///
+/// This generates:
+/// - `enum Call` - representing all the callable functions of the pallet, with their arguments.
+/// - implements the trait `support::PalletDispatch` to dispatch a `Call` to its function.
+/// - implements the trait `support::GetDispatchInfo` for `Call`. The weight of a function is given
+///   with `#[weight(...)]`, which can be any expression using the arguments of the function, e.g.
+///   `#[weight(DEFAULT_WEIGHT.saturating_add(call.get_dispatch_info().weight))]`. Functions without
+///   it weigh `support::DEFAULT_WEIGHT`.
+/// - implements the traits `support::codec::Encode` and `support::codec::Decode` for `Call`,
+///   prefixing each call with the index of its function. The index is given with
+///   `#[call_index(n)]`, so that reordering the functions does not change the encoding, and
+///   defaults to the position of the function. Two functions with the same index, or a position
+///   which does not fit in a `u8`, are a compile error.
+/// - implements `Clone` for `Call`, as long as the arguments of every function are `Clone`.
+/// - implements the trait `support::metadata::PalletCalls` for the pallet, describing each function
+///   with the names and types of its arguments, and the errors the functions can fail with. The
+///   errors are declared with `#[errors("...", ...)]` on the `impl`, including the ones of the
+///   helper functions the functions use, like `"Bad origin"`. An error a function returns itself,
+///   as the string literal of an `Err("...")` or `.ok_or("...")`, which is not declared is a
+///   compile error.
 #[proc_macro_attribute]
 pub fn call(
 	attr: proc_macro::TokenStream,
@@ -16,7 +37,59 @@ pub fn call(
 ///   included in the runtime.
 /// - `fn execute_block()` - which handles basic logic for executing a block of extrinsics. It does
 ///   basic actions like incrementing the block number and checking the block to be executed has a
-///   valid block number.
+///   valid block number, parent hash and extrinsics root, and that the block does not exceed the
+///   `BLOCK_WEIGHTS`, `MAX_BLOCK_LENGTH` and `MAX_EXTRINSICS` of the system pallet. The block must
+///   start with the inherents, which are unsigned, followed by the signed extrinsics, and must
+///   include an inherent for every pallet which requires one. Its digest, without the seal, must
+///   match the digest of the system pallet once the block is executed, and its state root the
+///   `state_root()` of the runtime.
+/// - `fn state_root()` - which is the hash of the encoded state of the runtime.
+/// - `fn apply_extrinsic()` - which dispatches a single extrinsic, or returns an error if it is
+///   invalid. Signed extrinsics must have a valid signature and the next nonce of their signer, and
+///   go through the `types::SignedExtra` of the runtime, which implements
+///   `support::SignedExtension`, e.g. to charge a transaction fee.
+/// - `fn check_block_limits()` and `fn is_inherent()` - which the block builder uses to author a
+///   block one extrinsic at a time.
+/// - `fn dry_run()` - which applies an extrinsic on a copy of the state, as if it was included in
+///   the next block, and returns its result, events and weight without changing the state. The
+///   `Runtime` must implement `Clone`.
+/// - `fn initialize_block()` - which starts a new block: it increments the block number,
+///   initializes the digest with the pre-runtime items of the author, clears the events and the
+///   weight of the previous block, and runs the `on_initialize` hook of every pallet.
+/// - `fn finalize_block()` - which finishes a block once its extrinsics are applied, by running the
+///   `on_idle` hook of every pallet with the weight the block has left, then their `on_finalize`
+///   hook. `execute_block()` and the block builder call it before checking or computing the state
+///   root.
+/// - `fn integrity_test()` - which runs the `integrity_test` hook of every pallet.
+/// - `fn metadata()` - which describes the pallets of the runtime as `support::metadata::
+///   RuntimeMetadata`, starting with system. The calls and errors of a pallet come from its
+///   `support::metadata::PalletCalls`, and its events, storage items and constants from its
+///   `support::metadata::PalletInfo`, which every pallet, including system, must implement.
+///
+/// It implements the traits `support::codec::Encode` and `support::codec::Decode` for `Runtime`,
+/// which encode the state of every pallet in declaration order, e.g. to share a snapshot of the
+/// state with other nodes. Every pallet must implement both traits.
+///
+/// It also implements the trait `support::ValidateTransaction` for `Runtime`, which the
+/// transaction pool uses to check signed extrinsics against the current state, and to order them
+/// by priority and nonce.
+///
+/// Every pallet, including system, must implement the trait `support::Hooks`, whose hooks are
+/// called in declaration order, starting with system.
+///
+/// A pallet field can be marked `#[inherent]` when the pallet provides inherents, i.e. implements
+/// `support::ProvideInherent`, like the `timestamp` pallet.
+///
+/// A pallet field can be marked `#[events]` when the pallet deposits events, i.e. implements
+/// `support::PalletEvents` with an `Event<Runtime>` type, like the `balances` pallet. Once an
+/// extrinsic is applied, the events of its call are deposited in the system pallet, followed by
+/// the `ExtrinsicSuccess` or `ExtrinsicFailed` event of system. A call which fails is reverted,
+/// along with its events; only the nonce and the fee of its signer are kept.
+///
+/// A pallet field can be given its index with `#[pallet_index(n)]`, so that reordering the fields
+/// does not change the encoding of its calls and events. It defaults to the position of the field,
+/// system being 0. Two pallets with the same index, or a pallet with the index 0 of system, are a
+/// compile error.
 ///
 /// This also generates code needed for dispatching calls to the pallets:
 /// - Note: For simplicity, we assume that the system pallet is not callable.
@@ -25,6 +98,17 @@ pub fn call(
 /// - implements the trait `support::Dispatch` to dispatch calls to the appropriate pallet. Basic
 ///   logic like incrementing the nonce of the user is included in the generated code. The system
 ///   pallet is not included.
+/// - implements the trait `support::GetDispatchInfo` for `RuntimeCall`, using the one of the pallet
+///   level call.
+/// - implements the traits `support::codec::Encode` and `support::codec::Decode` for
+///   `RuntimeCall`, prefixing each call with the index of its pallet.
+/// - `enum RuntimeEvent` - an "outer"-enum of the events of system and of the pallets marked
+///   `#[events]`, which implements `support::codec::Encode`, prefixing each event with the index of
+///   its pallet, system being 0.
+/// - `enum RuntimeOrigin` - an "outer"-enum representing the origins calls can be dispatched with,
+///   convertible from and into `support::RawOrigin`.
+/// - implements the trait `support::GetPallet` for every pallet, including system, so pallet level
+///   dispatch can reach the pallet instance owned by the runtime.
 #[proc_macro_attribute]
 pub fn runtime(
 	attr: proc_macro::TokenStream,
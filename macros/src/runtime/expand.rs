@@ -1,4 +1,4 @@
-use super::parse::RuntimeDef;
+use super::parse::{PalletDef, RuntimeDef};
 use quote::quote;
 
 /// See the `fn runtime` docs at the `lib.rs` of this crate for a high level definition.
@@ -6,9 +6,45 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 	let RuntimeDef { runtime_struct, pallets } = def;
 
 	// This is a vector of all the pallet names, not including system.
-	let pallet_names = pallets.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
+	let pallet_names = pallets.iter().map(|pallet| pallet.name.clone()).collect::<Vec<_>>();
 	// This is a vector of all the pallet types, not including system.
-	let pallet_types = pallets.iter().map(|(_, type_)| type_.clone()).collect::<Vec<_>>();
+	let pallet_types = pallets.iter().map(|pallet| pallet.type_.clone()).collect::<Vec<_>>();
+	// This is a vector of the names of the pallets marked `#[inherent]`.
+	let inherent_names =
+		pallets.iter().filter(|p| p.inherent).map(|p| p.name.clone()).collect::<Vec<_>>();
+	// This is a vector of the types of the pallets marked `#[inherent]`.
+	let inherent_types =
+		pallets.iter().filter(|p| p.inherent).map(|p| p.type_.clone()).collect::<Vec<_>>();
+	// This is a vector of the names of the pallets marked `#[events]`.
+	let event_names =
+		pallets.iter().filter(|p| p.events).map(|p| p.name.clone()).collect::<Vec<_>>();
+	// This is a vector of the index of each pallet, which is used to identify the pallet when
+	// encoding a call or an event. See `PalletDef::index`. System, which is not in the list of
+	// pallets, is 0.
+	let pallet_index = pallets.iter().map(|pallet| pallet.index).collect::<Vec<_>>();
+	// This is a vector of the index of each pallet marked `#[events]`.
+	let event_index = pallets
+		.iter()
+		.zip(&pallet_index)
+		.filter(|(p, _)| p.events)
+		.map(|(_, index)| *index)
+		.collect::<Vec<_>>();
+	// This is a vector of the match arms checking whether a `RuntimeCall` is an inherent, which is
+	// never the case for the calls of the pallets not marked `#[inherent]`.
+	let is_inherent_arms = pallets
+		.iter()
+		.map(|pallet| {
+			let PalletDef { name, type_, .. } = pallet;
+			if pallet.inherent {
+				quote! {
+					RuntimeCall::#name(call) =>
+						<#type_ as crate::support::ProvideInherent>::is_inherent(call)
+				}
+			} else {
+				quote! { RuntimeCall::#name(_) => false }
+			}
+		})
+		.collect::<Vec<_>>();
 
 	// This quote block implements functions on the `Runtime` struct.
 	let runtime_impl = quote! {
@@ -24,21 +60,291 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 				}
 			}
 
-			// Execute a block of extrinsics. Increments the block number.
-			fn execute_block(&mut self, block: types::Block) -> crate::support::DispatchResult {
+			// The hash of the encoded state of the runtime, which is the state of all its pallets.
+			fn state_root(&self) -> crate::support::hashing::Hash {
+				crate::support::hashing::hash(&crate::support::codec::Encode::encode(self))
+			}
+
+			// Whether the call is an inherent of its pallet, which is only possible for the pallets
+			// marked `#[inherent]`.
+			fn is_inherent(call: &RuntimeCall) -> bool {
+				match call {
+					#( #is_inherent_arms ),*
+				}
+			}
+
+			// Start executing a new block with the pre-runtime `digest` of its author: increment the
+			// block number and clear the events of the previous block, including those the pallets
+			// deposited outside of any block, e.g. at genesis. Then run the `on_initialize` hook of
+			// every pallet, in declaration order.
+			fn initialize_block(&mut self, digest: &crate::support::Digest) {
+				use crate::support::Hooks;
+
 				self.system.inc_block_number();
+				self.system.initialize_digest(digest);
+				self.system.reset_events();
+				#( crate::support::PalletEvents::take_events(&mut self.#event_names); )*
+
+				let n = self.system.block_number();
+				let weight = <system::Pallet<Self> as Hooks<Self, _>>::on_initialize(self, n);
+				self.system.register_extra_weight(weight);
+				#(
+					let weight = <#pallet_types as Hooks<Self, _>>::on_initialize(self, n);
+					self.system.register_extra_weight(weight);
+				)*
+				self.deposit_hook_events(crate::support::Phase::Initialization);
+			}
+
+			// Finish executing the current block once its extrinsics are applied: run the `on_idle`
+			// hook of every pallet with the weight the block has left, then their `on_finalize`
+			// hook, in declaration order.
+			fn finalize_block(&mut self) {
+				use crate::support::Hooks;
+
+				let remaining_weight = self.system.remaining_weight();
+				let weight = <system::Pallet<Self> as Hooks<Self, _>>::on_idle(self, remaining_weight);
+				self.system.register_extra_weight(weight);
+				#(
+					let remaining_weight = self.system.remaining_weight();
+					let weight = <#pallet_types as Hooks<Self, _>>::on_idle(self, remaining_weight);
+					self.system.register_extra_weight(weight);
+				)*
+
+				let n = self.system.block_number();
+				<system::Pallet<Self> as Hooks<Self, _>>::on_finalize(self, n);
+				#( <#pallet_types as Hooks<Self, _>>::on_finalize(self, n); )*
+				self.deposit_hook_events(crate::support::Phase::Finalization);
+			}
+
+			// Check the configuration of every pallet with its `integrity_test` hook, which panics if
+			// it is invalid.
+			fn integrity_test() {
+				use crate::support::Hooks;
+
+				type BlockNumber = <#runtime_struct as system::Config>::BlockNumber;
+				<system::Pallet<Self> as Hooks<Self, BlockNumber>>::integrity_test();
+				#( <#pallet_types as Hooks<Self, BlockNumber>>::integrity_test(); )*
+			}
+
+			// Deposit in the system pallet, in `phase`, the events the pallets deposited in their
+			// hooks.
+			#[allow(unused_variables)]
+			fn deposit_hook_events(&mut self, phase: crate::support::Phase) {
+				#(
+					for event in crate::support::PalletEvents::take_events(&mut self.#event_names) {
+						self.system.deposit_event_in_phase(phase, RuntimeEvent::#event_names(event));
+					}
+				)*
+			}
+
+			// Dispatch the call of an extrinsic with `origin`. The changes of a call which fails,
+			// including its events, are reverted, e.g. those of a nested call which succeeded
+			// before the outer call failed.
+			fn dispatch_transactional(
+				&mut self,
+				origin: RuntimeOrigin,
+				call: RuntimeCall,
+			) -> crate::support::DispatchResultWithPostInfo {
+				let snapshot = self.clone();
+				let res = self.dispatch(origin, call);
+				if res.is_err() {
+					*self = snapshot;
+				}
+				res
+			}
+
+			// Deposit in the system pallet the events the pallets deposited while dispatching the
+			// call of an extrinsic, followed by the outcome of the extrinsic. A call which failed
+			// was reverted, so it has no events to deposit.
+			fn deposit_events(
+				&mut self,
+				res: &crate::support::DispatchResultWithPostInfo,
+				info: &crate::support::DispatchInfo,
+			) {
+				#(
+					let events = crate::support::PalletEvents::take_events(&mut self.#event_names);
+					if res.is_ok() {
+						for event in events {
+							self.system.deposit_event(RuntimeEvent::#event_names(event));
+						}
+					}
+				)*
+				self.system.note_applied_extrinsic(res, info);
+			}
+
+			// Apply a single extrinsic of the current block.
+			//
+			// Unsigned extrinsics must be inherents, and inherents must be unsigned. A signed
+			// extrinsic must have a valid signature and the next nonce of its signer. It then goes
+			// through the `types::SignedExtra` of the runtime, e.g. to charge its fee: if any of
+			// these checks fail, the extrinsic is invalid and the state is left unchanged: it is
+			// not dispatched and the nonce of the signer is not incremented.
+			fn apply_extrinsic(
+				&mut self,
+				extrinsic: types::Extrinsic,
+			) -> crate::support::ApplyExtrinsicResult {
+				use crate::support::{GetDispatchInfo, SignedExtension};
+
+				let len = crate::support::codec::Encode::encode(&extrinsic).len();
+				extrinsic.check_signature()?;
+				let support::Extrinsic { signer, call } = extrinsic;
+				let info = call.get_dispatch_info();
+
+				let Some(signer) = signer else {
+					if !Self::is_inherent(&call) {
+						return Err("unsigned extrinsics can only be inherents")
+					}
+					let res = self.dispatch_transactional(crate::support::RawOrigin::None.into(), call);
+					self.deposit_events(&res, &info);
+					return Ok(res)
+				};
+				if Self::is_inherent(&call) {
+					return Err("inherents must be unsigned")
+				}
+				if signer.nonce != self.system.get_nonce(&signer.who) {
+					return Err("invalid transaction nonce")
+				}
+				let pre = <types::SignedExtra as SignedExtension<Self>>::pre_dispatch(
+					self, &signer.who, &info, len,
+				)?;
+				self.system.inc_nonce(&signer.who);
+				let origin = crate::support::RawOrigin::Signed(signer.who).into();
+				let res = self.dispatch_transactional(origin, call);
+				// A call which failed took its full weight.
+				let post_info = res.unwrap_or_default();
+				<types::SignedExtra as SignedExtension<Self>>::post_dispatch(
+					self, pre, &info, &post_info, len,
+				);
+				self.deposit_events(&res, &info);
+				Ok(res)
+			}
+
+			// Apply `extrinsic` as if it was included in the next block, on a copy of the state
+			// which is thrown away, and return what it would do. The state is not changed.
+			fn dry_run(
+				&self,
+				extrinsic: types::Extrinsic,
+			) -> crate::support::DryRunResult<RuntimeEvent> {
+				let info = crate::support::GetDispatchInfo::get_dispatch_info(&extrinsic.call);
+				let mut overlay = self.clone();
+				overlay.initialize_block(&crate::support::Digest::default());
+				let result = overlay.apply_extrinsic(extrinsic);
+				let weight = match &result {
+					Ok(Ok(post_info)) => post_info.calc_actual_weight(&info),
+					_ => info.weight,
+				};
+				crate::support::DryRunResult {
+					result,
+					events: overlay.system.events().to_vec(),
+					weight,
+				}
+			}
+
+			// Check that `extrinsics` fit in a block which already consumed `initial_weight` before
+			// them, e.g. in the `on_initialize` hooks of the pallets: their number, their encoded
+			// length and the total weight of the block must be within the limits of the system
+			// pallet.
+			fn check_block_limits(
+				initial_weight: crate::support::Weight,
+				extrinsics: &[types::Extrinsic],
+			) -> crate::support::DispatchResult {
+				if extrinsics.len() > <Self as system::Config>::MAX_EXTRINSICS as usize {
+					return Err("block has too many extrinsics")
+				}
+
+				let block_length = crate::support::codec::Encode::encode(extrinsics).len();
+				if block_length > <Self as system::Config>::MAX_BLOCK_LENGTH as usize {
+					return Err("block length exceeds the maximum block length")
+				}
+
+				// The total weight of the block, including the base weight of every extrinsic.
+				let block_weights = <Self as system::Config>::BLOCK_WEIGHTS;
+				let mut block_weight = initial_weight;
+				for ext in extrinsics {
+					let info = crate::support::GetDispatchInfo::get_dispatch_info(&ext.call);
+					block_weight = block_weight
+						.saturating_add(block_weights.base_extrinsic)
+						.saturating_add(info.weight);
+				}
+				if block_weight > block_weights.max_block {
+					return Err("block weight exceeds the maximum block weight")
+				}
+
+				Ok(())
+			}
+
+			// Execute a block of extrinsics. Increments the block number, runs the hooks of the
+			// pallets around the extrinsics, and records the events of the block in the system
+			// pallet.
+			//
+			// The block must be built on the last executed block, and start with its inherents,
+			// which are unsigned, followed by the signed extrinsics. An invalid extrinsic, or an
+			// inherent which fails, makes the whole block invalid. Its digest, besides the seal,
+			// must be the pre-runtime items of the author followed by the items deposited by the
+			// pallets.
+			fn execute_block(&mut self, block: types::Block) -> crate::support::DispatchResult {
+				// The pallets deposit their digest items after the pre-runtime items of the author.
+				self.initialize_block(&block.header.digest);
 				if block.header.block_number != self.system.block_number() {
 					return Err(&"block number does not match what is expected")
 				}
-				for (i, support::Extrinsic { caller, call }) in block.extrinsics.into_iter().enumerate() {
-					self.system.inc_nonce(&caller);
-					let _res = self.dispatch(caller, call).map_err(|e| {
+				let block_hash = block.header.hash();
+				if block.header.parent_hash != self.system.block_hash() {
+					return Err("parent hash does not match the last executed block")
+				}
+				if block.header.extrinsics_root != crate::support::extrinsics_root(&block.extrinsics) {
+					return Err("extrinsics root does not match the extrinsics of the block")
+				}
+
+				// The block already consumed the weight of the `on_initialize` hooks.
+				Self::check_block_limits(self.system.block_weight(), &block.extrinsics)?;
+
+				let inherents =
+					block.extrinsics.iter().take_while(|ext| Self::is_inherent(&ext.call)).count();
+				if block.extrinsics[inherents..].iter().any(|ext| Self::is_inherent(&ext.call)) {
+					return Err("inherents must be at the start of the block")
+				}
+				if block.extrinsics[..inherents].iter().any(|ext| ext.signer.is_some()) {
+					return Err("inherents must be unsigned")
+				}
+				if block.extrinsics[inherents..].iter().any(|ext| ext.signer.is_none()) {
+					return Err("extrinsics which are not inherents must be signed")
+				}
+				#(
+					if <#inherent_types as crate::support::ProvideInherent>::is_inherent_required()
+						&& !block.extrinsics[..inherents]
+							.iter()
+							.any(|ext| matches!(ext.call, RuntimeCall::#inherent_names(_)))
+					{
+						return Err("block is missing a required inherent")
+					}
+				)*
+
+				for (i, extrinsic) in block.extrinsics.into_iter().enumerate() {
+					if let Err(e) = self.apply_extrinsic(extrinsic)? {
 						eprintln!(
 							"Extrinsic Error\n\tBlock Number: {}\n\tExtrinsic Number: {}\n\tError: {}",
 							block.header.block_number, i, e
-						)
-					});
+						);
+						if i < inherents {
+							return Err(e)
+						}
+					}
+				}
+
+				self.finalize_block();
+
+				// The digest of the block, without its seal, must be the one the execution produced.
+				if self.system.digest().logs != block.header.digest.without_seal() {
+					return Err("digest does not match the digest of the block")
+				}
+				// The state root commits to the state before the hash of the block is recorded, since
+				// the hash of the block depends on it.
+				if block.header.state_root != self.state_root() {
+					return Err("state root does not match the state after the block")
 				}
+
+				self.system.set_block_hash(block_hash);
 				Ok(())
 			}
 		}
@@ -51,41 +357,304 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 		//
 		// The parsed function names will be `snake_case`, and that will show up in the enum.
 		#[allow(non_camel_case_types)]
+		#[derive(Clone)]
 		pub enum RuntimeCall {
 			#( #pallet_names(#pallet_names::Call<#runtime_struct>) ),*
 		}
 
 		impl crate::support::Dispatch for #runtime_struct {
-			type Caller = <Runtime as system::Config>::AccountId;
+			type Caller = RuntimeOrigin;
 			type Call = RuntimeCall;
-			// Dispatch a call on behalf of a caller. Increments the caller's nonce.
+			// Dispatch a call on behalf of an origin.
 			//
 			// Dispatch allows us to identify which underlying pallet call we want to execute.
-			// Note that we extract the `caller` from the extrinsic, and use that information
-			// to determine who we are executing the call on behalf of.
+			// Note that the `origin` is usually the signer of the extrinsic, but can also be
+			// `Root` for calls dispatched by `sudo`.
 			fn dispatch(
 				&mut self,
-				caller: Self::Caller,
+				origin: Self::Caller,
 				runtime_call: Self::Call,
-			) -> crate::support::DispatchResult {
+			) -> crate::support::DispatchResultWithPostInfo {
 				// This match statement will allow us to correctly route `RuntimeCall`s
-				// to the appropriate pallet level call.
+				// to the appropriate pallet level call. The pallet is given the whole runtime,
+				// since some calls need more than the pallet itself.
 				match runtime_call {
 					#(
 						RuntimeCall::#pallet_names(call) => {
-							self.#pallet_names.dispatch(caller, call)?;
+							<#pallet_types as crate::support::PalletDispatch<Self>>::dispatch(
+								self, origin, call,
+							)
 						}
 					),*
 				}
-				Ok(())
+			}
+		}
+
+		impl crate::support::GetDispatchInfo for RuntimeCall {
+			// The `DispatchInfo` of the pallet level call.
+			fn get_dispatch_info(&self) -> crate::support::DispatchInfo {
+				match self {
+					#(
+						RuntimeCall::#pallet_names(call) => {
+							crate::support::GetDispatchInfo::get_dispatch_info(call)
+						}
+					),*
+				}
+			}
+		}
+	};
+
+	// This quote block implements the `RuntimeEvent` enum, which the events of every pallet are
+	// converted into before being deposited in the system pallet.
+	let event_impl = quote! {
+		// The events which can be deposited in the runtime.
+		// Note that it is an "outer"-enum, like `RuntimeCall`, of the events of system and of the
+		// pallets marked `#[events]`.
+		#[allow(non_camel_case_types)]
+		#[derive(Debug, Clone)]
+		pub enum RuntimeEvent {
+			system(system::Event),
+			#(
+				#event_names(#event_names::Event<#runtime_struct>),
+			)*
+		}
+
+		impl From<system::Event> for RuntimeEvent {
+			fn from(event: system::Event) -> Self {
+				RuntimeEvent::system(event)
+			}
+		}
+
+		// The events are encoded like calls: the index of the pallet followed by the encoded pallet
+		// level event.
+		impl crate::support::codec::Encode for RuntimeEvent {
+			fn encode_to(&self, dest: &mut Vec<u8>) {
+				match self {
+					RuntimeEvent::system(event) => {
+						dest.push(0);
+						event.encode_to(dest);
+					}
+					#(
+						RuntimeEvent::#event_names(event) => {
+							dest.push(#event_index);
+							event.encode_to(dest);
+						}
+					)*
+				}
+			}
+		}
+	};
+
+	// This quote block implements `ValidateTransaction` for the runtime, which the transaction
+	// pool uses to check and order transactions.
+	let validate_impl = quote! {
+		impl crate::support::ValidateTransaction for #runtime_struct {
+			type Extrinsic = types::Extrinsic;
+
+			// Check the signature, the nonce and the `types::SignedExtra` of a signed extrinsic.
+			//
+			// A transaction whose nonce is after the next nonce of its signer is valid, but
+			// requires the transaction with the previous nonce to be included first.
+			fn validate_transaction(
+				&self,
+				extrinsic: &Self::Extrinsic,
+			) -> Result<crate::support::ValidTransaction, &'static str> {
+				use crate::support::{codec::Encode, GetDispatchInfo, SignedExtension};
+
+				let Some(signer) = &extrinsic.signer else {
+					return Err("unsigned extrinsics can only be inherents")
+				};
+				extrinsic.check_signature()?;
+				let next_nonce = self.system.get_nonce(&signer.who);
+				if signer.nonce < next_nonce {
+					return Err("transaction nonce is outdated")
+				}
+
+				let info = extrinsic.call.get_dispatch_info();
+				let len = extrinsic.encode().len();
+				let priority = <types::SignedExtra as SignedExtension<Self>>::validate(
+					self, &signer.who, &info, len,
+				)?;
+
+				let previous_nonce = num::traits::CheckedSub::checked_sub(
+					&signer.nonce,
+					&num::One::one(),
+				);
+				let requires = match previous_nonce {
+					Some(nonce) if signer.nonce > next_nonce => vec![(&signer.who, nonce).encode()],
+					_ => vec![],
+				};
+				let provides = vec![(&signer.who, signer.nonce).encode()];
+				Ok(crate::support::ValidTransaction { priority, requires, provides })
+			}
+		}
+	};
+
+
+	// This quote block implements `Encode` for the `RuntimeCall` enum: the index of the pallet
+	// followed by the encoded pallet level call.
+	let encode_impl = quote! {
+		impl crate::support::codec::Encode for RuntimeCall {
+			fn encode_to(&self, dest: &mut Vec<u8>) {
+				match self {
+					#(
+						RuntimeCall::#pallet_names(call) => {
+							dest.push(#pallet_index);
+							call.encode_to(dest);
+						}
+					),*
+				}
+			}
+		}
+	};
+
+	// This quote block implements `Decode` for the `RuntimeCall` enum, reading what `Encode` writes.
+	let decode_impl = quote! {
+		impl crate::support::codec::Decode for RuntimeCall {
+			fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+				match <u8 as crate::support::codec::Decode>::decode_from(input)? {
+					#(
+						#pallet_index => Ok(RuntimeCall::#pallet_names(
+							crate::support::codec::Decode::decode_from(input)?,
+						)),
+					)*
+					_ => Err("invalid pallet index"),
+				}
+			}
+		}
+	};
+
+	// This quote block implements `Encode` and `Decode` for the state of the runtime: the state of
+	// each pallet, in the order they are declared in, starting with system.
+	let state_codec_impl = quote! {
+		impl crate::support::codec::Encode for #runtime_struct {
+			fn encode_to(&self, dest: &mut Vec<u8>) {
+				self.system.encode_to(dest);
+				#( self.#pallet_names.encode_to(dest); )*
+			}
+		}
+
+		impl crate::support::codec::Decode for #runtime_struct {
+			fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+				Ok(Self {
+					system: crate::support::codec::Decode::decode_from(input)?,
+					#( #pallet_names: crate::support::codec::Decode::decode_from(input)?, )*
+				})
+			}
+		}
+	};
+
+	// This quote block implements the `RuntimeOrigin` enum, which every origin is converted into
+	// before being dispatched.
+	let origin_impl = quote! {
+		// The origins of the calls which can be dispatched in the runtime.
+		// Note that it is an "outer"-enum, like `RuntimeCall`, although only the system pallet
+		// defines origins for now: `Root`, `Signed` and `None`.
+		#[allow(non_camel_case_types)]
+		#[derive(Debug, Clone, PartialEq, Eq)]
+		pub enum RuntimeOrigin {
+			system(crate::support::RawOrigin<<#runtime_struct as system::Config>::AccountId>),
+		}
+
+		impl From<crate::support::RawOrigin<<#runtime_struct as system::Config>::AccountId>>
+			for RuntimeOrigin
+		{
+			fn from(
+				origin: crate::support::RawOrigin<<#runtime_struct as system::Config>::AccountId>,
+			) -> Self {
+				RuntimeOrigin::system(origin)
+			}
+		}
+
+		// This is how the `ensure_*` functions of `support` get back the `RawOrigin`.
+		impl From<RuntimeOrigin>
+			for Result<
+				crate::support::RawOrigin<<#runtime_struct as system::Config>::AccountId>,
+				RuntimeOrigin,
+			>
+		{
+			fn from(origin: RuntimeOrigin) -> Self {
+				match origin {
+					RuntimeOrigin::system(origin) => Ok(origin),
+				}
+			}
+		}
+	};
+
+	// This quote block gives access to each of the pallets through the runtime, which is how
+	// pallet level calls reach the pallet they are dispatched to.
+	let get_pallet_impl = quote! {
+		impl crate::support::GetPallet<system::Pallet<Self>> for #runtime_struct {
+			fn pallet(&self) -> &system::Pallet<Self> {
+				&self.system
+			}
+			fn pallet_mut(&mut self) -> &mut system::Pallet<Self> {
+				&mut self.system
+			}
+		}
+		#(
+			impl crate::support::GetPallet<#pallet_types> for #runtime_struct {
+				fn pallet(&self) -> &#pallet_types {
+					&self.#pallet_names
+				}
+				fn pallet_mut(&mut self) -> &mut #pallet_types {
+					&mut self.#pallet_names
+				}
+			}
+		)*
+	};
+
+	// This is a vector of the names of all the pallets, as strings, not including system.
+	let pallet_name_str = pallet_names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+
+	// This quote block implements `fn metadata()` for the runtime, which describes each of the
+	// pallets, with the calls described by `#[macros::call]` and the rest described by the pallet.
+	let metadata_impl = quote! {
+		impl #runtime_struct {
+			// The metadata of the runtime, starting with system, which has no calls.
+			fn metadata() -> crate::support::metadata::RuntimeMetadata {
+				use crate::support::metadata::{PalletCalls, PalletInfo, PalletMetadata};
+
+				crate::support::metadata::RuntimeMetadata {
+					pallets: vec![
+						PalletMetadata {
+							name: "system",
+							index: 0,
+							calls: Vec::new(),
+							events: <system::Pallet<Self> as PalletInfo>::events(),
+							errors: Vec::new(),
+							storage: <system::Pallet<Self> as PalletInfo>::storage(),
+							constants: <system::Pallet<Self> as PalletInfo>::constants(),
+						},
+						#(
+							PalletMetadata {
+								name: #pallet_name_str,
+								index: #pallet_index,
+								calls: <#pallet_types as PalletCalls>::calls(),
+								events: <#pallet_types as PalletInfo>::events(),
+								errors: <#pallet_types as PalletCalls>::errors(),
+								storage: <#pallet_types as PalletInfo>::storage(),
+								constants: <#pallet_types as PalletInfo>::constants(),
+							},
+						)*
+					],
+				}
 			}
 		}
 	};
 
 	// We combine and return all the generated code.
 	quote! {
+		#origin_impl
+		#event_impl
 		#dispatch_impl
+		#encode_impl
+		#decode_impl
+		#state_codec_impl
 		#runtime_impl
+		#validate_impl
+		#get_pallet_impl
+		#metadata_impl
 	}
 	.into()
 }
@@ -7,11 +7,32 @@ pub struct RuntimeDef {
 	pub runtime_struct: syn::Ident,
 	/// This is the list of pallets included in the `Runtime` struct. We omit `system` from this
 	/// list, but during parsing we check that system exists.
-	pub pallets: Vec<(syn::Ident, syn::Type)>,
+	pub pallets: Vec<PalletDef>,
+}
+
+/// This is the metadata we keep about each pallet included in the `Runtime` struct.
+#[derive(Debug)]
+pub struct PalletDef {
+	/// The name of the field of the pallet.
+	pub name: syn::Ident,
+	/// The type of the pallet, e.g. `balances::Pallet<Self>`.
+	pub type_: syn::Type,
+	/// Whether the field is marked `#[inherent]`: the pallet implements
+	/// `support::ProvideInherent`, and its inherents are checked when executing a block.
+	pub inherent: bool,
+	/// Whether the field is marked `#[events]`: the pallet implements `support::PalletEvents`, and
+	/// its events are part of the `RuntimeEvent`.
+	pub events: bool,
+	/// The index the calls and events of the pallet are encoded with: the one given with
+	/// `#[pallet_index(n)]`, or its position in the `Runtime` struct without it. System is always
+	/// 0.
+	pub index: u8,
 }
 
 impl RuntimeDef {
-	pub fn try_from(item: syn::Item) -> syn::Result<Self> {
+	/// Parse the `Runtime` struct. The attributes of the macro on the fields, like `#[inherent]`,
+	/// are removed from `item`, since they are not real attributes.
+	pub fn try_from(item: &mut syn::Item) -> syn::Result<Self> {
 		// First we check that we are parsing a `struct`.
 		let item_struct = if let syn::Item::Struct(item) = item {
 			item
@@ -20,19 +41,38 @@ impl RuntimeDef {
 		};
 
 		// We check that the `Runtime` includes the `system` pallet as the first item.
-		check_system(&item_struct)?;
+		check_system(item_struct)?;
 
-		let runtime_struct = item_struct.ident;
+		let runtime_struct = item_struct.ident.clone();
 
 		// Here is where we will store a list of all the pallets.
 		let mut pallets = vec![];
 		// We skip `system`, which we ensure is the first field in `check_system`.
-		for field in item_struct.fields.into_iter().skip(1) {
-			if let Some(ident) = field.ident {
-				pallets.push((ident, field.ty))
+		for field in item_struct.fields.iter_mut().skip(1) {
+			let inherent = take_attr(&mut field.attrs, "inherent");
+			let events = take_attr(&mut field.attrs, "events");
+			let index = match take_pallet_index_attr(&mut field.attrs)? {
+				Some(index) => index,
+				None => u8::try_from(pallets.len() + 1).map_err(|_| {
+					let msg = "Invalid runtime, too many pallets for a `u8` pallet index";
+					syn::Error::new(field.span(), msg)
+				})?,
+			};
+			if let Some(ident) = &field.ident {
+				pallets.push(PalletDef {
+					name: ident.clone(),
+					type_: field.ty.clone(),
+					inherent,
+					events,
+					index,
+				})
 			}
 		}
 
+		// The indices are checked once the attributes of every field are removed, so that they are
+		// not also reported as unknown attributes.
+		check_pallet_indices(&pallets)?;
+
 		Ok(Self { runtime_struct, pallets })
 	}
 }
@@ -65,3 +105,44 @@ fn check_system(item_struct: &syn::ItemStruct) -> syn::Result<()> {
 
 	Ok(())
 }
+
+/// Remove the attributes named `name` from `attrs`, and return whether there was any.
+fn take_attr(attrs: &mut Vec<syn::Attribute>, name: &str) -> bool {
+	let len = attrs.len();
+	attrs.retain(|attr| !attr.path().is_ident(name));
+	attrs.len() != len
+}
+
+/// Check that the indices of the pallets are unique, and that none of them is the index 0 of
+/// `system`.
+fn check_pallet_indices(pallets: &[PalletDef]) -> syn::Result<()> {
+	for (i, pallet) in pallets.iter().enumerate() {
+		if pallet.index == 0 {
+			let msg = "Invalid runtime, pallet index 0 is used by `system`";
+			return Err(syn::Error::new(pallet.name.span(), msg))
+		}
+		if let Some(other) = pallets[..i].iter().find(|other| other.index == pallet.index) {
+			let msg = format!(
+				"Invalid runtime, pallet index {} is already used by `{}`",
+				pallet.index, other.name
+			);
+			return Err(syn::Error::new(pallet.name.span(), msg))
+		}
+	}
+	Ok(())
+}
+
+/// Remove the `#[pallet_index(n)]` attribute from `attrs`, and return its index, if any.
+fn take_pallet_index_attr(attrs: &mut Vec<syn::Attribute>) -> syn::Result<Option<u8>> {
+	let mut index = None;
+	for attr in attrs.iter().filter(|attr| attr.path().is_ident("pallet_index")) {
+		if index.is_some() {
+			let msg = "Invalid runtime, expected a single `#[pallet_index(n)]` attribute";
+			return Err(syn::Error::new(attr.span(), msg))
+		}
+		index = Some(attr.parse_args::<syn::LitInt>()?.base10_parse::<u8>()?);
+	}
+
+	attrs.retain(|attr| !attr.path().is_ident("pallet_index"));
+	Ok(index)
+}
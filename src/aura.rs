@@ -0,0 +1,344 @@
+use crate::support::{
+    codec::{Decode, Encode},
+    ensure_none, ensure_root,
+    metadata::{ConstantMetadata, FieldMetadata, PalletInfo},
+    ConsensusEngineId, DigestItem, DispatchResult, GetPallet, Hooks, ProvideInherent,
+};
+
+/// The id of the Aura engine, which tags its digest items: the slot of the block as a pre-runtime
+/// item, the new authority set as a consensus item, and the seal.
+pub const AURA_ENGINE_ID: ConsensusEngineId = *b"aura";
+
+/// The number of a slot: the time is divided in slots of `SLOT_DURATION`, counted from the unix
+/// epoch, and each slot belongs to a single authority.
+pub type Slot = u64;
+
+pub trait Config: crate::timestamp::Config<Moment: Into<Slot>> + GetPallet<Pallet<Self>> {
+    /// The duration of a slot, in the unit of the timestamp.
+    const SLOT_DURATION: Slot;
+    /// The number of blocks of a session. The authority set only changes at the start of a
+    /// session.
+    const SESSION_LENGTH: u32;
+}
+
+/// This is the Aura Module.
+/// It keeps track of the authorities which take turns to author blocks, one slot each, and of the
+/// slot of the current block, which the block author notes with an inherent in every block.
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+    /// The authorities allowed to author blocks, in the order of their slots.
+    pub authorities: Vec<T::AccountId>,
+    /// The authority set which replaces `authorities` at the start of the next session, if any.
+    next_authorities: Option<Vec<T::AccountId>>,
+    /// The slot of the current block.
+    current_slot: Slot,
+    /// The number of blocks of the current session so far.
+    session_blocks: u32,
+}
+
+#[macros::call]
+#[errors(
+    "Bad origin",
+    "Slot does not match the timestamp of the block",
+    "Slot does not match the pre-runtime digest of the block",
+    "Slot must increase at every block",
+    "Authority set must not be empty"
+)]
+impl<T: Config> Pallet<T> {
+    /// Note `slot` as the slot of the current block.
+    /// This is an inherent: it can only be dispatched unsigned, after the timestamp inherent,
+    /// and `slot` must be the slot of the timestamp of the block, later than the slot of the
+    /// previous block, and the slot of the pre-runtime digest of the block, if any. It enacts the
+    /// next authority set when a new session starts, and deposits it in the digest.
+    #[call_index(0)]
+    pub fn note_slot(runtime: &mut T, origin: T::RuntimeOrigin, slot: Slot) -> DispatchResult {
+        ensure_none(origin)?;
+
+        let timestamp: &crate::timestamp::Pallet<T> = runtime.pallet();
+        let now: Slot = timestamp.now().into();
+        if slot != now / T::SLOT_DURATION {
+            return Err("Slot does not match the timestamp of the block");
+        }
+        let system: &crate::system::Pallet<T> = runtime.pallet();
+        if let Some(pre_runtime) = system.digest().pre_runtime(AURA_ENGINE_ID) {
+            if pre_runtime != slot.encode() {
+                return Err("Slot does not match the pre-runtime digest of the block");
+            }
+        }
+
+        let pallet: &mut Pallet<T> = runtime.pallet_mut();
+        if slot <= pallet.current_slot {
+            return Err("Slot must increase at every block");
+        }
+
+        pallet.current_slot = slot;
+        pallet.session_blocks += 1;
+        if pallet.session_blocks < T::SESSION_LENGTH {
+            return Ok(());
+        }
+        pallet.session_blocks = 0;
+        if let Some(authorities) = pallet.next_authorities.take() {
+            // The consensus engine learns about the new authority set from the digest.
+            let log = DigestItem::Consensus(AURA_ENGINE_ID, authorities.encode());
+            pallet.authorities = authorities;
+            let system: &mut crate::system::Pallet<T> = runtime.pallet_mut();
+            system.deposit_log(log);
+        }
+        Ok(())
+    }
+
+    /// Set the authority set of the next session to `authorities`.
+    /// This is a privileged function which can only be called by `Root`, e.g. through `sudo`.
+    #[call_index(1)]
+    pub fn set_authorities(
+        &mut self,
+        origin: T::RuntimeOrigin,
+        authorities: Vec<T::AccountId>,
+    ) -> DispatchResult {
+        ensure_root(origin)?;
+        if authorities.is_empty() {
+            return Err("Authority set must not be empty");
+        }
+
+        self.next_authorities = Some(authorities);
+        Ok(())
+    }
+}
+
+impl<T: Config> Pallet<T> {
+    /// Create a new instance of the Aura Module, without any authority.
+    pub fn new() -> Self {
+        Self {
+            authorities: Vec::new(),
+            next_authorities: None,
+            current_slot: 0,
+            session_blocks: 0,
+        }
+    }
+
+    /// Get the slot of the current block.
+    pub fn current_slot(&self) -> Slot {
+        self.current_slot
+    }
+
+    /// Get the authority allowed to author a block in `slot`, if there is any authority.
+    pub fn slot_author(&self, slot: Slot) -> Option<&T::AccountId> {
+        let index = slot.checked_rem(self.authorities.len() as Slot)?;
+        self.authorities.get(index as usize)
+    }
+}
+
+impl<T: Config> Encode for Pallet<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.authorities.encode_to(dest);
+        self.next_authorities.encode_to(dest);
+        self.current_slot.encode_to(dest);
+        self.session_blocks.encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for Pallet<T>
+where
+    T::AccountId: Decode,
+{
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        Ok(Self {
+            authorities: Decode::decode_from(input)?,
+            next_authorities: Decode::decode_from(input)?,
+            current_slot: Decode::decode_from(input)?,
+            session_blocks: Decode::decode_from(input)?,
+        })
+    }
+}
+
+/// The slot is only needed by the Aura consensus, which requires it in every block.
+impl<T: Config> ProvideInherent for Pallet<T> {
+    type Call = Call<T>;
+
+    fn is_inherent(call: &Self::Call) -> bool {
+        matches!(call, Call::note_slot { .. })
+    }
+}
+
+impl<T: Config> PalletInfo for Pallet<T> {
+    fn storage() -> Vec<FieldMetadata> {
+        vec![
+            FieldMetadata::new::<Vec<T::AccountId>>("authorities"),
+            FieldMetadata::new::<Option<Vec<T::AccountId>>>("next_authorities"),
+            FieldMetadata::new::<Slot>("current_slot"),
+            FieldMetadata::new::<u32>("session_blocks"),
+        ]
+    }
+
+    fn constants() -> Vec<ConstantMetadata> {
+        vec![
+            ConstantMetadata::new("SLOT_DURATION", T::SLOT_DURATION),
+            ConstantMetadata::new("SESSION_LENGTH", T::SESSION_LENGTH),
+        ]
+    }
+}
+
+impl<T: Config> Hooks<T, T::BlockNumber> for Pallet<T> {
+    fn integrity_test() {
+        assert!(T::SLOT_DURATION > 0, "the slot duration must not be zero");
+        assert!(
+            T::SESSION_LENGTH > 0,
+            "a session must last at least one block"
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::support::{codec::Encode, Digest, DigestItem, GetPallet, RawOrigin};
+
+    struct TestRuntime {
+        system: crate::system::Pallet<TestRuntime>,
+        timestamp: crate::timestamp::Pallet<TestRuntime>,
+        aura: super::Pallet<TestRuntime>,
+    }
+
+    impl super::Config for TestRuntime {
+        const SLOT_DURATION: u64 = 10;
+        const SESSION_LENGTH: u32 = 2;
+    }
+
+    impl crate::timestamp::Config for TestRuntime {
+        type Moment = u64;
+        const MINIMUM_PERIOD: u64 = 5;
+    }
+
+    impl crate::system::Config for TestRuntime {
+        type AccountId = String;
+        type RuntimeOrigin = RawOrigin<String>;
+        type BlockNumber = u32;
+        type Nonce = u32;
+        type RuntimeEvent = crate::system::Event;
+    }
+
+    impl GetPallet<crate::system::Pallet<Self>> for TestRuntime {
+        fn pallet(&self) -> &crate::system::Pallet<Self> {
+            &self.system
+        }
+        fn pallet_mut(&mut self) -> &mut crate::system::Pallet<Self> {
+            &mut self.system
+        }
+    }
+
+    impl GetPallet<crate::timestamp::Pallet<Self>> for TestRuntime {
+        fn pallet(&self) -> &crate::timestamp::Pallet<Self> {
+            &self.timestamp
+        }
+        fn pallet_mut(&mut self) -> &mut crate::timestamp::Pallet<Self> {
+            &mut self.timestamp
+        }
+    }
+
+    impl GetPallet<super::Pallet<Self>> for TestRuntime {
+        fn pallet(&self) -> &super::Pallet<Self> {
+            &self.aura
+        }
+        fn pallet_mut(&mut self) -> &mut super::Pallet<Self> {
+            &mut self.aura
+        }
+    }
+
+    fn new_runtime() -> TestRuntime {
+        let mut aura = super::Pallet::new();
+        aura.authorities = vec!["alice".to_string(), "bob".to_string()];
+        TestRuntime {
+            system: crate::system::Pallet::new(),
+            timestamp: crate::timestamp::Pallet::new(),
+            aura,
+        }
+    }
+
+    // Start the next block at the timestamp `now`, and note `slot` in it.
+    fn next_block(
+        runtime: &mut TestRuntime,
+        now: u64,
+        slot: u64,
+    ) -> crate::support::DispatchResult {
+        runtime.system.inc_block_number();
+        crate::timestamp::Pallet::set(runtime, RawOrigin::None, now).unwrap();
+        super::Pallet::note_slot(runtime, RawOrigin::None, slot)
+    }
+
+    #[test]
+    fn note_slot() {
+        let mut runtime = new_runtime();
+
+        assert_eq!(
+            next_block(&mut runtime, 105, 11),
+            Err("Slot does not match the timestamp of the block")
+        );
+        assert_eq!(runtime.aura.current_slot(), 0);
+
+        let res = super::Pallet::note_slot(&mut runtime, RawOrigin::None, 10);
+        assert_eq!(res, Ok(()));
+        assert_eq!(runtime.aura.current_slot(), 10);
+        assert_eq!(runtime.aura.slot_author(10), Some(&"alice".to_string()));
+        assert_eq!(runtime.aura.slot_author(11), Some(&"bob".to_string()));
+
+        let res = super::Pallet::note_slot(&mut runtime, RawOrigin::None, 10);
+        assert_eq!(res, Err("Slot must increase at every block"));
+
+        let res = super::Pallet::note_slot(&mut runtime, RawOrigin::Root, 10);
+        assert_eq!(res, Err("Bad origin"));
+    }
+
+    #[test]
+    fn note_slot_checks_pre_runtime_digest() {
+        let mut runtime = new_runtime();
+        runtime.system.initialize_digest(&Digest {
+            logs: vec![DigestItem::PreRuntime(
+                super::AURA_ENGINE_ID,
+                11u64.encode(),
+            )],
+        });
+
+        assert_eq!(
+            next_block(&mut runtime, 100, 10),
+            Err("Slot does not match the pre-runtime digest of the block")
+        );
+        assert_eq!(next_block(&mut runtime, 110, 11), Ok(()));
+    }
+
+    #[test]
+    fn authority_set_changes_at_the_next_session() {
+        let mut runtime = new_runtime();
+
+        assert_eq!(
+            runtime.aura.set_authorities(
+                RawOrigin::Signed("alice".to_string()),
+                vec!["charlie".to_string()]
+            ),
+            Err("Bad origin")
+        );
+        assert_eq!(
+            runtime.aura.set_authorities(RawOrigin::Root, vec![]),
+            Err("Authority set must not be empty")
+        );
+
+        // The first session ends with the second block.
+        assert_eq!(next_block(&mut runtime, 100, 10), Ok(()));
+        assert_eq!(
+            runtime
+                .aura
+                .set_authorities(RawOrigin::Root, vec!["charlie".to_string()]),
+            Ok(())
+        );
+        assert_eq!(runtime.aura.authorities.len(), 2);
+
+        assert_eq!(next_block(&mut runtime, 110, 11), Ok(()));
+        assert_eq!(runtime.aura.authorities, vec!["charlie".to_string()]);
+        assert_eq!(
+            runtime.system.digest().logs,
+            vec![DigestItem::Consensus(
+                super::AURA_ENGINE_ID,
+                vec!["charlie".to_string()].encode()
+            )]
+        );
+        assert_eq!(runtime.aura.slot_author(12), Some(&"charlie".to_string()));
+    }
+}
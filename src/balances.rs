@@ -1,44 +1,164 @@
-use num::traits::{CheckedAdd, CheckedSub, Zero};
+use num::traits::{Bounded, CheckedAdd, CheckedMul, CheckedSub, ToPrimitive, Zero};
 use std::collections::BTreeMap;
 
+use crate::support::{
+    codec::{Decode, Encode},
+    ensure_root, ensure_signed,
+    metadata::{FieldMetadata, PalletInfo, VariantMetadata},
+    DispatchResult, Hooks, PalletEvents,
+};
+
 pub trait Config: crate::system::Config {
-    type Balance: Zero + CheckedSub + CheckedAdd + Copy;
+    type Balance: Zero + Bounded + CheckedSub + CheckedAdd + CheckedMul + ToPrimitive + Ord + Copy;
+}
+
+// An identifier for a lock on the balance of an account, e.g. `*b"vesting "`.
+pub type LockIdentifier = [u8; 8];
+
+// The events of the balances pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<T: Config> {
+    // `amount` was transferred from one account to another.
+    Transfer {
+        from: T::AccountId,
+        to: T::AccountId,
+        amount: T::Balance,
+    },
+    // The balance of `who` was set to `amount` by `Root`.
+    BalanceSet {
+        who: T::AccountId,
+        amount: T::Balance,
+    },
+    // `amount` new tokens were minted into the balance of `who`.
+    Minted {
+        who: T::AccountId,
+        amount: T::Balance,
+    },
+}
+
+impl<T: Config> Encode for Event<T>
+where
+    T::Balance: Encode,
+{
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            Event::Transfer { from, to, amount } => {
+                dest.push(0);
+                from.encode_to(dest);
+                to.encode_to(dest);
+                amount.encode_to(dest);
+            }
+            Event::BalanceSet { who, amount } => {
+                dest.push(1);
+                who.encode_to(dest);
+                amount.encode_to(dest);
+            }
+            Event::Minted { who, amount } => {
+                dest.push(2);
+                who.encode_to(dest);
+                amount.encode_to(dest);
+            }
+        }
+    }
 }
 
 // This is the Balances Module.
 // It is a simple module which keeps track of how much balance each account has in this state
 // machine.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Pallet<T: Config> {
     // A simple storage mapping from accounts (`String`) to their balances (`u128`).
     pub balances: BTreeMap<T::AccountId, T::Balance>,
+    // The balance of each account which is reserved, e.g. as a deposit, and can not be spent.
+    pub reserved: BTreeMap<T::AccountId, T::Balance>,
+    // The locks on the balance of each account, e.g. by vesting. The free balance of an account
+    // can not go below the largest of its locks.
+    pub locks: BTreeMap<T::AccountId, BTreeMap<LockIdentifier, T::Balance>>,
+    // The events deposited by the calls of the pallet, until the runtime takes them. They are not
+    // part of the encoded state.
+    events: Vec<Event<T>>,
 }
 
 #[macros::call]
+#[errors(
+    "Bad origin",
+    "Balance not enough for the transfer",
+    "Balance is locked",
+    "Overflow to add balance"
+)]
 impl<T: Config> Pallet<T> {
     /// Transfer `amount` from one account to another.
     /// This function verifies that `from` has at least `amount` balance to transfer,
     /// and that no mathematical overflows occur.
+    /// A transfer to the caller itself is allowed: it leaves the balance unchanged, but is checked
+    /// and emits a `Transfer` event like any other transfer.
+    #[call_index(0)]
     pub fn transfer(
         &mut self,
-        caller: T::AccountId,
+        origin: T::RuntimeOrigin,
         to: T::AccountId,
         amount: T::Balance,
     ) -> Result<(), &'static str> {
-        let caller_balance = self.balance(&caller);
-        let to_balance = self.balance(&to);
-
-        let new_caller_balance = caller_balance
+        let caller = ensure_signed(origin)?;
+        let new_caller_balance = self
+            .balance(&caller)
             .checked_sub(&amount)
-            .ok_or("Balance not enough for the transfer");
+            .ok_or("Balance not enough for the transfer")?;
+        self.ensure_can_withdraw(&caller, new_caller_balance)?;
 
-        let to_new_balance = to_balance
-            .checked_add(&amount)
-            .ok_or("Overflow to add balance");
+        // A transfer to the caller itself leaves its balance unchanged.
+        if to != caller {
+            let to_new_balance = self
+                .balance(&to)
+                .checked_add(&amount)
+                .ok_or("Overflow to add balance")?;
+
+            self.balances.insert(caller.clone(), new_caller_balance);
+            self.balances.insert(to.clone(), to_new_balance);
+        }
+
+        self.events.push(Event::Transfer {
+            from: caller,
+            to,
+            amount,
+        });
+        Ok(())
+    }
+
+    /// Set the balance of an account to `amount`.
+    /// This is a privileged function which can only be called by `Root`, e.g. through `sudo`.
+    #[call_index(1)]
+    pub fn set_balance(
+        &mut self,
+        origin: T::RuntimeOrigin,
+        who: T::AccountId,
+        amount: T::Balance,
+    ) -> DispatchResult {
+        ensure_root(origin)?;
+
+        self.balances.insert(who.clone(), amount);
+        self.events.push(Event::BalanceSet { who, amount });
+        Ok(())
+    }
+
+    /// Mint `amount` new tokens into the balance of an account.
+    /// This is a privileged function which can only be called by `Root`, e.g. through `sudo`.
+    #[call_index(2)]
+    pub fn mint(
+        &mut self,
+        origin: T::RuntimeOrigin,
+        who: T::AccountId,
+        amount: T::Balance,
+    ) -> DispatchResult {
+        ensure_root(origin)?;
 
-        self.set_balance(&caller, new_caller_balance?);
-        self.set_balance(&to, to_new_balance?);
+        let new_balance = self
+            .balance(&who)
+            .checked_add(&amount)
+            .ok_or("Overflow to add balance")?;
 
+        self.balances.insert(who.clone(), new_balance);
+        self.events.push(Event::Minted { who, amount });
         Ok(())
     }
 }
@@ -47,20 +167,218 @@ impl<T: Config> Pallet<T> {
     pub fn new() -> Self {
         Self {
             balances: BTreeMap::new(),
+            reserved: BTreeMap::new(),
+            locks: BTreeMap::new(),
+            events: Vec::new(),
         }
     }
 
-    pub fn set_balance(&mut self, who: &T::AccountId, amount: T::Balance) {
-        self.balances.insert(who.clone(), amount);
-    }
-
     pub fn balance(&self, who: &T::AccountId) -> T::Balance {
         *self.balances.get(who).unwrap_or(&T::Balance::zero())
     }
+
+    pub fn reserved_balance(&self, who: &T::AccountId) -> T::Balance {
+        *self.reserved.get(who).unwrap_or(&T::Balance::zero())
+    }
+
+    // Move `amount` from the free balance of `who` to its reserved balance.
+    pub fn reserve(&mut self, who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+        let new_balance = self
+            .balance(who)
+            .checked_sub(&amount)
+            .ok_or("Balance not enough for the reserve")?;
+        self.ensure_can_withdraw(who, new_balance)?;
+        let new_reserved = self
+            .reserved_balance(who)
+            .checked_add(&amount)
+            .ok_or("Overflow to add balance")?;
+
+        self.balances.insert(who.clone(), new_balance);
+        self.reserved.insert(who.clone(), new_reserved);
+        Ok(())
+    }
+
+    // Move `amount` from the reserved balance of `who` back to its free balance.
+    pub fn unreserve(&mut self, who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+        let new_reserved = self
+            .reserved_balance(who)
+            .checked_sub(&amount)
+            .ok_or("Reserved balance not enough for the unreserve")?;
+        let new_balance = self
+            .balance(who)
+            .checked_add(&amount)
+            .ok_or("Overflow to add balance")?;
+
+        self.balances.insert(who.clone(), new_balance);
+        self.reserved.insert(who.clone(), new_reserved);
+        Ok(())
+    }
+
+    // Remove `amount` from the free balance of `who`, e.g. to pay a fee. The tokens are not given to
+    // anyone else.
+    pub fn withdraw(&mut self, who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+        let new_balance = self.can_withdraw(who, amount)?;
+
+        self.balances.insert(who.clone(), new_balance);
+        Ok(())
+    }
+
+    // Check that `amount` can be withdrawn from the free balance of `who`, and return the free
+    // balance it would leave.
+    pub fn can_withdraw(
+        &self,
+        who: &T::AccountId,
+        amount: T::Balance,
+    ) -> Result<T::Balance, &'static str> {
+        let new_balance = self
+            .balance(who)
+            .checked_sub(&amount)
+            .ok_or("Balance not enough for the withdraw")?;
+        self.ensure_can_withdraw(who, new_balance)?;
+
+        Ok(new_balance)
+    }
+
+    // Add `amount` to the free balance of `who`, e.g. to refund a fee.
+    pub fn deposit(&mut self, who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+        let new_balance = self
+            .balance(who)
+            .checked_add(&amount)
+            .ok_or("Overflow to add balance")?;
+
+        self.balances.insert(who.clone(), new_balance);
+        Ok(())
+    }
+
+    // Add `amount` to the free balance of `who`, up to the maximum balance. What is left over
+    // is burned.
+    pub fn deposit_saturating(&mut self, who: &T::AccountId, amount: T::Balance) {
+        let new_balance = self
+            .balance(who)
+            .checked_add(&amount)
+            .unwrap_or(T::Balance::max_value());
+
+        self.balances.insert(who.clone(), new_balance);
+    }
+
+    // The amount of the free balance of `who` which is locked, i.e. the largest of its locks.
+    pub fn locked_balance(&self, who: &T::AccountId) -> T::Balance {
+        self.locks
+            .get(who)
+            .and_then(|locks| locks.values().max().copied())
+            .unwrap_or(T::Balance::zero())
+    }
+
+    // Lock `amount` of the free balance of `who` under `id`, replacing any previous lock with the
+    // same `id`.
+    pub fn set_lock(&mut self, id: LockIdentifier, who: &T::AccountId, amount: T::Balance) {
+        self.locks
+            .entry(who.clone())
+            .or_default()
+            .insert(id, amount);
+    }
+
+    // Remove the lock `id` on the balance of `who`.
+    pub fn remove_lock(&mut self, id: LockIdentifier, who: &T::AccountId) {
+        if let Some(locks) = self.locks.get_mut(who) {
+            locks.remove(&id);
+            if locks.is_empty() {
+                self.locks.remove(who);
+            }
+        }
+    }
+
+    // Check that the free balance of `who` can become `new_balance` without going below its
+    // locks.
+    fn ensure_can_withdraw(&self, who: &T::AccountId, new_balance: T::Balance) -> DispatchResult {
+        if new_balance < self.locked_balance(who) {
+            return Err("Balance is locked");
+        }
+        Ok(())
+    }
+}
+
+impl<T: Config> Encode for Pallet<T>
+where
+    T::Balance: Encode,
+{
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.balances.encode_to(dest);
+        self.reserved.encode_to(dest);
+        self.locks.encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for Pallet<T>
+where
+    T::AccountId: Decode,
+    T::Balance: Decode,
+{
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        Ok(Self {
+            balances: Decode::decode_from(input)?,
+            reserved: Decode::decode_from(input)?,
+            locks: Decode::decode_from(input)?,
+            events: Vec::new(),
+        })
+    }
+}
+
+impl<T: Config> PalletEvents for Pallet<T> {
+    type Event = Event<T>;
+
+    fn take_events(&mut self) -> Vec<Event<T>> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+impl<T: Config> PalletInfo for Pallet<T> {
+    fn events() -> Vec<VariantMetadata> {
+        vec![
+            VariantMetadata {
+                name: "Transfer",
+                index: 0,
+                fields: vec![
+                    FieldMetadata::new::<T::AccountId>("from"),
+                    FieldMetadata::new::<T::AccountId>("to"),
+                    FieldMetadata::new::<T::Balance>("amount"),
+                ],
+            },
+            VariantMetadata {
+                name: "BalanceSet",
+                index: 1,
+                fields: vec![
+                    FieldMetadata::new::<T::AccountId>("who"),
+                    FieldMetadata::new::<T::Balance>("amount"),
+                ],
+            },
+            VariantMetadata {
+                name: "Minted",
+                index: 2,
+                fields: vec![
+                    FieldMetadata::new::<T::AccountId>("who"),
+                    FieldMetadata::new::<T::Balance>("amount"),
+                ],
+            },
+        ]
+    }
+
+    fn storage() -> Vec<FieldMetadata> {
+        vec![
+            FieldMetadata::new::<BTreeMap<T::AccountId, T::Balance>>("balances"),
+            FieldMetadata::new::<BTreeMap<T::AccountId, T::Balance>>("reserved"),
+            FieldMetadata::new::<BTreeMap<T::AccountId, BTreeMap<LockIdentifier, T::Balance>>>(
+                "locks",
+            ),
+        ]
+    }
 }
 
+impl<T: Config> Hooks<T, T::BlockNumber> for Pallet<T> {}
+
 #[cfg(test)]
 mod tests {
+    use crate::support::{PalletEvents, RawOrigin};
 
     struct TestConfig;
 
@@ -70,8 +388,10 @@ mod tests {
 
     impl crate::system::Config for TestConfig {
         type AccountId = String;
+        type RuntimeOrigin = RawOrigin<String>;
         type BlockNumber = u32;
         type Nonce = u32;
+        type RuntimeEvent = crate::system::Event;
     }
 
     #[test]
@@ -81,7 +401,7 @@ mod tests {
         assert_eq!(ballances.balance(&String::from("alice")), 0);
         assert_eq!(ballances.balance(&String::from("bob")), 0);
 
-        ballances.set_balance(&String::from("alice"), 100);
+        let _ = ballances.set_balance(RawOrigin::Root, String::from("alice"), 100);
 
         assert_eq!(ballances.balance(&String::from("alice")), 100);
         assert_eq!(ballances.balance(&String::from("bob")), 0);
@@ -91,7 +411,11 @@ mod tests {
     fn transfer_without_balance() {
         let mut ballances = super::Pallet::<TestConfig>::new();
 
-        let result = ballances.transfer(String::from("alice"), String::from("bob"), 100);
+        let result = ballances.transfer(
+            RawOrigin::Signed(String::from("alice")),
+            String::from("bob"),
+            100,
+        );
 
         assert!(result.is_err_and(|e| e == "Balance not enough for the transfer"));
     }
@@ -99,17 +423,162 @@ mod tests {
     #[test]
     fn transfer_balance() {
         let mut ballances = super::Pallet::<TestConfig>::new();
-        let transfer_result = ballances.transfer(String::from("alice"), String::from("bob"), 100);
+        let transfer_result = ballances.transfer(
+            RawOrigin::Signed(String::from("alice")),
+            String::from("bob"),
+            100,
+        );
         assert!(transfer_result.is_err_and(|e| e == "Balance not enough for the transfer"));
 
-        ballances.set_balance(&String::from("alice"), 100);
+        let _ = ballances.set_balance(RawOrigin::Root, String::from("alice"), 100);
         assert_eq!(ballances.balance(&String::from("alice")), 100);
         assert_eq!(ballances.balance(&String::from("bob")), 0);
 
-        let transfer_result =
-            ballances.transfer(String::from("alice"), String::from("bob").clone(), 100);
+        let transfer_result = ballances.transfer(
+            RawOrigin::Signed(String::from("alice")),
+            String::from("bob").clone(),
+            100,
+        );
         assert!(transfer_result.is_ok());
         assert_eq!(ballances.balance(&String::from("alice")), 0);
         assert_eq!(ballances.balance(&String::from("bob")), 100);
     }
+
+    #[test]
+    fn transfer_to_self() {
+        let mut ballances = super::Pallet::<TestConfig>::new();
+        let alice = String::from("alice");
+        let _ = ballances.set_balance(RawOrigin::Root, alice.clone(), 100);
+
+        let transfer_result =
+            ballances.transfer(RawOrigin::Signed(alice.clone()), alice.clone(), 100);
+        assert!(transfer_result.is_ok());
+        assert_eq!(ballances.balance(&alice), 100);
+        // The transfer is reported like any other.
+        let events = ballances.take_events();
+        assert!(matches!(
+            events.last(),
+            Some(super::Event::Transfer { from, to, amount: 100 }) if *from == alice && *to == alice
+        ));
+
+        // The caller still needs the balance it transfers.
+        let transfer_result =
+            ballances.transfer(RawOrigin::Signed(alice.clone()), alice.clone(), 101);
+        assert!(transfer_result.is_err_and(|e| e == "Balance not enough for the transfer"));
+        assert_eq!(ballances.balance(&alice), 100);
+    }
+
+    #[test]
+    fn set_balance_requires_root() {
+        let mut ballances = super::Pallet::<TestConfig>::new();
+
+        let result = ballances.set_balance(
+            RawOrigin::Signed(String::from("alice")),
+            String::from("alice"),
+            100,
+        );
+
+        assert_eq!(result, Err("Bad origin"));
+        assert_eq!(ballances.balance(&String::from("alice")), 0);
+    }
+
+    #[test]
+    fn mint() {
+        let mut ballances = super::Pallet::<TestConfig>::new();
+        let _ = ballances.set_balance(RawOrigin::Root, String::from("alice"), 100);
+
+        let result = ballances.mint(RawOrigin::Root, String::from("alice"), 50);
+        assert_eq!(result, Ok(()));
+        assert_eq!(ballances.balance(&String::from("alice")), 150);
+
+        let result = ballances.mint(RawOrigin::Root, String::from("alice"), u32::MAX);
+        assert_eq!(result, Err("Overflow to add balance"));
+
+        let result = ballances.mint(
+            RawOrigin::Signed(String::from("bob")),
+            String::from("bob"),
+            50,
+        );
+        assert_eq!(result, Err("Bad origin"));
+        assert_eq!(ballances.balance(&String::from("bob")), 0);
+    }
+
+    #[test]
+    fn reserve_and_unreserve() {
+        let mut ballances = super::Pallet::<TestConfig>::new();
+        let alice = String::from("alice");
+        let _ = ballances.set_balance(RawOrigin::Root, alice.clone(), 100);
+
+        assert_eq!(ballances.reserve(&alice, 30), Ok(()));
+        assert_eq!(ballances.balance(&alice), 70);
+        assert_eq!(ballances.reserved_balance(&alice), 30);
+
+        assert_eq!(
+            ballances.reserve(&alice, 80),
+            Err("Balance not enough for the reserve")
+        );
+        assert_eq!(
+            ballances.unreserve(&alice, 40),
+            Err("Reserved balance not enough for the unreserve")
+        );
+
+        assert_eq!(ballances.unreserve(&alice, 30), Ok(()));
+        assert_eq!(ballances.balance(&alice), 100);
+        assert_eq!(ballances.reserved_balance(&alice), 0);
+    }
+
+    #[test]
+    fn locks() {
+        let mut ballances = super::Pallet::<TestConfig>::new();
+        let alice = String::from("alice");
+        let _ = ballances.set_balance(RawOrigin::Root, alice.clone(), 100);
+
+        ballances.set_lock(*b"lock_one", &alice, 50);
+        ballances.set_lock(*b"lock_two", &alice, 80);
+        assert_eq!(ballances.locked_balance(&alice), 80);
+
+        let result = ballances.transfer(RawOrigin::Signed(alice.clone()), String::from("bob"), 30);
+        assert_eq!(result, Err("Balance is locked"));
+        assert_eq!(ballances.reserve(&alice, 30), Err("Balance is locked"));
+
+        ballances.remove_lock(*b"lock_two", &alice);
+        assert_eq!(ballances.locked_balance(&alice), 50);
+
+        let result = ballances.transfer(RawOrigin::Signed(alice.clone()), String::from("bob"), 30);
+        assert_eq!(result, Ok(()));
+        assert_eq!(ballances.balance(&alice), 70);
+
+        ballances.remove_lock(*b"lock_one", &alice);
+        assert_eq!(ballances.locked_balance(&alice), 0);
+        assert!(ballances.locks.is_empty());
+    }
+
+    #[test]
+    fn withdraw_and_deposit() {
+        let mut ballances = super::Pallet::<TestConfig>::new();
+        let alice = String::from("alice");
+        let _ = ballances.set_balance(RawOrigin::Root, alice.clone(), 100);
+
+        assert_eq!(ballances.withdraw(&alice, 30), Ok(()));
+        assert_eq!(ballances.balance(&alice), 70);
+        assert_eq!(
+            ballances.withdraw(&alice, 80),
+            Err("Balance not enough for the withdraw")
+        );
+
+        ballances.set_lock(*b"lock_one", &alice, 50);
+        assert_eq!(ballances.withdraw(&alice, 30), Err("Balance is locked"));
+
+        assert_eq!(ballances.deposit(&alice, 30), Ok(()));
+        assert_eq!(ballances.balance(&alice), 100);
+        assert_eq!(
+            ballances.deposit(&alice, u32::MAX),
+            Err("Overflow to add balance")
+        );
+
+        ballances.deposit_saturating(&alice, 30);
+        assert_eq!(ballances.balance(&alice), 130);
+        ballances.deposit_saturating(&alice, u32::MAX);
+        assert_eq!(ballances.balance(&alice), u32::MAX);
+    }
 }
@@ -0,0 +1,215 @@
+use crate::{
+    support::{self, hashing::Hash, Digest, DispatchResult, Weight},
+    types, Runtime,
+};
+
+/// This is the Block Builder.
+/// It authors the next block on top of the current state of the runtime, by applying extrinsics
+/// one at a time on its own copy of the state.
+///
+/// An extrinsic is only included if it is valid and the block stays within the limits of the
+/// system pallet. A signed extrinsic whose call fails is still included, since its fee was paid.
+pub struct BlockBuilder {
+    /// The state the extrinsics are applied on, which starts as a copy of the current state.
+    runtime: Runtime,
+    /// The hash of the block the new block is built on.
+    parent_hash: Hash,
+    /// The extrinsics included in the block so far.
+    extrinsics: Vec<types::Extrinsic>,
+    /// The weight the block consumed before its extrinsics, in the `on_initialize` hooks of the
+    /// pallets.
+    initial_weight: Weight,
+}
+
+impl BlockBuilder {
+    /// Start building the block after the current block of `runtime`, whose digest starts with
+    /// the pre-runtime items of `digest`, e.g. the slot of the block for the consensus engine.
+    pub fn new(runtime: &Runtime, digest: Digest) -> Self {
+        let mut runtime = runtime.clone();
+        let parent_hash = runtime.system.block_hash();
+        runtime.initialize_block(&digest);
+        let initial_weight = runtime.system.block_weight();
+        Self {
+            runtime,
+            parent_hash,
+            extrinsics: Vec::new(),
+            initial_weight,
+        }
+    }
+
+    /// Apply `extrinsic`, and include it in the block.
+    /// This function will return an error, and leave the extrinsic out of the block, if the
+    /// extrinsic is invalid, or if it does not fit in the block. Inherents must be pushed first,
+    /// and are left out if their call fails.
+    pub fn push(&mut self, extrinsic: types::Extrinsic) -> DispatchResult {
+        let is_inherent = Runtime::is_inherent(&extrinsic.call);
+        if is_inherent && self.extrinsics.iter().any(|ext| ext.signer.is_some()) {
+            return Err("inherents must be at the start of the block");
+        }
+
+        self.extrinsics.push(extrinsic.clone());
+        if let Err(e) = Runtime::check_block_limits(self.initial_weight, &self.extrinsics) {
+            self.extrinsics.pop();
+            return Err(e);
+        }
+
+        // Applying an extrinsic which is left out must not change the state of the block. An
+        // invalid extrinsic never does, but an inherent whose call failed is still noted by the
+        // runtime, so inherents are applied on a copy which replaces the state once included.
+        let res = if is_inherent {
+            let mut runtime = self.runtime.clone();
+            let res = runtime
+                .apply_extrinsic(extrinsic)
+                .and_then(|res| res.map(|_| ()));
+            if res.is_ok() {
+                self.runtime = runtime;
+            }
+            res
+        } else {
+            self.runtime.apply_extrinsic(extrinsic).map(|_| ())
+        };
+        if res.is_err() {
+            self.extrinsics.pop();
+        }
+        res
+    }
+
+    /// Finish the block, with the header committing to its parent, the state after the included
+    /// extrinsics and the hooks of the pallets, the extrinsics themselves, and the digest produced
+    /// by the runtime. The block is not sealed yet.
+    pub fn finalize(mut self) -> types::Block {
+        self.runtime.finalize_block();
+        let header = support::Header {
+            block_number: self.runtime.system.block_number(),
+            parent_hash: self.parent_hash,
+            state_root: self.runtime.state_root(),
+            extrinsics_root: support::extrinsics_root(&self.extrinsics),
+            digest: self.runtime.system.digest().clone(),
+        };
+        types::Block {
+            header,
+            extrinsics: self.extrinsics,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BlockBuilder;
+    use crate::{
+        balances, proof_of_existence,
+        support::{Digest, RawOrigin},
+        timestamp_inherent, DevAccount, Runtime, RuntimeCall,
+    };
+
+    fn new_runtime(alice: &DevAccount) -> Runtime {
+        let mut runtime = Runtime::new();
+        runtime
+            .balances
+            .set_balance(RawOrigin::Root.into(), alice.id(), 1_000_000)
+            .unwrap();
+        runtime
+    }
+
+    fn transfer(from: &mut DevAccount, to: &DevAccount, amount: u128) -> crate::types::Extrinsic {
+        from.sign(RuntimeCall::balances(balances::Call::transfer {
+            to: to.id(),
+            amount,
+        }))
+    }
+
+    #[test]
+    fn build_block_which_executes() {
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+        let mut runtime = new_runtime(&alice);
+        let mut builder = BlockBuilder::new(&runtime, Digest::default());
+
+        assert_eq!(builder.push(timestamp_inherent(0, 1)), Ok(()));
+        assert_eq!(builder.push(transfer(&mut alice, &bob, 10)), Ok(()));
+        // A call which fails is included, since the signer paid for it.
+        assert_eq!(builder.push(transfer(&mut alice, &bob, 2_000_000)), Ok(()));
+        // Building the block did not change the state of the runtime.
+        assert_eq!(runtime.balances.balance(&bob.id()), 0);
+
+        let block = builder.finalize();
+        assert_eq!(block.header.block_number, 1);
+        assert_eq!(block.extrinsics.len(), 3);
+
+        assert_eq!(runtime.execute_block(block), Ok(()));
+        assert_eq!(runtime.balances.balance(&bob.id()), 10);
+        assert_eq!(runtime.system.get_nonce(&alice.id()), 2);
+    }
+
+    #[test]
+    fn push_leaves_out_invalid_extrinsics() {
+        let (mut alice, mut bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+        let runtime = new_runtime(&alice);
+        let mut builder = BlockBuilder::new(&runtime, Digest::default());
+        assert_eq!(builder.push(timestamp_inherent(0, 1)), Ok(()));
+
+        // Bob can not pay the fee.
+        let res = builder.push(transfer(&mut bob, &alice, 1));
+        assert_eq!(res, Err("Inability to pay some fees"));
+
+        alice.next_nonce = 1;
+        let res = builder.push(transfer(&mut alice, &bob, 1));
+        assert_eq!(res, Err("invalid transaction nonce"));
+
+        alice.next_nonce = 0;
+        let res = builder.push(alice.sign(RuntimeCall::proof_of_existence(
+            proof_of_existence::Call::create_claim {
+                claim: "doc".to_string(),
+            },
+        )));
+        assert_eq!(res, Ok(()));
+
+        let res = builder.push(timestamp_inherent(0, 1));
+        assert_eq!(res, Err("inherents must be at the start of the block"));
+
+        assert_eq!(builder.finalize().extrinsics.len(), 2);
+    }
+
+    #[test]
+    fn build_block_after_failed_inherent() {
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+        let mut runtime = new_runtime(&alice);
+        let mut builder = BlockBuilder::new(&runtime, Digest::default());
+
+        assert_eq!(builder.push(timestamp_inherent(0, 1)), Ok(()));
+        // The timestamp can only be set once per block.
+        let res = builder.push(timestamp_inherent(0, 1));
+        assert_eq!(res, Err("Timestamp must be updated only once in the block"));
+        assert_eq!(builder.push(transfer(&mut alice, &bob, 10)), Ok(()));
+
+        // The inherent which was left out did not change the state of the block.
+        let mut expected = runtime.clone();
+        expected.initialize_block(&Digest::default());
+        for extrinsic in builder.extrinsics.clone() {
+            assert!(expected.apply_extrinsic(extrinsic).is_ok());
+        }
+        let system = &builder.runtime.system;
+        assert_eq!(system.events().len(), expected.system.events().len());
+        assert_eq!(system.block_weight(), expected.system.block_weight());
+
+        let block = builder.finalize();
+        assert_eq!(block.extrinsics.len(), 2);
+        assert_eq!(runtime.execute_block(block), Ok(()));
+        assert_eq!(runtime.balances.balance(&bob.id()), 10);
+    }
+
+    #[test]
+    fn push_leaves_out_extrinsics_over_the_limits() {
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+        let runtime = new_runtime(&alice);
+        let mut builder = BlockBuilder::new(&runtime, Digest::default());
+        assert_eq!(builder.push(timestamp_inherent(0, 1)), Ok(()));
+
+        for _ in 0..49 {
+            assert_eq!(builder.push(transfer(&mut alice, &bob, 1)), Ok(()));
+        }
+        let res = builder.push(transfer(&mut alice, &bob, 1));
+        assert_eq!(res, Err("block has too many extrinsics"));
+
+        assert_eq!(builder.finalize().extrinsics.len(), 50);
+    }
+}
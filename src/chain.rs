@@ -0,0 +1,536 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    support::{self, hashing::Hash, Digest, DigestItem},
+    types, Runtime,
+};
+
+/// A block known by the chain, with the state of the runtime after executing it, unless it was
+/// pruned.
+struct ChainEntry {
+    block: types::Block,
+    state: Option<Runtime>,
+}
+
+/// What changed in the chain after importing a block.
+#[derive(Clone)]
+pub struct ImportedBlock {
+    /// The hash of the imported block.
+    pub hash: Hash,
+    /// The blocks which left the best chain because of a re-org, from the previous best block
+    /// down to the common ancestor with the new best chain. The blocks themselves are returned,
+    /// since they are already pruned if the import also finalized a block.
+    pub retracted: Vec<types::Block>,
+    /// The blocks which joined the best chain, from the common ancestor up to the new best block.
+    /// This is empty if the imported block is not the new best block.
+    pub enacted: Vec<Hash>,
+}
+
+/// How the chain finalizes blocks and prunes their state.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChainConfig {
+    /// Blocks this deep under the best block are finalized when a block is imported. With `None`,
+    /// blocks are only finalized by `Chain::finalize`.
+    pub finality_depth: Option<types::BlockNumber>,
+    /// The number of finalized blocks whose state is kept, including the last finalized block. The
+    /// state of older blocks is pruned, but the blocks themselves are kept. With `None`, the state
+    /// of every block is kept.
+    pub state_pruning: Option<types::BlockNumber>,
+}
+
+/// This is the Chain Store.
+/// It keeps the tree of the blocks built on the genesis, linked by their parent hash, and the state
+/// of the runtime after each of them, so a block can be executed on top of any known block.
+///
+/// The best chain is the longest one: a block becomes the best block when its number is higher
+/// than the number of the current best block. On a tie, the block which was imported first stays
+/// the best block.
+///
+/// A finalized block can never leave the best chain. Once a block is finalized, the forks which do
+/// not include it are pruned, and so is the state of the finalized blocks outside of the
+/// `state_pruning` window.
+pub struct Chain {
+    /// The known blocks, by hash.
+    blocks: BTreeMap<Hash, ChainEntry>,
+    /// The hash of the best block, whose state is the canonical state.
+    best: Hash,
+    /// The hash of the last finalized block.
+    finalized: Hash,
+    config: ChainConfig,
+}
+
+impl Chain {
+    /// Create a new chain from the `genesis` state of the runtime. The genesis block is finalized.
+    pub fn new(mut genesis: Runtime, config: ChainConfig) -> Self {
+        let extrinsics = Vec::new();
+        let header = support::Header {
+            block_number: genesis.system.block_number(),
+            parent_hash: Hash::default(),
+            state_root: genesis.state_root(),
+            extrinsics_root: support::extrinsics_root(&extrinsics),
+            // The genesis sets up the runtime environment the first block is executed in.
+            digest: Digest {
+                logs: vec![DigestItem::RuntimeEnvironmentUpdated],
+            },
+        };
+        let hash = header.hash();
+        // The genesis block is the parent of the first block.
+        genesis.system.set_block_hash(hash);
+
+        let block = types::Block { header, extrinsics };
+        let entry = ChainEntry {
+            block,
+            state: Some(genesis),
+        };
+        Self {
+            blocks: BTreeMap::from([(hash, entry)]),
+            best: hash,
+            finalized: hash,
+            config,
+        }
+    }
+
+    /// Get the hash of the best block.
+    pub fn best_hash(&self) -> Hash {
+        self.best
+    }
+
+    /// Get the hash of the last finalized block.
+    pub fn finalized_hash(&self) -> Hash {
+        self.finalized
+    }
+
+    /// Get the state after the best block, which is the canonical state.
+    pub fn best_state(&self) -> &Runtime {
+        self.state(&self.best_hash())
+            .expect("the state of the best block is not pruned")
+    }
+
+    /// Get the block with the hash `hash`, if it is known.
+    pub fn block(&self, hash: &Hash) -> Option<&types::Block> {
+        self.blocks.get(hash).map(|entry| &entry.block)
+    }
+
+    /// Get the state after the block with the hash `hash`, if it is known and its state was not
+    /// pruned.
+    pub fn state(&self, hash: &Hash) -> Option<&Runtime> {
+        self.blocks.get(hash).and_then(|entry| entry.state.as_ref())
+    }
+
+    /// Execute `block` on the state of its parent, and add it to the chain.
+    /// This function will return an error if the block is already known, if its parent is not
+    /// known or not a descendant of the last finalized block, or if it is not valid on the state
+    /// of its parent.
+    ///
+    /// The block becomes the best block if it makes the longest chain, which re-orgs the chain when
+    /// it is not built on the current best block. Then, with a `finality_depth`, the block that
+    /// deep under the best block is finalized.
+    pub fn import_block(&mut self, block: types::Block) -> Result<ImportedBlock, &'static str> {
+        let hash = block.header.hash();
+        if self.blocks.contains_key(&hash) {
+            return Err("Block is already in the chain");
+        }
+        let parent_hash = block.header.parent_hash;
+        if !self.blocks.contains_key(&parent_hash) {
+            return Err("Block parent is unknown");
+        }
+        if !self.is_finalized_or_descendant(parent_hash) {
+            return Err("Block is not a descendant of the finalized block");
+        }
+
+        let mut state = self
+            .state(&parent_hash)
+            .ok_or("Block parent state is pruned")?
+            .clone();
+        state.execute_block(block.clone())?;
+
+        let is_new_best = block.header.block_number > self.header(&self.best).block_number;
+        let state = Some(state);
+        self.blocks.insert(hash, ChainEntry { block, state });
+
+        let (retracted, enacted) = if is_new_best {
+            let route = self.route(self.best, hash);
+            self.best = hash;
+            route
+        } else {
+            (Vec::new(), Vec::new())
+        };
+        // The retracted blocks are pruned if a block is finalized below.
+        let retracted = retracted
+            .iter()
+            .map(|hash| self.blocks[hash].block.clone())
+            .collect();
+
+        if let Some(depth) = self.config.finality_depth {
+            let best_number = self.header(&self.best).block_number;
+            if let Some(number) = best_number.checked_sub(depth) {
+                if number > self.header(&self.finalized).block_number {
+                    self.finalize(self.ancestor_at(self.best, number))?;
+                }
+            }
+        }
+
+        Ok(ImportedBlock {
+            hash,
+            retracted,
+            enacted,
+        })
+    }
+
+    /// Finalize the block with the hash `hash`, and its ancestors.
+    /// This function will return an error if the block is unknown, is not in the best chain, or is
+    /// already finalized.
+    ///
+    /// The blocks which are not the finalized block, its ancestors or its descendants are pruned,
+    /// and so is the state of the finalized blocks outside of the `state_pruning` window.
+    pub fn finalize(&mut self, hash: Hash) -> Result<(), &'static str> {
+        let number = self
+            .block(&hash)
+            .ok_or("Block is unknown")?
+            .header
+            .block_number;
+        if number <= self.header(&self.finalized).block_number {
+            return Err("Block is already finalized");
+        }
+        if number > self.header(&self.best).block_number
+            || self.ancestor_at(self.best, number) != hash
+        {
+            return Err("Block is not in the best chain");
+        }
+
+        self.finalized = hash;
+        self.prune();
+        Ok(())
+    }
+
+    /// Replace the chain with `block` and the `state` after it, e.g. a snapshot of the state of a
+    /// peer at one of its finalized blocks. The block becomes both the best and the finalized
+    /// block, and the blocks under it are not known anymore.
+    /// This function will return an error if the block is not higher than the last finalized
+    /// block. The state must have been checked against the state root of the block.
+    pub fn import_state(
+        &mut self,
+        block: types::Block,
+        state: Runtime,
+    ) -> Result<(), &'static str> {
+        if block.header.block_number <= self.header(&self.finalized).block_number {
+            return Err("Block is already finalized");
+        }
+
+        let hash = block.header.hash();
+        let state = Some(state);
+        self.blocks = BTreeMap::from([(hash, ChainEntry { block, state })]);
+        self.best = hash;
+        self.finalized = hash;
+        Ok(())
+    }
+
+    /// Get the header of the known block with the hash `hash`.
+    fn header(&self, hash: &Hash) -> &types::Header {
+        &self.blocks[hash].block.header
+    }
+
+    /// The ancestor with the number `number` of the known block with the hash `hash`, which can be
+    /// the block itself. The number must not be higher than the number of the block.
+    fn ancestor_at(&self, mut hash: Hash, number: types::BlockNumber) -> Hash {
+        while self.header(&hash).block_number > number {
+            hash = self.header(&hash).parent_hash;
+        }
+        hash
+    }
+
+    /// Whether the known block with the hash `hash` is the last finalized block or one of its
+    /// descendants.
+    fn is_finalized_or_descendant(&self, hash: Hash) -> bool {
+        let finalized_number = self.header(&self.finalized).block_number;
+        self.header(&hash).block_number >= finalized_number
+            && self.ancestor_at(hash, finalized_number) == self.finalized
+    }
+
+    /// Remove the blocks which can not be in the best chain anymore, since they do not include
+    /// the last finalized block, and the state of the finalized blocks outside of the
+    /// `state_pruning` window.
+    fn prune(&mut self) {
+        let finalized_number = self.header(&self.finalized).block_number;
+        let pruned: Vec<Hash> = self
+            .blocks
+            .iter()
+            .filter(|(hash, entry)| {
+                let number = entry.block.header.block_number;
+                if number < finalized_number {
+                    self.ancestor_at(self.finalized, number) != **hash
+                } else {
+                    !self.is_finalized_or_descendant(**hash)
+                }
+            })
+            .map(|(hash, _)| *hash)
+            .collect();
+        for hash in pruned {
+            self.blocks.remove(&hash);
+        }
+
+        if let Some(window) = self.config.state_pruning {
+            // The state of the last finalized block is always kept, to build on it.
+            let window = window.max(1);
+            for entry in self.blocks.values_mut() {
+                if entry.block.header.block_number + window <= finalized_number {
+                    entry.state = None;
+                }
+            }
+        }
+    }
+
+    /// The route from the block `from` to the block `to` through their common ancestor: the blocks
+    /// from `from` down to the ancestor, and the blocks from the ancestor up to `to`. Both blocks
+    /// must be known.
+    fn route(&self, mut from: Hash, mut to: Hash) -> (Vec<Hash>, Vec<Hash>) {
+        let mut retracted = Vec::new();
+        let mut enacted = Vec::new();
+
+        while from != to {
+            // Walk back from the highest block, or from both when they are at the same height.
+            let from_number = self.header(&from).block_number;
+            let to_number = self.header(&to).block_number;
+            if from_number >= to_number {
+                retracted.push(from);
+                from = self.header(&from).parent_hash;
+            }
+            if to_number >= from_number {
+                enacted.push(to);
+                to = self.header(&to).parent_hash;
+            }
+        }
+
+        enacted.reverse();
+        (retracted, enacted)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Chain, ChainConfig};
+    use crate::{
+        balances, block_builder::BlockBuilder, support::hashing::Hash, support::Digest,
+        support::RawOrigin, timestamp_inherent, DevAccount, Runtime, RuntimeCall,
+    };
+
+    fn new_chain(alice: &DevAccount, config: ChainConfig) -> Chain {
+        let mut genesis = Runtime::new();
+        genesis
+            .balances
+            .set_balance(RawOrigin::Root.into(), alice.id(), 1_000_000)
+            .unwrap();
+        Chain::new(genesis, config)
+    }
+
+    // Build a block on top of the block `parent`, with a transfer from alice to bob of `amount`.
+    fn build_block(
+        chain: &Chain,
+        parent: &Hash,
+        alice: &mut DevAccount,
+        bob: &DevAccount,
+        amount: u128,
+    ) -> crate::types::Block {
+        let state = chain.state(parent).unwrap();
+        let block_number = state.system.block_number() + 1;
+        alice.next_nonce = state.system.get_nonce(&alice.id());
+
+        let mut builder = BlockBuilder::new(state, Digest::default());
+        builder.push(timestamp_inherent(0, block_number)).unwrap();
+        let transfer = alice.sign(RuntimeCall::balances(balances::Call::transfer {
+            to: bob.id(),
+            amount,
+        }));
+        builder.push(transfer).unwrap();
+        builder.finalize()
+    }
+
+    #[test]
+    fn import_blocks_on_the_best_chain() {
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+        let mut chain = new_chain(&alice, ChainConfig::default());
+        let genesis = chain.best_hash();
+
+        let block_1 = build_block(&chain, &genesis, &mut alice, &bob, 10);
+        let imported = chain.import_block(block_1.clone()).unwrap();
+        assert!(imported.retracted.is_empty());
+        assert_eq!(imported.enacted, vec![imported.hash]);
+        assert_eq!(chain.best_hash(), imported.hash);
+        assert_eq!(chain.best_state().balances.balance(&bob.id()), 10);
+        // The state of the genesis is kept.
+        assert_eq!(
+            chain.state(&genesis).unwrap().balances.balance(&bob.id()),
+            0
+        );
+
+        assert_eq!(
+            chain.import_block(block_1).map(|imported| imported.hash),
+            Err("Block is already in the chain")
+        );
+    }
+
+    #[test]
+    fn import_block_return_unknown_parent() {
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+        let mut chain = new_chain(&alice, ChainConfig::default());
+        let genesis = chain.best_hash();
+
+        let mut block = build_block(&chain, &genesis, &mut alice, &bob, 10);
+        block.header.parent_hash = [1; 32];
+        assert_eq!(
+            chain.import_block(block).map(|imported| imported.hash),
+            Err("Block parent is unknown")
+        );
+    }
+
+    #[test]
+    fn longest_fork_becomes_the_best_chain() {
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+        let mut chain = new_chain(&alice, ChainConfig::default());
+        let genesis = chain.best_hash();
+
+        let a1 = chain
+            .import_block(build_block(&chain, &genesis, &mut alice, &bob, 10))
+            .unwrap()
+            .hash;
+
+        // A fork of the same length does not replace the best chain.
+        let b1 = chain
+            .import_block(build_block(&chain, &genesis, &mut alice, &bob, 20))
+            .unwrap();
+        assert!(b1.enacted.is_empty());
+        assert_eq!(chain.best_hash(), a1);
+        assert_eq!(chain.best_state().balances.balance(&bob.id()), 10);
+
+        // Once the fork is longer, the chain re-orgs to it.
+        let b2 = chain
+            .import_block(build_block(&chain, &b1.hash, &mut alice, &bob, 30))
+            .unwrap();
+        let retracted: Vec<_> = b2.retracted.iter().map(|b| b.header.hash()).collect();
+        assert_eq!(retracted, vec![a1]);
+        assert_eq!(b2.enacted, vec![b1.hash, b2.hash]);
+        assert_eq!(chain.best_hash(), b2.hash);
+        assert_eq!(chain.best_state().balances.balance(&bob.id()), 50);
+        assert!(chain.block(&a1).is_some());
+    }
+
+    #[test]
+    fn reorg_which_finalizes_keeps_retracted_extrinsics() {
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+        let config = ChainConfig {
+            finality_depth: Some(1),
+            state_pruning: None,
+        };
+        let mut chain = new_chain(&alice, config);
+        let genesis = chain.best_hash();
+
+        let a1 = chain
+            .import_block(build_block(&chain, &genesis, &mut alice, &bob, 10))
+            .unwrap()
+            .hash;
+        let b1 = chain
+            .import_block(build_block(&chain, &genesis, &mut alice, &bob, 20))
+            .unwrap()
+            .hash;
+
+        // The re-org to the fork finalizes b1, which prunes a1.
+        let b2 = chain
+            .import_block(build_block(&chain, &b1, &mut alice, &bob, 30))
+            .unwrap();
+        assert_eq!(chain.finalized_hash(), b1);
+        assert!(chain.block(&a1).is_none());
+        let retracted: Vec<_> = b2.retracted.iter().map(|b| b.header.hash()).collect();
+        assert_eq!(retracted, vec![a1]);
+    }
+
+    #[test]
+    fn finalize_prunes_forks() {
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+        let mut chain = new_chain(&alice, ChainConfig::default());
+        let genesis = chain.best_hash();
+
+        let mut import = |chain: &mut Chain, parent: Hash, amount| {
+            let block = build_block(chain, &parent, &mut alice, &bob, amount);
+            chain.import_block(block).unwrap().hash
+        };
+        let a1 = import(&mut chain, genesis, 10);
+        let a2 = import(&mut chain, a1, 10);
+        let b1 = import(&mut chain, genesis, 20);
+        let c2 = import(&mut chain, a1, 30);
+
+        assert_eq!(chain.finalize(b1), Err("Block is not in the best chain"));
+        assert_eq!(chain.finalize(a1), Ok(()));
+        assert_eq!(chain.finalized_hash(), a1);
+        assert_eq!(chain.finalize(a1), Err("Block is already finalized"));
+        assert_eq!(chain.finalize(genesis), Err("Block is already finalized"));
+
+        // The fork from the genesis is pruned, while the fork from the finalized block is kept.
+        assert!(chain.block(&b1).is_none());
+        assert!(chain.block(&a2).is_some());
+        assert!(chain.block(&c2).is_some());
+
+        // A block can not be built on a block before the finalized block anymore.
+        let mut block = build_block(&chain, &a1, &mut alice, &bob, 40);
+        block.header.parent_hash = genesis;
+        assert_eq!(
+            chain.import_block(block).map(|imported| imported.hash),
+            Err("Block is not a descendant of the finalized block")
+        );
+
+        assert_eq!(chain.finalize(a2), Ok(()));
+        assert!(chain.block(&c2).is_none());
+    }
+
+    #[test]
+    fn finality_depth_finalizes_and_prunes_state() {
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+        let config = ChainConfig {
+            finality_depth: Some(2),
+            state_pruning: Some(2),
+        };
+        let mut chain = new_chain(&alice, config);
+        let genesis = chain.best_hash();
+
+        let mut hashes = vec![genesis];
+        for _ in 0..5 {
+            let block = build_block(&chain, &chain.best_hash(), &mut alice, &bob, 10);
+            hashes.push(chain.import_block(block).unwrap().hash);
+        }
+
+        // Block 3 is two blocks under the best block 5.
+        assert_eq!(chain.finalized_hash(), hashes[3]);
+        // The state of the finalized block and of the one before it are kept.
+        assert!(chain.state(&hashes[1]).is_none());
+        assert!(chain.state(&hashes[2]).is_some());
+        assert!(chain.state(&hashes[3]).is_some());
+        assert!(chain.block(&hashes[1]).is_some());
+    }
+
+    #[test]
+    fn import_state_replaces_the_chain() {
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+        let mut peer_chain = new_chain(&alice, ChainConfig::default());
+        for _ in 0..2 {
+            let block = build_block(&peer_chain, &peer_chain.best_hash(), &mut alice, &bob, 10);
+            peer_chain.import_block(block).unwrap();
+        }
+        let hash = peer_chain.best_hash();
+        let block = peer_chain.block(&hash).unwrap().clone();
+        let state = peer_chain.state(&hash).unwrap().clone();
+
+        let mut chain = new_chain(&alice, ChainConfig::default());
+        let genesis = chain.best_hash();
+        assert_eq!(chain.import_state(block.clone(), state.clone()), Ok(()));
+        assert_eq!(chain.best_hash(), hash);
+        assert_eq!(chain.finalized_hash(), hash);
+        assert!(chain.block(&genesis).is_none());
+
+        // Blocks are imported on top of the imported state.
+        let next = build_block(&peer_chain, &hash, &mut alice, &bob, 10);
+        assert!(chain.import_block(next).is_ok());
+        assert_eq!(
+            chain.import_state(block, state),
+            Err("Block is already finalized")
+        );
+    }
+}
@@ -0,0 +1,49 @@
+//! Consensus engines, which decide who can author blocks: the author of a block seals it, and the
+//! seal of every block is verified before it is imported in the chain. The seal is the last item
+//! of the digest of the header, and it is stripped from the header before the hash it seals is
+//! checked.
+
+pub mod aura;
+pub mod pow;
+
+use crate::{
+    chain::{Chain, ImportedBlock},
+    support::Digest,
+    timestamp, types, RuntimeCall,
+};
+
+/// A consensus engine, which seals the blocks authored by the node and verifies the seal of the
+/// blocks it imports.
+pub trait Consensus {
+    /// The pre-runtime digest of a block with the `inherents`, which the block is built with.
+    fn pre_digest(&self, inherents: &[types::Extrinsic]) -> Digest;
+
+    /// Seal the header of `block`, whose parent is in `chain`.
+    fn seal(&self, chain: &Chain, block: &mut types::Block) -> Result<(), &'static str>;
+
+    /// Check the seal of the header of `block`, whose parent is in `chain`.
+    fn verify(&self, chain: &Chain, block: &types::Block) -> Result<(), &'static str>;
+
+    /// Check the seals of `headers`, in ascending order from a child of a block in `chain`, from
+    /// the headers alone: their bodies and the state after them are unknown, e.g. when fast
+    /// syncing the state of the last one.
+    fn verify_headers(&self, chain: &Chain, headers: &[types::Header]) -> Result<(), &'static str>;
+
+    /// Verify the seal of `block`, then import it in `chain`.
+    fn import_block(
+        &self,
+        chain: &mut Chain,
+        block: types::Block,
+    ) -> Result<ImportedBlock, &'static str> {
+        self.verify(chain, &block)?;
+        chain.import_block(block)
+    }
+}
+
+/// The timestamp of `block`, set by its timestamp inherent, or `None` for the genesis.
+fn timestamp(block: &types::Block) -> Option<types::Moment> {
+    block.extrinsics.iter().find_map(|ext| match ext.call {
+        RuntimeCall::timestamp(timestamp::Call::set { now }) => Some(now),
+        _ => None,
+    })
+}
@@ -0,0 +1,316 @@
+use super::Consensus;
+use crate::{
+    aura::{self, Slot, AURA_ENGINE_ID},
+    chain::Chain,
+    support::{
+        codec::{Decode, Encode},
+        crypto::{Pair, Signature},
+        hashing::Hash,
+        Digest, DigestItem, Verify,
+    },
+    types, RuntimeCall,
+};
+
+/// Decode the signature of an Aura seal.
+fn decode_signature(bytes: &[u8]) -> Option<Signature> {
+    if bytes.len() != 64 {
+        return None;
+    }
+    let (e, s) = bytes.split_at(32);
+    Some(Signature {
+        e: e.try_into().ok()?,
+        s: s.try_into().ok()?,
+    })
+}
+
+/// The slot of a header, in the pre-runtime digest of its author.
+fn header_slot(header: &types::Header) -> Option<Slot> {
+    let slot = header.digest.pre_runtime(AURA_ENGINE_ID)?;
+    Some(Slot::from_le_bytes(slot.try_into().ok()?))
+}
+
+/// Check that `header` without its seal is signed by the authority of its slot among
+/// `authorities`, the authority set of its parent.
+fn verify_seal(header: &types::Header, authorities: &[String]) -> Result<(), &'static str> {
+    let mut header = header.clone();
+    let signature = header
+        .take_seal(AURA_ENGINE_ID)
+        .and_then(|seal| decode_signature(&seal))
+        .ok_or("Block seal is invalid")?;
+    let slot = header_slot(&header).ok_or("Block has no slot")?;
+    let author = slot
+        .checked_rem(authorities.len() as Slot)
+        .and_then(|index| authorities.get(index as usize))
+        .ok_or("There is no authority to author blocks")?;
+    if !Verify::verify(&signature, &header.hash(), author) {
+        return Err("Block seal is not signed by the authority of its slot");
+    }
+    Ok(())
+}
+
+/// This is the Aura engine.
+/// A fixed set of authorities, stored by the `aura` pallet, take turns to author blocks: the time
+/// is divided in slots, and the author of a block must be the authority of its slot in the state
+/// of its parent.
+#[derive(Debug, Clone)]
+pub struct Aura {
+    /// The key pairs of the authorities the node authors blocks for.
+    keys: Vec<Pair>,
+}
+
+impl Aura {
+    /// Create an engine authoring the blocks of the slots of the authorities with the `keys`.
+    pub fn new(keys: Vec<Pair>) -> Self {
+        Self { keys }
+    }
+
+    /// Whether the node can author a block on the block `parent` in `slot`: the slot must come
+    /// after the slot of the parent, and belong to one of the authorities of the node.
+    pub fn can_author(&self, chain: &Chain, parent: &Hash, slot: Slot) -> bool {
+        let Some(state) = chain.state(parent) else {
+            return false;
+        };
+        slot > state.aura.current_slot()
+            && Self::slot_author(chain, parent, slot)
+                .is_ok_and(|author| self.key(&author).is_some())
+    }
+
+    /// The key pair of `authority`, if the node has it.
+    fn key(&self, authority: &str) -> Option<&Pair> {
+        self.keys
+            .iter()
+            .find(|pair| pair.public().to_hex() == authority)
+    }
+
+    /// The authority of `slot`, in the state of the block `parent`.
+    fn slot_author(chain: &Chain, parent: &Hash, slot: Slot) -> Result<String, &'static str> {
+        let state = chain.state(parent).ok_or("Block parent state is pruned")?;
+        state
+            .aura
+            .slot_author(slot)
+            .cloned()
+            .ok_or("There is no authority to author blocks")
+    }
+}
+
+impl Consensus for Aura {
+    /// The slot noted by the slot inherent, if any.
+    fn pre_digest(&self, inherents: &[types::Extrinsic]) -> Digest {
+        let logs = slot(inherents)
+            .map(|slot| DigestItem::PreRuntime(AURA_ENGINE_ID, slot.encode()))
+            .into_iter()
+            .collect();
+        Digest { logs }
+    }
+
+    /// Sign the header with the key of the authority of its slot, if the node has it.
+    fn seal(&self, chain: &Chain, block: &mut types::Block) -> Result<(), &'static str> {
+        let header = &mut block.header;
+        let slot = header_slot(header).ok_or("Block has no slot")?;
+        let author = Self::slot_author(chain, &header.parent_hash, slot)?;
+        let pair = self
+            .key(&author)
+            .ok_or("Slot belongs to another authority")?;
+
+        let signature = pair.sign(&header.pre_hash());
+        header
+            .digest
+            .push(DigestItem::Seal(AURA_ENGINE_ID, signature.encode()));
+        Ok(())
+    }
+
+    /// The header without its seal must be signed by the authority of its slot. The runtime checks
+    /// the slot against the slot inherent and the timestamp of the block when executing it.
+    fn verify(&self, chain: &Chain, block: &types::Block) -> Result<(), &'static str> {
+        let parent = chain
+            .state(&block.header.parent_hash)
+            .ok_or("Block parent state is pruned")?;
+        verify_seal(&block.header, &parent.aura.authorities)
+    }
+
+    /// Every header must be signed by the authority of its slot, starting from the authority set
+    /// in the state of the known parent of the first header. A header whose block enacted a new
+    /// authority set has it in its digest, for the headers after it.
+    fn verify_headers(&self, chain: &Chain, headers: &[types::Header]) -> Result<(), &'static str> {
+        let Some(first) = headers.first() else {
+            return Ok(());
+        };
+        let mut authorities = chain
+            .state(&first.parent_hash)
+            .ok_or("Block parent state is pruned")?
+            .aura
+            .authorities
+            .clone();
+        for header in headers {
+            verify_seal(header, &authorities)?;
+            if let Some(next) = header.digest.consensus(AURA_ENGINE_ID) {
+                authorities =
+                    Vec::decode(next).map_err(|_| "Block authority set can not be decoded")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The slot noted by the slot inherent among `extrinsics`.
+fn slot(extrinsics: &[types::Extrinsic]) -> Option<Slot> {
+    extrinsics.iter().find_map(|ext| match ext.call {
+        RuntimeCall::aura(aura::Call::note_slot { slot }) => Some(slot),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode_signature, header_slot, Aura};
+    use crate::{
+        aura::AURA_ENGINE_ID,
+        block_builder::BlockBuilder,
+        block_time,
+        chain::{Chain, ChainConfig},
+        consensus::Consensus,
+        support::{codec::Encode, crypto::Pair, DigestItem, RawOrigin},
+        types, Runtime, BLOCK_TIME,
+    };
+
+    // The genesis state, where alice and bob are the authorities.
+    fn genesis() -> Runtime {
+        let mut genesis = Runtime::new();
+        genesis.aura.authorities = vec![
+            Pair::from_seed("alice").public().to_hex(),
+            Pair::from_seed("bob").public().to_hex(),
+        ];
+        genesis
+    }
+
+    // Build an unsealed block on the best block, in the slot following the one of the best block.
+    fn build_block(chain: &Chain, engine: &Aura) -> types::Block {
+        let state = chain.best_state();
+        let inherents = crate::inherents(block_time(0, state.system.block_number() + 1));
+        let mut builder = BlockBuilder::new(state, engine.pre_digest(&inherents));
+        for inherent in inherents {
+            builder.push(inherent).unwrap();
+        }
+        builder.finalize()
+    }
+
+    #[test]
+    fn signature_encoding() {
+        let signature = Pair::from_seed("alice").sign(b"message");
+        assert_eq!(decode_signature(&signature.encode()), Some(signature));
+        assert_eq!(decode_signature(&[0; 10]), None);
+    }
+
+    #[test]
+    fn authorities_take_turns() {
+        let mut chain = Chain::new(genesis(), ChainConfig::default());
+        let alice = Aura::new(vec![Pair::from_seed("alice")]);
+        let bob = Aura::new(vec![Pair::from_seed("bob")]);
+
+        // The first block is in slot 1, which belongs to bob.
+        let mut block = build_block(&chain, &alice);
+        assert_eq!(header_slot(&block.header), Some(1));
+        assert_eq!(
+            alice.seal(&chain, &mut block),
+            Err("Slot belongs to another authority")
+        );
+        assert_eq!(bob.seal(&chain, &mut block), Ok(()));
+        assert!(alice.import_block(&mut chain, block).is_ok());
+
+        let mut block = build_block(&chain, &alice);
+        assert_eq!(alice.seal(&chain, &mut block), Ok(()));
+        assert!(bob.import_block(&mut chain, block).is_ok());
+        assert_eq!(chain.best_state().aura.current_slot(), 2);
+    }
+
+    #[test]
+    fn import_verifies_the_seal() {
+        let mut chain = Chain::new(genesis(), ChainConfig::default());
+        let engine = Aura::new(vec![Pair::from_seed("bob")]);
+        let mut block = build_block(&chain, &engine);
+
+        // An unsealed block is rejected.
+        assert_eq!(
+            engine
+                .import_block(&mut chain, block.clone())
+                .map(|imported| imported.hash),
+            Err("Block seal is invalid")
+        );
+
+        // A block signed by another authority than the one of its slot is rejected.
+        let mut forged = block.clone();
+        let signature = Pair::from_seed("alice").sign(&block.header.pre_hash());
+        forged
+            .header
+            .digest
+            .push(DigestItem::Seal(AURA_ENGINE_ID, signature.encode()));
+        assert_eq!(
+            engine
+                .import_block(&mut chain, forged)
+                .map(|imported| imported.hash),
+            Err("Block seal is not signed by the authority of its slot")
+        );
+
+        assert_eq!(engine.seal(&chain, &mut block), Ok(()));
+        assert!(engine.import_block(&mut chain, block).is_ok());
+    }
+
+    #[test]
+    fn verify_headers_follows_the_authority_set_changes() {
+        let mut genesis = genesis();
+        let charlie = Pair::from_seed("charlie");
+        genesis
+            .aura
+            .set_authorities(RawOrigin::Root.into(), vec![charlie.public().to_hex()])
+            .unwrap();
+        let mut chain = Chain::new(genesis.clone(), ChainConfig::default());
+        let engine = Aura::new(vec![
+            Pair::from_seed("alice"),
+            Pair::from_seed("bob"),
+            charlie.clone(),
+        ]);
+
+        // Charlie is the only authority from the third block, at the start of the second session.
+        let mut headers = Vec::new();
+        for _ in 0..4 {
+            let mut block = build_block(&chain, &engine);
+            engine.seal(&chain, &mut block).unwrap();
+            headers.push(block.header.clone());
+            engine.import_block(&mut chain, block).unwrap();
+        }
+        assert!(headers[1].digest.consensus(AURA_ENGINE_ID).is_some());
+        let genesis_chain = Chain::new(genesis, ChainConfig::default());
+        assert_eq!(engine.verify_headers(&genesis_chain, &headers), Ok(()));
+
+        // The third block is signed by an authority of the previous set.
+        let mut forged = headers[2].clone();
+        forged.take_seal(AURA_ENGINE_ID);
+        let signature = Pair::from_seed("bob").sign(&forged.pre_hash());
+        forged
+            .digest
+            .push(DigestItem::Seal(AURA_ENGINE_ID, signature.encode()));
+        headers[2] = forged;
+        assert_eq!(
+            engine.verify_headers(&genesis_chain, &headers),
+            Err("Block seal is not signed by the authority of its slot")
+        );
+    }
+
+    #[test]
+    fn slot_inherent_must_match_the_pre_runtime_digest() {
+        let chain = Chain::new(genesis(), ChainConfig::default());
+        let engine = Aura::new(vec![Pair::from_seed("bob")]);
+        let inherents = crate::inherents(BLOCK_TIME);
+
+        // The slot of the pre-runtime digest is the one of the slot inherent of another block.
+        let digest = engine.pre_digest(&crate::inherents(3 * BLOCK_TIME));
+        let mut builder = BlockBuilder::new(chain.best_state(), digest);
+        let mut inherents = inherents.into_iter();
+        let (timestamp, note_slot) = (inherents.next().unwrap(), inherents.next().unwrap());
+        assert_eq!(builder.push(timestamp), Ok(()));
+        assert_eq!(
+            builder.push(note_slot),
+            Err("Slot does not match the pre-runtime digest of the block")
+        );
+    }
+}
@@ -0,0 +1,335 @@
+use num::BigUint;
+
+use super::{timestamp, Consensus};
+use crate::{
+    chain::Chain,
+    support::{
+        codec::Encode,
+        hashing::{hash, Hash},
+        ConsensusEngineId, Digest, DigestItem,
+    },
+    types,
+};
+
+/// The id of the Proof-of-Work engine, which tags its seals.
+pub const POW_ENGINE_ID: ConsensusEngineId = *b"pow_";
+
+/// The lowest difficulty a block can be mined at.
+const MIN_DIFFICULTY: u128 = 1;
+/// The most the difficulty can change from a block to the next one, as a factor.
+const MAX_ADJUSTMENT: u128 = 4;
+
+/// The seal of a block mined with Proof-of-Work: the difficulty the block was mined at, and the
+/// nonce which solves it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Seal {
+    pub difficulty: u128,
+    pub nonce: u64,
+}
+
+impl Encode for Seal {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.difficulty.encode_to(dest);
+        self.nonce.encode_to(dest);
+    }
+}
+
+impl Seal {
+    /// Decode a seal encoded with `Encode`.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != 24 {
+            return None;
+        }
+        let (difficulty, nonce) = bytes.split_at(16);
+        Some(Self {
+            difficulty: u128::from_le_bytes(difficulty.try_into().ok()?),
+            nonce: u64::from_le_bytes(nonce.try_into().ok()?),
+        })
+    }
+
+    /// Whether the nonce of the seal solves its difficulty for a header with the `pre_hash`: the
+    /// hash of both, as a 256 bits number, multiplied by the difficulty must fit in 256 bits. A
+    /// nonce solves the difficulty `d` with a probability of `1 / d`.
+    pub fn is_valid(&self, pre_hash: &Hash) -> bool {
+        let work = hash(&(pre_hash, self.nonce).encode());
+        let product = BigUint::from_bytes_be(&work) * self.difficulty;
+        product.bits() <= 256
+    }
+}
+
+/// This is the Proof-of-Work engine.
+/// Anyone can author a block, as long as they find a nonce which solves the difficulty of the
+/// block. The difficulty is adjusted at every block, so blocks are mined every `target_block_time`
+/// on average.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pow {
+    /// The difficulty of the first block.
+    pub initial_difficulty: u128,
+    /// The time between two blocks the difficulty aims for, in milliseconds.
+    pub target_block_time: types::Moment,
+}
+
+impl Pow {
+    /// The difficulty of the block built on the block `parent`.
+    ///
+    /// It is the difficulty of the parent, scaled by how much faster or slower than
+    /// `target_block_time` the parent was produced after its own parent, according to their
+    /// timestamps. The difficulty changes at most `MAX_ADJUSTMENT` times from a block to the next.
+    pub fn difficulty(&self, chain: &Chain, parent: &Hash) -> Result<u128, &'static str> {
+        let parent = chain.block(parent).ok_or("Block parent is unknown")?;
+        // The genesis is not sealed: the first block is mined at the initial difficulty.
+        let Some(parent_seal) = parent
+            .header
+            .digest
+            .seal(POW_ENGINE_ID)
+            .and_then(Seal::decode)
+        else {
+            return Ok(self.initial_difficulty);
+        };
+        let grandparent = chain
+            .block(&parent.header.parent_hash)
+            .ok_or("Block parent is unknown")?;
+        // The genesis has no timestamp: the second block keeps the difficulty of the first one.
+        let (Some(now), Some(previous)) = (timestamp(parent), timestamp(grandparent)) else {
+            return Ok(parent_seal.difficulty);
+        };
+
+        let elapsed = u128::from(now.saturating_sub(previous));
+        Ok(self.adjust(parent_seal.difficulty, elapsed))
+    }
+
+    /// The difficulty after a block mined at `difficulty`, which was produced `elapsed` after its
+    /// parent.
+    fn adjust(&self, difficulty: u128, elapsed: u128) -> u128 {
+        let target = u128::from(self.target_block_time).max(1);
+        let elapsed = elapsed
+            .clamp(target / MAX_ADJUSTMENT, target * MAX_ADJUSTMENT)
+            .max(1);
+        (difficulty.saturating_mul(target) / elapsed).max(MIN_DIFFICULTY)
+    }
+
+    /// Mine a header with the `pre_hash` at `difficulty`, by trying every nonce until one solves
+    /// the difficulty.
+    pub fn mine(pre_hash: &Hash, difficulty: u128) -> Seal {
+        (0..=u64::MAX)
+            .map(|nonce| Seal { difficulty, nonce })
+            .find(|seal| seal.is_valid(pre_hash))
+            .expect("a nonce solves any reachable difficulty")
+    }
+}
+
+impl Consensus for Pow {
+    /// Anyone can mine a block, so the block author has nothing to tell the runtime.
+    fn pre_digest(&self, _inherents: &[types::Extrinsic]) -> Digest {
+        Digest::default()
+    }
+
+    /// Mine the header at the difficulty of its parent.
+    fn seal(&self, chain: &Chain, block: &mut types::Block) -> Result<(), &'static str> {
+        let header = &mut block.header;
+        let difficulty = self.difficulty(chain, &header.parent_hash)?;
+        let seal = Self::mine(&header.pre_hash(), difficulty);
+        header
+            .digest
+            .push(DigestItem::Seal(POW_ENGINE_ID, seal.encode()));
+        Ok(())
+    }
+
+    /// The seal must be mined at the difficulty of the parent, and solve it for the header without
+    /// its seal.
+    fn verify(&self, chain: &Chain, block: &types::Block) -> Result<(), &'static str> {
+        let mut header = block.header.clone();
+        let seal = header
+            .take_seal(POW_ENGINE_ID)
+            .and_then(|seal| Seal::decode(&seal))
+            .ok_or("Block seal is invalid")?;
+        if seal.difficulty != self.difficulty(chain, &header.parent_hash)? {
+            return Err("Block seal has the wrong difficulty");
+        }
+        if !seal.is_valid(&header.hash()) {
+            return Err("Block seal does not solve its difficulty");
+        }
+        Ok(())
+    }
+
+    /// Every seal must solve its difficulty, which is the difficulty of the parent of the first
+    /// header, then changes at most `MAX_ADJUSTMENT` times from a header to the next. The exact
+    /// difficulty depends on the timestamps of the blocks, which are not in the headers.
+    fn verify_headers(&self, chain: &Chain, headers: &[types::Header]) -> Result<(), &'static str> {
+        let Some(first) = headers.first() else {
+            return Ok(());
+        };
+        let difficulty = self.difficulty(chain, &first.parent_hash)?;
+        let (mut min, mut max) = (difficulty, difficulty);
+        for header in headers {
+            let mut header = header.clone();
+            let seal = header
+                .take_seal(POW_ENGINE_ID)
+                .and_then(|seal| Seal::decode(&seal))
+                .ok_or("Block seal is invalid")?;
+            if seal.difficulty < min || seal.difficulty > max {
+                return Err("Block seal has the wrong difficulty");
+            }
+            if !seal.is_valid(&header.hash()) {
+                return Err("Block seal does not solve its difficulty");
+            }
+            // The difficulty is the lowest when the block took the longest, and the other way
+            // around.
+            min = self.adjust(seal.difficulty, u128::MAX);
+            max = self.adjust(seal.difficulty, 0);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Pow, Seal, POW_ENGINE_ID};
+    use crate::{
+        block_builder::BlockBuilder,
+        chain::{Chain, ChainConfig},
+        consensus::Consensus,
+        support::{codec::Encode, hashing::Hash, Digest, DigestItem},
+        timestamp_inherent, types, Runtime,
+    };
+
+    const POW: Pow = Pow {
+        initial_difficulty: 100,
+        target_block_time: 12_000,
+    };
+
+    // Build an unsealed block on the best block, whose timestamp is `6_000` times its number.
+    fn build_block(chain: &Chain) -> types::Block {
+        let state = chain.best_state();
+        let block_number = state.system.block_number() + 1;
+        let mut builder = BlockBuilder::new(state, Digest::default());
+        builder.push(timestamp_inherent(0, block_number)).unwrap();
+        builder.finalize()
+    }
+
+    #[test]
+    fn seal_encoding() {
+        let seal = Seal {
+            difficulty: 1_000,
+            nonce: 42,
+        };
+        assert_eq!(Seal::decode(&seal.encode()), Some(seal));
+        assert_eq!(Seal::decode(&[0; 10]), None);
+    }
+
+    #[test]
+    fn mine_solves_difficulty() {
+        let pre_hash: Hash = [7; 32];
+        let seal = Pow::mine(&pre_hash, 1_000);
+
+        assert!(seal.is_valid(&pre_hash));
+        // Almost no hash solves the highest difficulty.
+        let seal = Seal {
+            difficulty: u128::MAX,
+            ..seal
+        };
+        assert!(!seal.is_valid(&pre_hash));
+    }
+
+    #[test]
+    fn import_verifies_the_seal() {
+        let mut chain = Chain::new(Runtime::new(), ChainConfig::default());
+        let block = build_block(&chain);
+        let pre_hash = block.header.pre_hash();
+        let sealed = |engine, seal: Seal| {
+            let mut block = block.clone();
+            block
+                .header
+                .digest
+                .push(DigestItem::Seal(engine, seal.encode()));
+            block
+        };
+
+        // An unsealed block, or a block sealed by another engine, is rejected.
+        assert_eq!(
+            POW.import_block(&mut chain, block.clone())
+                .map(|imported| imported.hash),
+            Err("Block seal is invalid")
+        );
+        let seal = Pow::mine(&pre_hash, 100);
+        assert_eq!(
+            POW.import_block(&mut chain, sealed(*b"test", seal))
+                .map(|imported| imported.hash),
+            Err("Block seal is invalid")
+        );
+
+        // A block mined at another difficulty is rejected.
+        assert_eq!(
+            POW.import_block(&mut chain, sealed(POW_ENGINE_ID, Pow::mine(&pre_hash, 1)))
+                .map(|imported| imported.hash),
+            Err("Block seal has the wrong difficulty")
+        );
+
+        // A nonce which does not solve the difficulty is rejected.
+        let nonce = (seal.nonce..)
+            .find(|nonce| {
+                !Seal {
+                    nonce: *nonce,
+                    ..seal
+                }
+                .is_valid(&pre_hash)
+            })
+            .unwrap();
+        assert_eq!(
+            POW.import_block(&mut chain, sealed(POW_ENGINE_ID, Seal { nonce, ..seal }))
+                .map(|imported| imported.hash),
+            Err("Block seal does not solve its difficulty")
+        );
+
+        assert!(POW
+            .import_block(&mut chain, sealed(POW_ENGINE_ID, seal))
+            .is_ok());
+    }
+
+    #[test]
+    fn difficulty_follows_block_time() {
+        let mut chain = Chain::new(Runtime::new(), ChainConfig::default());
+
+        let mut difficulties = Vec::new();
+        for _ in 0..4 {
+            let mut block = build_block(&chain);
+            POW.seal(&chain, &mut block).unwrap();
+            let seal = block.header.digest.seal(POW_ENGINE_ID).unwrap();
+            difficulties.push(Seal::decode(seal).unwrap().difficulty);
+            POW.import_block(&mut chain, block).unwrap();
+        }
+
+        // Blocks are produced twice as fast as the target, so the difficulty doubles at every block
+        // after the second one.
+        assert_eq!(difficulties, vec![100, 100, 200, 400]);
+    }
+
+    #[test]
+    fn verify_headers_checks_the_seals() {
+        let mut chain = Chain::new(Runtime::new(), ChainConfig::default());
+        let genesis = chain.best_hash();
+        let mut headers = Vec::new();
+        for _ in 0..3 {
+            let mut block = build_block(&chain);
+            POW.seal(&chain, &mut block).unwrap();
+            headers.push(block.header.clone());
+            POW.import_block(&mut chain, block).unwrap();
+        }
+        let genesis_chain = Chain::new(Runtime::new(), ChainConfig::default());
+        assert_eq!(genesis_chain.best_hash(), genesis);
+        assert_eq!(POW.verify_headers(&genesis_chain, &headers), Ok(()));
+
+        // A header mined at a much lower difficulty than its parent is rejected.
+        let mut forged = headers[2].clone();
+        forged.take_seal(POW_ENGINE_ID);
+        let seal = Pow::mine(&forged.pre_hash(), 1);
+        forged
+            .digest
+            .push(DigestItem::Seal(POW_ENGINE_ID, seal.encode()));
+        headers[2] = forged;
+        assert_eq!(
+            POW.verify_headers(&genesis_chain, &headers),
+            Err("Block seal has the wrong difficulty")
+        );
+    }
+}
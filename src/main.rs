@@ -1,9 +1,34 @@
+mod aura;
 mod balances;
+mod block_builder;
+mod chain;
+mod consensus;
+mod multisig;
+mod network;
+mod node;
 mod proof_of_existence;
+mod proxy;
+mod rpc;
+mod sudo;
 mod support;
+mod sync;
 mod system;
+mod timestamp;
+mod transaction_payment;
+mod transaction_pool;
+mod vesting;
 
-use crate::support::Dispatch;
+use crate::support::{
+    codec::{Decode, Encode},
+    crypto::Pair,
+    Dispatch, InstanceFilter, RawOrigin,
+};
+use crate::{
+    block_builder::BlockBuilder,
+    chain::{Chain, ChainConfig, ImportedBlock},
+    consensus::{aura::Aura, pow::Pow, Consensus},
+    transaction_pool::TransactionPool,
+};
 
 // These are the concrete types we will use in our simple state machine.
 // Modules are configured for these types directly, and they satisfy all of our
@@ -15,26 +40,57 @@ mod types {
     pub type Balance = u128;
     pub type BlockNumber = u128;
     pub type Nonce = u32;
-    pub type Extrinsic = support::Extrinsic<AccountId, crate::RuntimeCall>;
+    pub type Signature = support::crypto::Signature;
+    pub type Extrinsic = support::Extrinsic<AccountId, crate::RuntimeCall, Nonce, Signature>;
     pub type Header = support::Header<BlockNumber>;
     pub type Block = support::Block<Header, Extrinsic>;
-    pub type Content = &'static str;
+    pub type Content = String;
+    pub type Moment = u64;
+    pub type SignedExtra = crate::transaction_payment::ChargeTransactionPayment<crate::Runtime>;
 }
 
 // This is our main Runtime.
 // It accumulates all of the different pallets we want to use.
-#[derive(Debug)]
+// Each pallet has a fixed index, which its calls and events are encoded with, so that reordering
+// the pallets does not change the encoding of extrinsics.
+#[derive(Debug, Clone)]
 #[macros::runtime]
 pub struct Runtime {
     pub system: system::Pallet<Self>,
+    #[pallet_index(1)]
+    #[events]
     pub balances: balances::Pallet<Self>,
+    #[pallet_index(2)]
+    #[events]
     pub proof_of_existence: proof_of_existence::Pallet<Self>,
+    #[pallet_index(3)]
+    pub sudo: sudo::Pallet<Self>,
+    #[pallet_index(4)]
+    pub multisig: multisig::Pallet<Self>,
+    #[pallet_index(5)]
+    pub proxy: proxy::Pallet<Self>,
+    #[pallet_index(6)]
+    pub vesting: vesting::Pallet<Self>,
+    #[pallet_index(7)]
+    #[inherent]
+    pub timestamp: timestamp::Pallet<Self>,
+    #[pallet_index(8)]
+    #[inherent]
+    pub aura: aura::Pallet<Self>,
 }
 
 impl system::Config for Runtime {
     type AccountId = types::AccountId;
+    type RuntimeOrigin = RuntimeOrigin;
     type BlockNumber = types::BlockNumber;
     type Nonce = types::Nonce;
+    type RuntimeEvent = RuntimeEvent;
+    const BLOCK_WEIGHTS: system::BlockWeights = system::BlockWeights {
+        base_extrinsic: 1_000,
+        max_block: 1_000_000,
+    };
+    const MAX_BLOCK_LENGTH: u32 = 16 * 1024;
+    const MAX_EXTRINSICS: u32 = 50;
 }
 
 impl balances::Config for Runtime {
@@ -45,63 +101,1151 @@ impl proof_of_existence::Config for Runtime {
     type Content = types::Content;
 }
 
-fn main() {
+impl sudo::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+}
+
+impl multisig::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+    const DEPOSIT_BASE: types::Balance = 10;
+    const DEPOSIT_FACTOR: types::Balance = 1;
+    const MAX_SIGNATORIES: u16 = 10;
+}
+
+impl proxy::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+    type ProxyType = ProxyType;
+    const MAX_PROXIES: u32 = 10;
+}
+
+impl vesting::Config for Runtime {
+    type BlockNumberToBalance = support::ConvertInto;
+    const MIN_VESTED_TRANSFER: types::Balance = 10;
+    const MAX_VESTING_SCHEDULES: u32 = 5;
+}
+
+impl transaction_payment::Config for Runtime {
+    type WeightToFee = support::ConvertInto;
+    type LengthToFee = support::ConvertInto;
+    type FeeDestination = ToTreasury;
+}
+
+impl timestamp::Config for Runtime {
+    type Moment = types::Moment;
+    const MINIMUM_PERIOD: types::Moment = 3_000;
+}
+
+impl aura::Config for Runtime {
+    const SLOT_DURATION: aura::Slot = BLOCK_TIME;
+    const SESSION_LENGTH: u32 = 2;
+}
+
+// The kinds of proxies an account can register with the proxy pallet.
+// Each of them restricts which calls the proxy can make on behalf of the account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyType {
+    // Any call is allowed.
+    Any,
+    // Only calls to the balances pallet are allowed.
+    BalancesOnly,
+    // Only calls to the proof of existence pallet are allowed.
+    ClaimsOnly,
+}
+
+impl InstanceFilter<RuntimeCall> for ProxyType {
+    fn filter(&self, call: &RuntimeCall) -> bool {
+        match self {
+            ProxyType::Any => true,
+            ProxyType::BalancesOnly => matches!(call, RuntimeCall::balances(_)),
+            ProxyType::ClaimsOnly => matches!(call, RuntimeCall::proof_of_existence(_)),
+        }
+    }
+}
+
+impl Encode for ProxyType {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        dest.push(*self as u8);
+    }
+}
+
+impl Decode for ProxyType {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        match u8::decode_from(input)? {
+            0 => Ok(ProxyType::Any),
+            1 => Ok(ProxyType::BalancesOnly),
+            2 => Ok(ProxyType::ClaimsOnly),
+            _ => Err("invalid proxy type"),
+        }
+    }
+}
+
+// The account which receives the transaction fees.
+const TREASURY: &str = "treasury";
+
+// Gives the transaction fees to the `TREASURY` account.
+pub struct ToTreasury;
+
+impl transaction_payment::FeeDestination<Runtime> for ToTreasury {
+    fn on_fee(runtime: &mut Runtime, fee: types::Balance) -> support::DispatchResult {
+        runtime.balances.deposit(&TREASURY.to_string(), fee)
+    }
+}
+
+impl support::RuntimeApi for Runtime {
+    type AccountId = types::AccountId;
+    type Balance = types::Balance;
+    type Nonce = types::Nonce;
+    type Claim = str;
+    type Extrinsic = types::Extrinsic;
+
+    fn account_nonce(&self, who: &types::AccountId) -> types::Nonce {
+        self.system.get_nonce(who)
+    }
+
+    fn balance_of(&self, who: &types::AccountId) -> types::Balance {
+        self.balances.balance(who)
+    }
+
+    fn claim_owner(&self, claim: &str) -> Option<types::AccountId> {
+        self.proof_of_existence.get_claim(claim).cloned()
+    }
+
+    // The fee for the weight of the call and the encoded length of the extrinsic, as the
+    // `types::SignedExtra` charges it. Unsigned extrinsics pay no fee.
+    fn query_fee_info(
+        &self,
+        extrinsic: &types::Extrinsic,
+    ) -> Result<support::FeeInfo<types::Balance>, &'static str> {
+        let weight = support::GetDispatchInfo::get_dispatch_info(&extrinsic.call).weight;
+        let partial_fee = match extrinsic.signer {
+            Some(_) => types::SignedExtra::compute_fee(extrinsic.encode().len(), weight)?,
+            None => 0,
+        };
+        Ok(support::FeeInfo {
+            weight,
+            partial_fee,
+        })
+    }
+}
+
+// The time between two blocks, in milliseconds.
+const BLOCK_TIME: types::Moment = 6_000;
+
+// The Proof-of-Work engine of the development chain.
+const DEV_POW: Pow = Pow {
+    initial_difficulty: 1_000,
+    target_block_time: BLOCK_TIME,
+};
+
+// The time of a block, as if a block was produced every `BLOCK_TIME` since `genesis_time`.
+fn block_time(genesis_time: types::Moment, block_number: types::BlockNumber) -> types::Moment {
+    genesis_time + block_number as types::Moment * BLOCK_TIME
+}
+
+// The inherent which sets the timestamp of a block to its `block_time`. It must be the first
+// extrinsic of every block. Tests use it to build blocks without a slot.
+#[cfg(test)]
+fn timestamp_inherent(
+    genesis_time: types::Moment,
+    block_number: types::BlockNumber,
+) -> types::Extrinsic {
+    let now = block_time(genesis_time, block_number);
+    support::Extrinsic::new_unsigned(RuntimeCall::timestamp(timestamp::Call::set { now }))
+}
+
+// The inherents of a block authored at `now`: its timestamp, then its slot.
+fn inherents(now: types::Moment) -> Vec<types::Extrinsic> {
+    let slot = now / <Runtime as aura::Config>::SLOT_DURATION;
+    vec![
+        support::Extrinsic::new_unsigned(RuntimeCall::timestamp(timestamp::Call::set { now })),
+        support::Extrinsic::new_unsigned(RuntimeCall::aura(aura::Call::note_slot { slot })),
+    ]
+}
+
+// A development account, whose key pair is derived from its name.
+// It keeps track of its next nonce to sign extrinsics.
+struct DevAccount {
+    pair: Pair,
+    next_nonce: types::Nonce,
+}
+
+impl DevAccount {
+    fn new(name: &str) -> Self {
+        Self {
+            pair: Pair::from_seed(name),
+            next_nonce: 0,
+        }
+    }
+
+    // The account id, which is the public key of the account.
+    fn id(&self) -> types::AccountId {
+        self.pair.public().to_hex()
+    }
+
+    // Sign `call` with the next nonce of the account.
+    fn sign(&mut self, call: RuntimeCall) -> types::Extrinsic {
+        let nonce = self.next_nonce;
+        self.next_nonce += 1;
+        let signature = self
+            .pair
+            .sign(&types::Extrinsic::signing_payload(&call, &nonce));
+        support::Extrinsic::new_signed(call, self.id(), nonce, signature)
+    }
+}
+
+// Author a block on the best block of the chain, from the `inherents` and the ready transactions
+// of the pool, with the pre-runtime digest of the consensus `engine`, then seal it with the
+// engine, import it, and update the pool.
+fn author_block(
+    chain: &mut Chain,
+    pool: &mut TransactionPool<Runtime>,
+    engine: &dyn Consensus,
+    inherents: Vec<types::Extrinsic>,
+) -> Result<ImportedBlock, &'static str> {
+    let mut builder = BlockBuilder::new(chain.best_state(), engine.pre_digest(&inherents));
+    for inherent in inherents {
+        builder.push(inherent)?;
+    }
+    for extrinsic in pool.ready() {
+        // Extrinsics which are invalid or do not fit in the block are left out of it.
+        let _ = builder.push(extrinsic);
+    }
+
+    let mut block = builder.finalize();
+    engine.seal(chain, &mut block)?;
+    let imported = engine.import_block(chain, block)?;
+    update_pool(chain, pool, &imported);
+    Ok(imported)
+}
+
+// Author the next block of the demo, whose time is its `block_time`.
+fn produce_block(
+    chain: &mut Chain,
+    pool: &mut TransactionPool<Runtime>,
+    engine: &dyn Consensus,
+    genesis_time: types::Moment,
+) {
+    let block_number = chain.best_state().system.block_number() + 1;
+    let now = block_time(genesis_time, block_number);
+    let imported =
+        author_block(chain, pool, engine, inherents(now)).expect("wrong block execution");
+    println!(
+        "Imported block #{} {}, finalized {}",
+        block_number,
+        support::hashing::to_hex(&imported.hash),
+        support::hashing::to_hex(&chain.finalized_hash()),
+    );
+}
+
+// Update the pool after a block was imported: the extrinsics of the blocks which left the best
+// chain go back to the pool, and the ones of the blocks which joined it are pruned from the pool.
+fn update_pool(chain: &Chain, pool: &mut TransactionPool<Runtime>, imported: &ImportedBlock) {
+    let state = chain.best_state();
+    // The retracted blocks may be pruned already, so they come with the import.
+    let retracted = imported
+        .retracted
+        .iter()
+        .flat_map(|block| &block.extrinsics)
+        .filter(|ext| ext.signer.is_some());
+    for extrinsic in retracted {
+        // The extrinsic may be invalid on the new best chain, e.g. if it was also included in it.
+        let _ = pool.submit(state, extrinsic.clone());
+    }
+
+    let enacted = imported
+        .enacted
+        .iter()
+        .flat_map(|hash| {
+            &chain
+                .block(hash)
+                .expect("the best chain is never pruned")
+                .extrinsics
+        })
+        .filter(|ext| ext.signer.is_some())
+        .cloned()
+        .collect::<Vec<_>>();
+    pool.prune(state, &enacted);
+}
+
+// The genesis state of the development chain.
+fn dev_genesis() -> Runtime {
     // Create a new instance of the Runtime.
     // It will instantiate with it all the modules it uses.
-    let mut runtime = Runtime::new();
-    let alice = "alice".to_string();
-    let bob = "bob".to_string();
-    let charlie = "charlie".to_string();
-
-    // Initialize the system with some initial balance.
-    runtime.balances.set_balance(&alice, 100);
-
-    let block_1 = types::Block {
-        header: support::Header { block_number: 1 },
-        extrinsics: vec![
-            support::Extrinsic {
-                caller: alice.clone(),
-                call: RuntimeCall::balances(balances::Call::transfer {
-                    to: bob.clone(),
-                    amount: 20,
-                }),
+    let mut genesis = Runtime::new();
+    let alice = DevAccount::new("alice");
+    let bob = DevAccount::new("bob");
+
+    // Initialize the system with a sudo key, which is allowed to make privileged calls, and some
+    // initial balance for it to pay the transaction fees.
+    genesis.sudo.key = Some(alice.id());
+    genesis
+        .balances
+        .set_balance(RawOrigin::Root.into(), alice.id(), 1_000_000)
+        .expect("root can set a balance");
+    // Alice and bob are the first authorities of the chain.
+    genesis.aura.authorities = vec![alice.id(), bob.id()];
+    genesis
+}
+
+fn main() {
+    // Check the configuration of the pallets before anything runs.
+    Runtime::integrity_test();
+
+    // With `--metadata`, print the metadata of the runtime as JSON.
+    if std::env::args().any(|arg| arg == "--metadata") {
+        println!("{}", rpc::metadata(&Runtime::metadata()));
+        return;
+    }
+
+    // With `--listen`, run a node of the development chain instead of the demo.
+    match node::NodeConfig::from_args(std::env::args().skip(1)) {
+        Ok(Some(config)) => return node::run(config),
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    }
+
+    let genesis = dev_genesis();
+    // Signed extrinsics wait in the transaction pool until they are included in a block.
+    let mut pool = TransactionPool::new();
+    let mut alice = DevAccount::new("alice");
+    let mut bob = DevAccount::new("bob");
+    let charlie = DevAccount::new("charlie");
+
+    // The time the chain starts at, which the timestamp of each block is derived from.
+    let genesis_time = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("time is after the unix epoch")
+        .as_millis() as types::Moment;
+
+    // The chain of blocks built on the genesis state, where blocks two blocks under the best block
+    // are finalized, and the state of the ten last finalized blocks is kept.
+    let config = ChainConfig {
+        finality_depth: Some(2),
+        state_pruning: Some(10),
+    };
+    let mut chain = Chain::new(genesis, config);
+    // Blocks are authored in turns by the authorities with Aura, where this node holds the keys of
+    // all the authorities. With `--pow`, blocks are mined with Proof-of-Work instead, at a
+    // difficulty low enough for the CPU miner to find a block quickly.
+    let engine: Box<dyn Consensus> = if std::env::args().any(|arg| arg == "--pow") {
+        Box::new(DEV_POW)
+    } else {
+        Box::new(Aura::new(vec![
+            alice.pair.clone(),
+            bob.pair.clone(),
+            charlie.pair.clone(),
+        ]))
+    };
+
+    // Each extrinsic is submitted to the pool, which validates it against the current state.
+    // Give some initial balance to bob, using the sudo key held by alice.
+    let ext = alice.sign(RuntimeCall::sudo(sudo::Call::sudo {
+        call: Box::new(RuntimeCall::balances(balances::Call::set_balance {
+            who: bob.id(),
+            amount: 100_000,
+        })),
+    }));
+    pool.submit(chain.best_state(), ext)
+        .expect("valid transaction");
+    let ext = alice.sign(RuntimeCall::balances(balances::Call::transfer {
+        to: bob.id(),
+        amount: 20,
+    }));
+    pool.submit(chain.best_state(), ext)
+        .expect("valid transaction");
+    let ext = alice.sign(RuntimeCall::balances(balances::Call::transfer {
+        to: charlie.id(),
+        amount: 30,
+    }));
+    pool.submit(chain.best_state(), ext)
+        .expect("valid transaction");
+
+    produce_block(&mut chain, &mut pool, engine.as_ref(), genesis_time);
+
+    let ext = alice.sign(RuntimeCall::proof_of_existence(
+        proof_of_existence::Call::create_claim {
+            claim: "my_document".to_string(),
+        },
+    ));
+    pool.submit(chain.best_state(), ext)
+        .expect("valid transaction");
+    let ext = bob.sign(RuntimeCall::proof_of_existence(
+        proof_of_existence::Call::create_claim {
+            claim: "Bobs Doc".to_string(),
+        },
+    ));
+    pool.submit(chain.best_state(), ext)
+        .expect("valid transaction");
+
+    produce_block(&mut chain, &mut pool, engine.as_ref(), genesis_time);
+
+    // A multisig account shared by alice, bob and charlie, where any two of them can dispatch a
+    // call on its behalf. The multisig pallet sorts the signatories of the account.
+    let mut signatories = vec![alice.id(), bob.id(), charlie.id()];
+    signatories.sort();
+    let multisig_account = multisig::Pallet::<Runtime>::multi_account_id(&signatories, 2);
+    let multisig_transfer = || {
+        Box::new(RuntimeCall::balances(balances::Call::transfer {
+            to: charlie.id(),
+            amount: 10,
+        }))
+    };
+
+    let ext = alice.sign(RuntimeCall::sudo(sudo::Call::sudo {
+        call: Box::new(RuntimeCall::balances(balances::Call::mint {
+            who: multisig_account,
+            amount: 50,
+        })),
+    }));
+    pool.submit(chain.best_state(), ext)
+        .expect("valid transaction");
+    let ext = alice.sign(RuntimeCall::multisig(multisig::Call::as_multi {
+        threshold: 2,
+        other_signatories: vec![bob.id(), charlie.id()],
+        call: multisig_transfer(),
+    }));
+    pool.submit(chain.best_state(), ext)
+        .expect("valid transaction");
+    let ext = bob.sign(RuntimeCall::multisig(multisig::Call::as_multi {
+        threshold: 2,
+        other_signatories: vec![alice.id(), charlie.id()],
+        call: multisig_transfer(),
+    }));
+    pool.submit(chain.best_state(), ext)
+        .expect("valid transaction");
+
+    // Bob and charlie become the authorities from the next session.
+    let ext = alice.sign(RuntimeCall::sudo(sudo::Call::sudo {
+        call: Box::new(RuntimeCall::aura(aura::Call::set_authorities {
+            authorities: vec![bob.id(), charlie.id()],
+        })),
+    }));
+    pool.submit(chain.best_state(), ext)
+        .expect("valid transaction");
+
+    produce_block(&mut chain, &mut pool, engine.as_ref(), genesis_time);
+
+    // Bob becomes a proxy of alice which can only make balances calls: in the next block, the
+    // transfer succeeds, while the claim is rejected.
+    let ext = alice.sign(RuntimeCall::proxy(proxy::Call::add_proxy {
+        delegate: bob.id(),
+        proxy_type: ProxyType::BalancesOnly,
+    }));
+    pool.submit(chain.best_state(), ext)
+        .expect("valid transaction");
+
+    produce_block(&mut chain, &mut pool, engine.as_ref(), genesis_time);
+
+    let ext = bob.sign(RuntimeCall::proxy(proxy::Call::proxy {
+        real: alice.id(),
+        call: Box::new(RuntimeCall::balances(balances::Call::transfer {
+            to: bob.id(),
+            amount: 5,
+        })),
+    }));
+    pool.submit(chain.best_state(), ext)
+        .expect("valid transaction");
+    let ext = bob.sign(RuntimeCall::proxy(proxy::Call::proxy {
+        real: alice.id(),
+        call: Box::new(RuntimeCall::proof_of_existence(
+            proof_of_existence::Call::create_claim {
+                claim: "Alices Doc".to_string(),
             },
-            support::Extrinsic {
-                caller: alice.clone(),
-                call: RuntimeCall::balances(balances::Call::transfer {
-                    to: charlie,
-                    amount: 30,
+        )),
+    }));
+    pool.submit(chain.best_state(), ext)
+        .expect("valid transaction");
+
+    produce_block(&mut chain, &mut pool, engine.as_ref(), genesis_time);
+
+    // Alice transfers some balance to charlie which unlocks over the following blocks.
+    let ext = alice.sign(RuntimeCall::vesting(vesting::Call::vested_transfer {
+        target: charlie.id(),
+        schedule: vesting::VestingInfo {
+            locked: 20,
+            per_block: 5,
+            starting_block: 6,
+        },
+    }));
+    pool.submit(chain.best_state(), ext)
+        .expect("valid transaction");
+
+    produce_block(&mut chain, &mut pool, engine.as_ref(), genesis_time);
+
+    // Finalize the whole chain, then simply print the debug format of the state of the best block.
+    chain
+        .finalize(chain.best_hash())
+        .expect("the best block can be finalized");
+    println!("{:#?}", chain.best_state());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transfer(from: &mut DevAccount, to: &DevAccount) -> types::Extrinsic {
+        from.sign(RuntimeCall::balances(balances::Call::transfer {
+            to: to.id(),
+            amount: 1,
+        }))
+    }
+
+    fn new_block(
+        block_number: types::BlockNumber,
+        extrinsics: Vec<types::Extrinsic>,
+    ) -> types::Block {
+        let header = support::Header {
+            block_number,
+            parent_hash: Default::default(),
+            state_root: Default::default(),
+            extrinsics_root: support::extrinsics_root(&extrinsics),
+            digest: Default::default(),
+        };
+        types::Block { header, extrinsics }
+    }
+
+    // Set the state root of `block` to the one of the state after executing it on a new runtime.
+    fn set_state_root(block: &mut types::Block) {
+        let mut runtime = Runtime::new();
+        let res = runtime.execute_block(block.clone());
+        assert_eq!(
+            res,
+            Err("state root does not match the state after the block")
+        );
+        block.header.state_root = runtime.state_root();
+    }
+
+    #[test]
+    fn execute_block_rejects_too_many_extrinsics() {
+        let mut runtime = Runtime::new();
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+        let mut extrinsics = vec![timestamp_inherent(0, 1)];
+        extrinsics.extend((0..50).map(|_| transfer(&mut alice, &bob)));
+
+        let block = new_block(1, extrinsics);
+
+        assert_eq!(
+            runtime.execute_block(block),
+            Err("block has too many extrinsics")
+        );
+    }
+
+    #[test]
+    fn check_block_limits_counts_the_initial_weight() {
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+        let extrinsics = [transfer(&mut alice, &bob)];
+        let block_weights = <Runtime as system::Config>::BLOCK_WEIGHTS;
+        // The transfer takes the base weight of an extrinsic and the default weight.
+        let weight = block_weights.base_extrinsic + support::DEFAULT_WEIGHT;
+
+        assert_eq!(
+            Runtime::check_block_limits(block_weights.max_block - weight, &extrinsics),
+            Ok(())
+        );
+        assert_eq!(
+            Runtime::check_block_limits(block_weights.max_block - weight + 1, &extrinsics),
+            Err("block weight exceeds the maximum block weight")
+        );
+    }
+
+    #[test]
+    fn execute_block_rejects_too_long_block() {
+        let mut runtime = Runtime::new();
+        let claim = "a".repeat(17 * 1024);
+        let create_claim = DevAccount::new("alice").sign(RuntimeCall::proof_of_existence(
+            proof_of_existence::Call::create_claim { claim },
+        ));
+
+        let block = new_block(1, vec![timestamp_inherent(0, 1), create_claim]);
+
+        assert_eq!(
+            runtime.execute_block(block),
+            Err("block length exceeds the maximum block length")
+        );
+    }
+
+    #[test]
+    fn execute_block_rejects_block_without_timestamp() {
+        let mut runtime = Runtime::new();
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+
+        let block = new_block(1, vec![transfer(&mut alice, &bob)]);
+
+        assert_eq!(
+            runtime.execute_block(block),
+            Err("block is missing a required inherent")
+        );
+    }
+
+    #[test]
+    fn execute_block_rejects_wrong_extrinsics_root() {
+        let mut runtime = Runtime::new();
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+
+        let mut block = new_block(1, vec![timestamp_inherent(0, 1)]);
+        block.extrinsics.push(transfer(&mut alice, &bob));
+
+        assert_eq!(
+            runtime.execute_block(block),
+            Err("extrinsics root does not match the extrinsics of the block")
+        );
+    }
+
+    #[test]
+    fn execute_block_checks_digest() {
+        let consensus_log = support::DigestItem::Consensus(*b"test", vec![1]);
+
+        // The runtime did not deposit the consensus item.
+        let mut block = new_block(1, vec![timestamp_inherent(0, 1)]);
+        block.header.digest.push(consensus_log.clone());
+        assert_eq!(
+            Runtime::new().execute_block(block),
+            Err("digest does not match the digest of the block")
+        );
+
+        // Pre-runtime items and the seal are not produced by the runtime.
+        let mut block = new_block(1, vec![timestamp_inherent(0, 1)]);
+        block
+            .header
+            .digest
+            .push(support::DigestItem::PreRuntime(*b"test", vec![2]));
+        block
+            .header
+            .digest
+            .push(support::DigestItem::Seal(*b"test", vec![3]));
+        set_state_root(&mut block);
+        assert_eq!(Runtime::new().execute_block(block), Ok(()));
+    }
+
+    #[test]
+    fn execute_block_checks_state_root() {
+        let mut block = new_block(1, vec![timestamp_inherent(0, 1)]);
+        set_state_root(&mut block);
+
+        // The state root commits to the state before the hash of the block is recorded.
+        let mut runtime = Runtime::new();
+        let state_root = block.header.state_root;
+        assert_eq!(runtime.execute_block(block.clone()), Ok(()));
+        assert_eq!(runtime.system.block_hash(), block.header.hash());
+        assert_ne!(runtime.state_root(), state_root);
+
+        block.header.state_root = [0; 32];
+        assert_eq!(
+            Runtime::new().execute_block(block),
+            Err("state root does not match the state after the block")
+        );
+    }
+
+    #[test]
+    fn execute_block_rejects_invalid_extrinsic() {
+        let mut runtime = Runtime::new();
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+
+        // Alice can not pay the fee of her transfer.
+        let block = new_block(
+            1,
+            vec![timestamp_inherent(0, 1), transfer(&mut alice, &bob)],
+        );
+
+        assert_eq!(
+            runtime.execute_block(block),
+            Err("Inability to pay some fees")
+        );
+    }
+
+    #[test]
+    fn execute_block_deposits_events() {
+        let mut runtime = Runtime::new();
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+        runtime
+            .balances
+            .set_balance(RawOrigin::Root.into(), alice.id(), 1_000_000)
+            .unwrap();
+
+        let mut builder = BlockBuilder::new(&runtime, support::Digest::default());
+        builder.push(timestamp_inherent(0, 1)).unwrap();
+        builder.push(transfer(&mut alice, &bob)).unwrap();
+        let claim = proof_of_existence::Call::create_claim {
+            claim: "document".to_string(),
+        };
+        builder
+            .push(alice.sign(RuntimeCall::proof_of_existence(claim.clone())))
+            .unwrap();
+        builder
+            .push(alice.sign(RuntimeCall::proof_of_existence(claim)))
+            .unwrap();
+        assert_eq!(runtime.execute_block(builder.finalize()), Ok(()));
+
+        // The balance set before the block is not one of its events.
+        let events: Vec<_> = runtime
+            .system
+            .events()
+            .iter()
+            .map(|record| (record.phase, record.event.clone()))
+            .collect();
+        assert_eq!(events.len(), 6);
+        assert!(matches!(
+            &events[..],
+            [
+                (
+                    support::Phase::ApplyExtrinsic(0),
+                    RuntimeEvent::system(system::Event::ExtrinsicSuccess { .. })
+                ),
+                (
+                    support::Phase::ApplyExtrinsic(1),
+                    RuntimeEvent::balances(balances::Event::Transfer { amount: 1, .. })
+                ),
+                (
+                    support::Phase::ApplyExtrinsic(1),
+                    RuntimeEvent::system(system::Event::ExtrinsicSuccess { .. })
+                ),
+                (
+                    support::Phase::ApplyExtrinsic(2),
+                    RuntimeEvent::proof_of_existence(proof_of_existence::Event::ClaimCreated {
+                        claim,
+                        ..
+                    })
+                ),
+                (
+                    support::Phase::ApplyExtrinsic(2),
+                    RuntimeEvent::system(system::Event::ExtrinsicSuccess { .. })
+                ),
+                (
+                    support::Phase::ApplyExtrinsic(3),
+                    RuntimeEvent::system(system::Event::ExtrinsicFailed {
+                        error: "Claim already exists"
+                    })
+                ),
+            ] if claim == "document"
+        ));
+
+        // Events are encoded with the index of their pallet.
+        assert_eq!(events[3].1.encode()[0], 2);
+    }
+
+    #[test]
+    fn apply_extrinsic_reverts_failed_call() {
+        let (mut alice, mut bob, charlie) = (
+            DevAccount::new("alice"),
+            DevAccount::new("bob"),
+            DevAccount::new("charlie"),
+        );
+        let mut runtime = Runtime::new();
+        for who in [&alice, &bob] {
+            runtime
+                .balances
+                .set_balance(RawOrigin::Root.into(), who.id(), 1_000_000)
+                .unwrap();
+        }
+        runtime.initialize_block(&support::Digest::default());
+
+        // The multisig account has no balance to transfer.
+        let mut signatories = vec![alice.id(), bob.id(), charlie.id()];
+        signatories.sort();
+        let multisig_account = multisig::Pallet::<Runtime>::multi_account_id(&signatories, 2);
+        let multisig_transfer = || {
+            Box::new(RuntimeCall::balances(balances::Call::transfer {
+                to: charlie.id(),
+                amount: 10,
+            }))
+        };
+        let call_hash = support::hashing::hash(&multisig_transfer().encode());
+
+        let ext = alice.sign(RuntimeCall::multisig(multisig::Call::as_multi {
+            threshold: 2,
+            other_signatories: vec![bob.id(), charlie.id()],
+            call: multisig_transfer(),
+        }));
+        assert!(matches!(runtime.apply_extrinsic(ext), Ok(Ok(_))));
+        let balance = runtime.balances.balance(&bob.id());
+
+        // The approval of bob is reverted along with the transfer, but bob paid the fee.
+        let ext = bob.sign(RuntimeCall::multisig(multisig::Call::as_multi {
+            threshold: 2,
+            other_signatories: vec![alice.id(), charlie.id()],
+            call: multisig_transfer(),
+        }));
+        assert!(matches!(runtime.apply_extrinsic(ext), Ok(Err(_))));
+        let multisig = runtime.multisig.multisig(&multisig_account, &call_hash);
+        assert_eq!(
+            multisig.map(|m| m.approvals.clone()),
+            Some(vec![alice.id()])
+        );
+        assert_eq!(runtime.system.get_nonce(&bob.id()), 1);
+        assert!(runtime.balances.balance(&bob.id()) < balance);
+    }
+
+    #[test]
+    fn execute_block_runs_the_hooks() {
+        use support::GetDispatchInfo;
+
+        Runtime::integrity_test();
+
+        let mut runtime = Runtime::new();
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+        runtime
+            .balances
+            .set_balance(RawOrigin::Root.into(), alice.id(), 1_000_000)
+            .unwrap();
+
+        let mut builder = BlockBuilder::new(&runtime, support::Digest::default());
+        builder.push(timestamp_inherent(0, 1)).unwrap();
+        builder.push(transfer(&mut alice, &bob)).unwrap();
+        let block = builder.finalize();
+        // The pallets have no weight of their own in their hooks, so the block takes the weight of
+        // its extrinsics.
+        let base_extrinsic = <Runtime as system::Config>::BLOCK_WEIGHTS.base_extrinsic;
+        let weight: support::Weight = block
+            .extrinsics
+            .iter()
+            .map(|ext| base_extrinsic + ext.call.get_dispatch_info().weight)
+            .sum();
+        assert_eq!(runtime.execute_block(block), Ok(()));
+        assert_eq!(runtime.system.block_weight(), weight);
+        assert_eq!(
+            runtime.system.remaining_weight(),
+            <Runtime as system::Config>::BLOCK_WEIGHTS.max_block - weight
+        );
+    }
+
+    #[test]
+    fn runtime_api_reads_the_state() {
+        use support::RuntimeApi;
+
+        let mut runtime = Runtime::new();
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+        runtime
+            .balances
+            .set_balance(RawOrigin::Root.into(), alice.id(), 1_000_000)
+            .unwrap();
+        runtime
+            .proof_of_existence
+            .create_claim(RawOrigin::Signed(bob.id()).into(), "document".to_string())
+            .unwrap();
+
+        assert_eq!(runtime.balance_of(&alice.id()), 1_000_000);
+        assert_eq!(runtime.claim_owner("document"), Some(bob.id()));
+        assert_eq!(runtime.claim_owner("other"), None);
+
+        // The fee is the one the transaction is charged.
+        let ext = transfer(&mut alice, &bob);
+        let info = runtime.query_fee_info(&ext).unwrap();
+        assert_eq!(info.weight, support::DEFAULT_WEIGHT);
+        assert!(matches!(runtime.apply_extrinsic(ext), Ok(Ok(_))));
+        assert_eq!(
+            runtime.balance_of(&alice.id()),
+            1_000_000 - 1 - info.partial_fee
+        );
+        assert_eq!(runtime.account_nonce(&alice.id()), 1);
+        assert_eq!(
+            runtime
+                .query_fee_info(&timestamp_inherent(0, 1))
+                .unwrap()
+                .partial_fee,
+            0
+        );
+    }
+
+    #[test]
+    fn dry_run_does_not_change_the_state() {
+        let mut runtime = Runtime::new();
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+        runtime
+            .balances
+            .set_balance(RawOrigin::Root.into(), alice.id(), 1_000_000)
+            .unwrap();
+        let state_root = runtime.state_root();
+
+        let dry_run = runtime.dry_run(transfer(&mut alice, &bob));
+        assert!(matches!(dry_run.result, Ok(Ok(_))));
+        assert_eq!(dry_run.weight, support::DEFAULT_WEIGHT);
+        assert!(matches!(
+            &dry_run.events[..],
+            [
+                support::EventRecord {
+                    phase: support::Phase::ApplyExtrinsic(0),
+                    event: RuntimeEvent::balances(balances::Event::Transfer { amount: 1, .. }),
+                },
+                support::EventRecord {
+                    event: RuntimeEvent::system(system::Event::ExtrinsicSuccess { .. }),
+                    ..
+                },
+            ]
+        ));
+        assert_eq!(runtime.state_root(), state_root);
+        assert_eq!(runtime.balances.balance(&bob.id()), 0);
+
+        // The same nonce can be tried again, since the state did not change.
+        alice.next_nonce = 0;
+        let too_much = alice.sign(RuntimeCall::balances(balances::Call::transfer {
+            to: bob.id(),
+            amount: 2_000_000,
+        }));
+        let dry_run = runtime.dry_run(too_much);
+        assert_eq!(
+            dry_run.result,
+            Ok(Err("Balance not enough for the transfer"))
+        );
+        assert!(matches!(
+            &dry_run.events[..],
+            [support::EventRecord {
+                event: RuntimeEvent::system(system::Event::ExtrinsicFailed {
+                    error: "Balance not enough for the transfer"
                 }),
-            },
-        ],
-    };
+                ..
+            }]
+        ));
+
+        let dry_run = runtime.dry_run(transfer(&mut alice, &bob));
+        assert_eq!(dry_run.result, Err("invalid transaction nonce"));
+        assert!(dry_run.events.is_empty());
+    }
 
-    runtime
-        .execute_block(block_1)
-        .expect("wrong block execution");
-
-    let block_2 = types::Block {
-        header: support::Header { block_number: 2 },
-        extrinsics: vec![
-            support::Extrinsic {
-                caller: alice.clone(),
-                call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
-                    claim: "my_document",
+    #[test]
+    fn calls_are_encoded_with_their_indices() {
+        // The indices of `#[pallet_index(n)]` and `#[call_index(n)]`.
+        let calls = [
+            (
+                RuntimeCall::balances(balances::Call::mint {
+                    who: "bob".to_string(),
+                    amount: 1,
                 }),
-            },
-            support::Extrinsic {
-                caller: bob,
-                call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
-                    claim: "Bobs Doc",
+                [1, 2],
+            ),
+            (
+                RuntimeCall::sudo(sudo::Call::set_key {
+                    new: "bob".to_string(),
                 }),
-            },
-        ],
-    };
+                [3, 1],
+            ),
+            (RuntimeCall::vesting(vesting::Call::vest {}), [6, 0]),
+            (
+                RuntimeCall::timestamp(timestamp::Call::set { now: 1 }),
+                [7, 0],
+            ),
+            (RuntimeCall::aura(aura::Call::note_slot { slot: 1 }), [8, 0]),
+        ];
+        for (call, indices) in calls {
+            let encoded = call.encode();
+            assert_eq!(encoded[..2], indices);
+            assert_eq!(RuntimeCall::decode(&encoded).unwrap().encode(), encoded);
+        }
+        assert_eq!(
+            RuntimeCall::decode(&[9, 0]).err(),
+            Some("invalid pallet index")
+        );
+        assert_eq!(
+            RuntimeCall::decode(&[8, 2]).err(),
+            Some("invalid call index")
+        );
+    }
+
+    #[test]
+    fn metadata_describes_the_pallets() {
+        use support::metadata::{ConstantMetadata, FieldMetadata, VariantMetadata};
+
+        let metadata = Runtime::metadata();
+        let names: Vec<_> = metadata.pallets.iter().map(|pallet| pallet.name).collect();
+        assert_eq!(
+            names,
+            [
+                "system",
+                "balances",
+                "proof_of_existence",
+                "sudo",
+                "multisig",
+                "proxy",
+                "vesting",
+                "timestamp",
+                "aura"
+            ]
+        );
+        assert!(metadata
+            .pallets
+            .iter()
+            .enumerate()
+            .all(|(i, pallet)| pallet.index as usize == i));
+
+        // The index of a pallet and of its calls is the one they are encoded with.
+        let balances = &metadata.pallets[1];
+        let call = RuntimeCall::balances(balances::Call::set_balance {
+            who: "bob".to_string(),
+            amount: 1,
+        });
+        let encoded = call.encode();
+        assert_eq!(encoded[0], balances.index);
+        assert_eq!(
+            balances.calls[1],
+            VariantMetadata {
+                name: "set_balance",
+                index: encoded[1],
+                fields: vec![
+                    FieldMetadata::new::<String>("who"),
+                    FieldMetadata::new::<u128>("amount"),
+                ],
+            }
+        );
+        assert_eq!(balances.events[0].name, "Transfer");
+        assert!(balances
+            .errors
+            .contains(&"Balance not enough for the transfer"));
+        assert_eq!(balances.storage[0].type_name, "BTreeMap<String, u128>");
+
+        let sudo = &metadata.pallets[3];
+        assert_eq!(sudo.calls[0].fields[0].type_name, "Box<RuntimeCall>");
+        // The errors of the helper functions are declared too.
+        assert_eq!(sudo.errors, ["Bad origin", "Caller is not the sudo key"]);
+        let multisig = &metadata.pallets[4];
+        assert!(multisig.errors.contains(&"Threshold must be at least two"));
+        let timestamp = &metadata.pallets[7];
+        assert_eq!(
+            timestamp.constants,
+            [ConstantMetadata::new("MINIMUM_PERIOD", 3_000u64)]
+        );
+        let system = &metadata.pallets[0];
+        assert!(system.calls.is_empty());
+        assert_eq!(system.events[1].name, "ExtrinsicFailed");
+    }
+
+    #[test]
+    fn apply_extrinsic_checks_signature_and_nonce() {
+        let mut runtime = Runtime::new();
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+        runtime
+            .balances
+            .set_balance(RawOrigin::Root.into(), alice.id(), 1_000_000)
+            .unwrap();
+
+        let mut forged = transfer(&mut alice, &bob);
+        forged.signer.as_mut().unwrap().who = bob.id();
+        assert_eq!(
+            runtime.apply_extrinsic(forged),
+            Err("invalid transaction signature")
+        );
+
+        alice.next_nonce = 1;
+        assert_eq!(
+            runtime.apply_extrinsic(transfer(&mut alice, &bob)),
+            Err("invalid transaction nonce")
+        );
+        alice.next_nonce = 0;
+        assert!(matches!(
+            runtime.apply_extrinsic(transfer(&mut alice, &bob)),
+            Ok(Ok(_))
+        ));
+        assert_eq!(runtime.balances.balance(&bob.id()), 1);
+    }
+
+    #[test]
+    fn pool_transactions_are_included_in_order() {
+        let mut genesis = Runtime::new();
+        let mut pool = TransactionPool::new();
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+        genesis
+            .balances
+            .set_balance(RawOrigin::Root.into(), alice.id(), 1_000_000)
+            .unwrap();
+        let mut chain = Chain::new(genesis, ChainConfig::default());
+        let runtime = chain.best_state();
+
+        // The second transfer of alice waits for the first one.
+        let first = transfer(&mut alice, &bob);
+        let second = transfer(&mut alice, &bob);
+        assert!(pool.submit(runtime, second).is_ok());
+        assert!(pool.ready().is_empty());
+        assert!(pool.submit(runtime, first).is_ok());
+        assert_eq!(pool.ready().len(), 2);
+
+        let unsigned = timestamp_inherent(0, 1);
+        assert_eq!(
+            pool.submit(runtime, unsigned),
+            Err("unsigned extrinsics can only be inherents")
+        );
+
+        produce_block(&mut chain, &mut pool, &DEV_POW, 0);
+        let runtime = chain.best_state();
+        assert_eq!(runtime.balances.balance(&bob.id()), 2);
+        assert_eq!(runtime.system.get_nonce(&alice.id()), 2);
+        assert!(pool.ready().is_empty());
+
+        // An outdated transaction is rejected.
+        alice.next_nonce = 0;
+        assert_eq!(
+            pool.submit(runtime, transfer(&mut alice, &bob)),
+            Err("transaction nonce is outdated")
+        );
+    }
+
+    #[test]
+    fn reorg_returns_retracted_transactions_to_the_pool() {
+        let mut genesis = Runtime::new();
+        let mut pool = TransactionPool::new();
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+        genesis
+            .balances
+            .set_balance(RawOrigin::Root.into(), alice.id(), 1_000_000)
+            .unwrap();
+        let mut chain = Chain::new(genesis, ChainConfig::default());
+        let genesis_hash = chain.best_hash();
+
+        let ext = transfer(&mut alice, &bob);
+        assert!(pool.submit(chain.best_state(), ext).is_ok());
+        produce_block(&mut chain, &mut pool, &DEV_POW, 0);
+        assert!(pool.ready().is_empty());
+
+        // A longer fork without the transfer re-orgs the chain.
+        let mut parent = genesis_hash;
+        for block_number in 1..=2 {
+            let mut builder =
+                BlockBuilder::new(chain.state(&parent).unwrap(), support::Digest::default());
+            builder.push(timestamp_inherent(1, block_number)).unwrap();
+            let imported = chain.import_block(builder.finalize()).unwrap();
+            update_pool(&chain, &mut pool, &imported);
+            parent = imported.hash;
+        }
+
+        assert_eq!(chain.best_state().system.block_number(), 2);
+        assert_eq!(chain.best_state().balances.balance(&bob.id()), 0);
+        assert_eq!(pool.ready().len(), 1);
+    }
+
+    #[test]
+    fn reorg_which_finalizes_returns_retracted_transactions_to_the_pool() {
+        let mut genesis = Runtime::new();
+        let mut pool = TransactionPool::new();
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+        genesis
+            .balances
+            .set_balance(RawOrigin::Root.into(), alice.id(), 1_000_000)
+            .unwrap();
+        let config = ChainConfig {
+            finality_depth: Some(1),
+            state_pruning: None,
+        };
+        let mut chain = Chain::new(genesis, config);
+        let genesis_hash = chain.best_hash();
+
+        let ext = transfer(&mut alice, &bob);
+        assert!(pool.submit(chain.best_state(), ext).is_ok());
+        produce_block(&mut chain, &mut pool, &DEV_POW, 0);
+        let retracted = chain.best_hash();
 
-    runtime
-        .execute_block(block_2)
-        .expect("wrong block execution");
+        // The longer fork finalizes its first block, which prunes the block with the transfer.
+        let mut parent = genesis_hash;
+        for block_number in 1..=2 {
+            let mut builder =
+                BlockBuilder::new(chain.state(&parent).unwrap(), support::Digest::default());
+            builder.push(timestamp_inherent(1, block_number)).unwrap();
+            let imported = chain.import_block(builder.finalize()).unwrap();
+            update_pool(&chain, &mut pool, &imported);
+            parent = imported.hash;
+        }
 
-    // Simply print the debug format of our runtime state.
-    println!("{:#?}", runtime);
+        assert!(chain.block(&retracted).is_none());
+        assert_eq!(chain.best_state().balances.balance(&bob.id()), 0);
+        assert_eq!(pool.ready().len(), 1);
+    }
 }
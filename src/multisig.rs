@@ -0,0 +1,644 @@
+use core::fmt::Debug;
+use std::collections::BTreeMap;
+
+use num::traits::CheckedAdd;
+
+use crate::support::{
+    codec::{Decode, Encode},
+    ensure_signed,
+    hashing::{hash, Hash},
+    metadata::{ConstantMetadata, FieldMetadata, PalletInfo},
+    Dispatch, DispatchResult, DispatchResultWithPostInfo, FromEntropy, GetDispatchInfo, GetPallet,
+    Hooks, PostDispatchInfo, RawOrigin, DEFAULT_WEIGHT,
+};
+
+pub trait Config:
+    crate::balances::Config
+    + Sized
+    + GetPallet<Pallet<Self>>
+    + GetPallet<crate::balances::Pallet<Self>>
+    + Dispatch<Caller = Self::RuntimeOrigin, Call = <Self as Config>::RuntimeCall>
+{
+    /// The outer call type of the runtime, which a multisig account can dispatch.
+    type RuntimeCall: Encode + GetDispatchInfo;
+    /// The base amount reserved by the signatory opening a multisig operation.
+    const DEPOSIT_BASE: Self::Balance;
+    /// The amount reserved for each unit of threshold, on top of `DEPOSIT_BASE`.
+    const DEPOSIT_FACTOR: Self::Balance;
+    /// The maximum number of signatories of a multisig account.
+    const MAX_SIGNATORIES: u16;
+}
+
+/// An open multisig operation, waiting for enough approvals to dispatch its call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Multisig<AccountId, Balance> {
+    /// The signatory which opened the operation, and reserved the deposit.
+    pub depositor: AccountId,
+    /// The amount reserved by the depositor until the operation is executed or cancelled.
+    pub deposit: Balance,
+    /// The signatories which approved the operation so far.
+    pub approvals: Vec<AccountId>,
+}
+
+impl<AccountId: Encode, Balance: Encode> Encode for Multisig<AccountId, Balance> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.depositor.encode_to(dest);
+        self.deposit.encode_to(dest);
+        self.approvals.encode_to(dest);
+    }
+}
+
+impl<AccountId: Decode, Balance: Decode> Decode for Multisig<AccountId, Balance> {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        Ok(Self {
+            depositor: Decode::decode_from(input)?,
+            deposit: Decode::decode_from(input)?,
+            approvals: Decode::decode_from(input)?,
+        })
+    }
+}
+
+/// A `Multisig` with the types of the runtime `T`.
+pub type MultisigOf<T> =
+    Multisig<<T as crate::system::Config>::AccountId, <T as crate::balances::Config>::Balance>;
+
+/// This is the Multisig Module.
+/// It allows a set of signatories to share an account, which dispatches a call once enough of
+/// them (the threshold) approved it.
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+    /// The open operations, by multisig account and hash of the call.
+    #[allow(clippy::type_complexity)]
+    multisigs: BTreeMap<(T::AccountId, Hash), Multisig<T::AccountId, T::Balance>>,
+}
+
+#[macros::call]
+#[errors(
+    "Bad origin",
+    "Threshold must be at least two",
+    "Duplicate signatories",
+    "Too many signatories",
+    "Threshold is higher than the number of signatories",
+    "Caller already approved the multisig operation",
+    "Overflow to add balance",
+    "Balance not enough for the reserve",
+    "Balance is locked",
+    "Reserved balance not enough for the unreserve",
+    "Multisig operation does not exists",
+    "Caller is not the depositor of the multisig operation"
+)]
+impl<T: Config> Pallet<T> {
+    /// Approve the call with hash `call_hash` on behalf of the multisig account made of the signer
+    /// of `origin`, `other_signatories` and `threshold`.
+    /// The first approval opens the operation, and reserves a deposit from the signer.
+    /// Note that the call can only be dispatched by `as_multi`, since only its hash is known here.
+    #[call_index(0)]
+    pub fn approve_as_multi(
+        runtime: &mut T,
+        origin: T::RuntimeOrigin,
+        threshold: u16,
+        other_signatories: Vec<T::AccountId>,
+        call_hash: Hash,
+    ) -> DispatchResult {
+        let who = ensure_signed(origin)?;
+        let signatories = Self::signatories(who.clone(), threshold, other_signatories)?;
+        let id = Self::multi_account_id(&signatories, threshold);
+
+        Self::approve(runtime, who, id, threshold, call_hash)?;
+        Ok(())
+    }
+
+    /// Approve `call` on behalf of the multisig account made of the signer of `origin`,
+    /// `other_signatories` and `threshold`.
+    /// Once the operation has `threshold` approvals, `call` is dispatched with the multisig
+    /// account as signed origin, and the deposit is returned if it succeeds. Until then, the
+    /// weight of `call` is refunded.
+    // The `call` is boxed since `Call` would otherwise be a recursive type.
+    #[allow(clippy::boxed_local)]
+    #[call_index(1)]
+    #[weight(DEFAULT_WEIGHT.saturating_add(call.get_dispatch_info().weight))]
+    pub fn as_multi(
+        runtime: &mut T,
+        origin: T::RuntimeOrigin,
+        threshold: u16,
+        other_signatories: Vec<T::AccountId>,
+        call: Box<T::RuntimeCall>,
+    ) -> DispatchResultWithPostInfo {
+        let who = ensure_signed(origin)?;
+        let signatories = Self::signatories(who.clone(), threshold, other_signatories)?;
+        let id = Self::multi_account_id(&signatories, threshold);
+        let call_hash = hash(&call.encode());
+
+        // The signer may already have approved an operation which has enough approvals, in which
+        // case it just executes it.
+        let pallet: &Pallet<T> = runtime.pallet();
+        let approvals = match pallet.multisig(&id, &call_hash) {
+            Some(multisig)
+                if multisig.approvals.contains(&who)
+                    && multisig.approvals.len() >= threshold as usize =>
+            {
+                multisig.approvals.len()
+            }
+            _ => Self::approve(runtime, who, id.clone(), threshold, call_hash)?,
+        };
+        if approvals < threshold as usize {
+            return Ok(PostDispatchInfo {
+                actual_weight: Some(DEFAULT_WEIGHT),
+            });
+        }
+
+        // The operation is only closed once its call succeeded, so it can be retried otherwise.
+        runtime.dispatch(RawOrigin::Signed(id.clone()).into(), *call)?;
+        Self::close(runtime, &id, &call_hash)?;
+        Ok(().into())
+    }
+
+    /// Cancel the open operation for the call with hash `call_hash`, returning the deposit.
+    /// This function will return an error if the signer of `origin` is not the depositor.
+    #[call_index(2)]
+    pub fn cancel_as_multi(
+        runtime: &mut T,
+        origin: T::RuntimeOrigin,
+        threshold: u16,
+        other_signatories: Vec<T::AccountId>,
+        call_hash: Hash,
+    ) -> DispatchResult {
+        let who = ensure_signed(origin)?;
+        let signatories = Self::signatories(who.clone(), threshold, other_signatories)?;
+        let id = Self::multi_account_id(&signatories, threshold);
+
+        let pallet: &Pallet<T> = runtime.pallet();
+        let multisig = pallet
+            .multisig(&id, &call_hash)
+            .ok_or("Multisig operation does not exists")?;
+        if multisig.depositor != who {
+            return Err("Caller is not the depositor of the multisig operation");
+        }
+
+        Self::close(runtime, &id, &call_hash)
+    }
+}
+
+impl<T: Config> Pallet<T> {
+    /// Create a new instance of the Multisig Module.
+    pub fn new() -> Self {
+        Self {
+            multisigs: BTreeMap::new(),
+        }
+    }
+
+    /// Get the open operation (if any) of the multisig account `id` for the call `call_hash`.
+    pub fn multisig(&self, id: &T::AccountId, call_hash: &Hash) -> Option<&MultisigOf<T>> {
+        self.multisigs.get(&(id.clone(), *call_hash))
+    }
+
+    /// Derive the account of the multisig made of the sorted `signatories` and `threshold`.
+    pub fn multi_account_id(signatories: &[T::AccountId], threshold: u16) -> T::AccountId {
+        let mut entropy = Vec::new();
+        "multisig".encode_to(&mut entropy);
+        signatories.encode_to(&mut entropy);
+        threshold.encode_to(&mut entropy);
+
+        T::AccountId::from_entropy(&hash(&entropy))
+    }
+
+    /// Check the multisig made of `who`, `other_signatories` and `threshold`, and return all of its
+    /// signatories sorted, so the multisig account does not depend on the order they are given.
+    fn signatories(
+        who: T::AccountId,
+        threshold: u16,
+        mut other_signatories: Vec<T::AccountId>,
+    ) -> Result<Vec<T::AccountId>, &'static str> {
+        if threshold < 2 {
+            return Err("Threshold must be at least two");
+        }
+
+        other_signatories.push(who);
+        other_signatories.sort();
+        if other_signatories.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err("Duplicate signatories");
+        }
+        if other_signatories.len() > T::MAX_SIGNATORIES as usize {
+            return Err("Too many signatories");
+        }
+        if other_signatories.len() < threshold as usize {
+            return Err("Threshold is higher than the number of signatories");
+        }
+
+        Ok(other_signatories)
+    }
+
+    /// Add the approval of `who` to the operation of the multisig account `id` for the call
+    /// `call_hash`, opening it and reserving the deposit if it does not exist yet.
+    /// Returns the number of approvals of the operation.
+    fn approve(
+        runtime: &mut T,
+        who: T::AccountId,
+        id: T::AccountId,
+        threshold: u16,
+        call_hash: Hash,
+    ) -> Result<usize, &'static str> {
+        let pallet: &mut Pallet<T> = runtime.pallet_mut();
+        if let Some(multisig) = pallet.multisigs.get_mut(&(id.clone(), call_hash)) {
+            if multisig.approvals.contains(&who) {
+                return Err("Caller already approved the multisig operation");
+            }
+            multisig.approvals.push(who);
+            return Ok(multisig.approvals.len());
+        }
+
+        let deposit = (0..threshold)
+            .try_fold(T::DEPOSIT_BASE, |deposit, _| {
+                deposit.checked_add(&T::DEPOSIT_FACTOR)
+            })
+            .ok_or("Overflow to add balance")?;
+        let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+        balances.reserve(&who, deposit)?;
+
+        let pallet: &mut Pallet<T> = runtime.pallet_mut();
+        let multisig = Multisig {
+            depositor: who.clone(),
+            deposit,
+            approvals: vec![who],
+        };
+        pallet.multisigs.insert((id, call_hash), multisig);
+        Ok(1)
+    }
+
+    /// Remove the operation of the multisig account `id` for the call `call_hash`, and return the
+    /// deposit to the depositor.
+    fn close(runtime: &mut T, id: &T::AccountId, call_hash: &Hash) -> DispatchResult {
+        let pallet: &mut Pallet<T> = runtime.pallet_mut();
+        let multisig = pallet
+            .multisigs
+            .remove(&(id.clone(), *call_hash))
+            .ok_or("Multisig operation does not exists")?;
+
+        let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+        balances.unreserve(&multisig.depositor, multisig.deposit)
+    }
+}
+
+impl<T: Config> Encode for Pallet<T>
+where
+    T::Balance: Encode,
+{
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.multisigs.encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for Pallet<T>
+where
+    T::AccountId: Decode,
+    T::Balance: Decode,
+{
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        Ok(Self {
+            multisigs: Decode::decode_from(input)?,
+        })
+    }
+}
+
+impl<T: Config> PalletInfo for Pallet<T>
+where
+    T::Balance: Debug,
+{
+    fn storage() -> Vec<FieldMetadata> {
+        vec![FieldMetadata::new::<
+            BTreeMap<(T::AccountId, Hash), Multisig<T::AccountId, T::Balance>>,
+        >("multisigs")]
+    }
+
+    fn constants() -> Vec<ConstantMetadata> {
+        vec![
+            ConstantMetadata::new("DEPOSIT_BASE", T::DEPOSIT_BASE),
+            ConstantMetadata::new("DEPOSIT_FACTOR", T::DEPOSIT_FACTOR),
+            ConstantMetadata::new("MAX_SIGNATORIES", T::MAX_SIGNATORIES),
+        ]
+    }
+}
+
+impl<T: Config> Hooks<T, T::BlockNumber> for Pallet<T> {
+    fn integrity_test() {
+        assert!(
+            T::MAX_SIGNATORIES >= 2,
+            "a multisig account must allow at least two signatories"
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::support::{
+        codec::Encode, hashing::hash, Dispatch, DispatchResultWithPostInfo, GetPallet,
+        PostDispatchInfo, RawOrigin, DEFAULT_WEIGHT,
+    };
+
+    struct TestRuntime {
+        balances: crate::balances::Pallet<TestRuntime>,
+        multisig: super::Pallet<TestRuntime>,
+        dispatched: Vec<(RawOrigin<String>, &'static str)>,
+    }
+
+    impl super::Config for TestRuntime {
+        type RuntimeCall = &'static str;
+        const DEPOSIT_BASE: u32 = 10;
+        const DEPOSIT_FACTOR: u32 = 1;
+        const MAX_SIGNATORIES: u16 = 3;
+    }
+
+    impl crate::balances::Config for TestRuntime {
+        type Balance = u32;
+    }
+
+    impl crate::system::Config for TestRuntime {
+        type AccountId = String;
+        type RuntimeOrigin = RawOrigin<String>;
+        type BlockNumber = u32;
+        type Nonce = u32;
+        type RuntimeEvent = crate::system::Event;
+    }
+
+    impl GetPallet<crate::balances::Pallet<Self>> for TestRuntime {
+        fn pallet(&self) -> &crate::balances::Pallet<Self> {
+            &self.balances
+        }
+        fn pallet_mut(&mut self) -> &mut crate::balances::Pallet<Self> {
+            &mut self.balances
+        }
+    }
+
+    impl GetPallet<super::Pallet<Self>> for TestRuntime {
+        fn pallet(&self) -> &super::Pallet<Self> {
+            &self.multisig
+        }
+        fn pallet_mut(&mut self) -> &mut super::Pallet<Self> {
+            &mut self.multisig
+        }
+    }
+
+    impl Dispatch for TestRuntime {
+        type Caller = RawOrigin<String>;
+        type Call = &'static str;
+
+        fn dispatch(
+            &mut self,
+            caller: Self::Caller,
+            call: Self::Call,
+        ) -> DispatchResultWithPostInfo {
+            if call == "failing_call" {
+                return Err("Call failed");
+            }
+            self.dispatched.push((caller, call));
+            Ok(().into())
+        }
+    }
+
+    fn new_runtime() -> TestRuntime {
+        let mut runtime = TestRuntime {
+            balances: crate::balances::Pallet::new(),
+            multisig: super::Pallet::new(),
+            dispatched: vec![],
+        };
+        for who in ["alice", "bob", "charlie"] {
+            let _ = runtime
+                .balances
+                .set_balance(RawOrigin::Root, who.to_string(), 100);
+        }
+        runtime
+    }
+
+    fn signed(who: &str) -> RawOrigin<String> {
+        RawOrigin::Signed(who.to_string())
+    }
+
+    fn others(who: &[&str]) -> Vec<String> {
+        who.iter().map(|who| who.to_string()).collect()
+    }
+
+    fn multi_account_id() -> String {
+        super::Pallet::<TestRuntime>::multi_account_id(&others(&["alice", "bob", "charlie"]), 2)
+    }
+
+    #[test]
+    fn multi_account_id_is_deterministic() {
+        let signatories = others(&["alice", "bob", "charlie"]);
+        let id = super::Pallet::<TestRuntime>::multi_account_id(&signatories, 2);
+
+        assert_eq!(id, multi_account_id());
+        assert_ne!(
+            id,
+            super::Pallet::<TestRuntime>::multi_account_id(&signatories, 3)
+        );
+    }
+
+    #[test]
+    fn as_multi_dispatches_with_enough_approvals() {
+        let mut runtime = new_runtime();
+
+        let res = super::Pallet::as_multi(
+            &mut runtime,
+            signed("charlie"),
+            2,
+            others(&["bob", "alice"]),
+            Box::new("my_call"),
+        );
+        // The call is not dispatched yet, so its weight is refunded.
+        let post_info = PostDispatchInfo {
+            actual_weight: Some(DEFAULT_WEIGHT),
+        };
+        assert_eq!(res, Ok(post_info));
+        assert!(runtime.dispatched.is_empty());
+        assert_eq!(
+            runtime.balances.reserved_balance(&"charlie".to_string()),
+            12
+        );
+
+        let res = super::Pallet::as_multi(
+            &mut runtime,
+            signed("alice"),
+            2,
+            others(&["bob", "charlie"]),
+            Box::new("my_call"),
+        );
+        assert_eq!(res, Ok(PostDispatchInfo::default()));
+        assert_eq!(
+            runtime.dispatched,
+            vec![(RawOrigin::Signed(multi_account_id()), "my_call")]
+        );
+        assert_eq!(runtime.balances.reserved_balance(&"charlie".to_string()), 0);
+        assert_eq!(runtime.balances.balance(&"charlie".to_string()), 100);
+        assert_eq!(
+            runtime
+                .multisig
+                .multisig(&multi_account_id(), &hash(&"my_call".encode())),
+            None
+        );
+    }
+
+    #[test]
+    fn as_multi_keeps_the_operation_when_the_call_fails() {
+        let mut runtime = new_runtime();
+        let call_hash = hash(&"failing_call".encode());
+
+        let res = super::Pallet::as_multi(
+            &mut runtime,
+            signed("charlie"),
+            2,
+            others(&["bob", "alice"]),
+            Box::new("failing_call"),
+        );
+        assert!(res.is_ok());
+        let res = super::Pallet::as_multi(
+            &mut runtime,
+            signed("alice"),
+            2,
+            others(&["bob", "charlie"]),
+            Box::new("failing_call"),
+        );
+        assert_eq!(res, Err("Call failed"));
+
+        // The operation is still open, and the deposit still reserved.
+        assert!(runtime
+            .multisig
+            .multisig(&multi_account_id(), &call_hash)
+            .is_some());
+        assert_eq!(
+            runtime.balances.reserved_balance(&"charlie".to_string()),
+            12
+        );
+        assert!(runtime.dispatched.is_empty());
+    }
+
+    #[test]
+    fn approve_as_multi_then_as_multi() {
+        let mut runtime = new_runtime();
+        let call_hash = hash(&"my_call".encode());
+
+        let res = super::Pallet::approve_as_multi(
+            &mut runtime,
+            signed("alice"),
+            2,
+            others(&["bob", "charlie"]),
+            call_hash,
+        );
+        assert_eq!(res, Ok(()));
+
+        let res = super::Pallet::approve_as_multi(
+            &mut runtime,
+            signed("alice"),
+            2,
+            others(&["bob", "charlie"]),
+            call_hash,
+        );
+        assert_eq!(res, Err("Caller already approved the multisig operation"));
+
+        let res = super::Pallet::approve_as_multi(
+            &mut runtime,
+            signed("bob"),
+            2,
+            others(&["alice", "charlie"]),
+            call_hash,
+        );
+        assert_eq!(res, Ok(()));
+        assert!(runtime.dispatched.is_empty());
+
+        // Alice already approved, but the operation has enough approvals to be executed.
+        let res = super::Pallet::as_multi(
+            &mut runtime,
+            signed("alice"),
+            2,
+            others(&["bob", "charlie"]),
+            Box::new("my_call"),
+        );
+        assert_eq!(res, Ok(PostDispatchInfo::default()));
+        assert_eq!(
+            runtime.dispatched,
+            vec![(RawOrigin::Signed(multi_account_id()), "my_call")]
+        );
+        assert_eq!(runtime.balances.reserved_balance(&"alice".to_string()), 0);
+    }
+
+    #[test]
+    fn cancel_as_multi() {
+        let mut runtime = new_runtime();
+        let call_hash = hash(&"my_call".encode());
+
+        let res = super::Pallet::cancel_as_multi(
+            &mut runtime,
+            signed("alice"),
+            2,
+            others(&["bob", "charlie"]),
+            call_hash,
+        );
+        assert_eq!(res, Err("Multisig operation does not exists"));
+
+        let _ = super::Pallet::approve_as_multi(
+            &mut runtime,
+            signed("alice"),
+            2,
+            others(&["bob", "charlie"]),
+            call_hash,
+        );
+
+        let res = super::Pallet::cancel_as_multi(
+            &mut runtime,
+            signed("bob"),
+            2,
+            others(&["alice", "charlie"]),
+            call_hash,
+        );
+        assert_eq!(
+            res,
+            Err("Caller is not the depositor of the multisig operation")
+        );
+
+        let res = super::Pallet::cancel_as_multi(
+            &mut runtime,
+            signed("alice"),
+            2,
+            others(&["bob", "charlie"]),
+            call_hash,
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(runtime.balances.reserved_balance(&"alice".to_string()), 0);
+        assert_eq!(runtime.balances.balance(&"alice".to_string()), 100);
+        assert_eq!(
+            runtime.multisig.multisig(&multi_account_id(), &call_hash),
+            None
+        );
+    }
+
+    #[test]
+    fn invalid_signatories() {
+        let mut runtime = new_runtime();
+        let call_hash = hash(&"my_call".encode());
+        let mut approve = |who, threshold, other_signatories| {
+            super::Pallet::approve_as_multi(
+                &mut runtime,
+                signed(who),
+                threshold,
+                others(other_signatories),
+                call_hash,
+            )
+        };
+
+        assert_eq!(
+            approve("alice", 1, &["bob"]),
+            Err("Threshold must be at least two")
+        );
+        assert_eq!(
+            approve("alice", 2, &["alice", "bob"]),
+            Err("Duplicate signatories")
+        );
+        assert_eq!(
+            approve("alice", 2, &["bob", "charlie", "dave"]),
+            Err("Too many signatories")
+        );
+        assert_eq!(
+            approve("alice", 3, &["bob"]),
+            Err("Threshold is higher than the number of signatories")
+        );
+    }
+}
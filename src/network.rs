@@ -0,0 +1,576 @@
+//! A simple peer-to-peer network over TCP, which the nodes of a chain use to gossip blocks and
+//! transactions, and to sync their chain with their peers.
+//!
+//! Every message is encoded with `codec`, and prefixed with its length as a `u32`. The first
+//! message on a connection is a `Handshake` in both directions: connections to nodes with another
+//! genesis block are closed. Each connection is read by its own thread, which forwards the
+//! messages of the peer to the node as `Event`s. Writes to a peer time out after `WRITE_TIMEOUT`,
+//! so that a peer which stops reading can not hold up the node.
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    support::{
+        codec::{Decode, Encode},
+        hashing::Hash,
+    },
+    types,
+};
+
+/// The maximum length of an encoded message, which is far more than a block can take.
+pub const MAX_MESSAGE_LENGTH: u32 = 1024 * 1024;
+
+/// How long a write to a peer may block before the peer is disconnected.
+pub const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The id of a peer, unique for the lifetime of the network.
+pub type PeerId = u64;
+
+/// The best and finalized blocks of a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Status {
+    pub best_number: types::BlockNumber,
+    pub best_hash: Hash,
+    pub finalized_number: types::BlockNumber,
+    pub finalized_hash: Hash,
+}
+
+impl Encode for Status {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.best_number.encode_to(dest);
+        self.best_hash.encode_to(dest);
+        self.finalized_number.encode_to(dest);
+        self.finalized_hash.encode_to(dest);
+    }
+}
+
+impl Decode for Status {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        Ok(Self {
+            best_number: Decode::decode_from(input)?,
+            best_hash: Decode::decode_from(input)?,
+            finalized_number: Decode::decode_from(input)?,
+            finalized_hash: Decode::decode_from(input)?,
+        })
+    }
+}
+
+/// A part of the encoded state after a block, answering a `StateRequest`.
+#[derive(Clone)]
+pub struct StateChunk {
+    /// The block the state is after.
+    pub block: types::Block,
+    /// The length of the whole encoded state.
+    pub total: u32,
+    /// The encoded state from the requested `start` on, which may stop before its end.
+    pub data: Vec<u8>,
+}
+
+impl Encode for StateChunk {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.block.encode_to(dest);
+        self.total.encode_to(dest);
+        self.data.encode_to(dest);
+    }
+}
+
+impl Decode for StateChunk {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        Ok(Self {
+            block: Decode::decode_from(input)?,
+            total: Decode::decode_from(input)?,
+            data: Decode::decode_from(input)?,
+        })
+    }
+}
+
+/// The messages nodes exchange.
+#[derive(Clone)]
+pub enum Message {
+    /// The first message on a connection: the genesis of the chain of the node, and its status.
+    Handshake { genesis_hash: Hash, status: Status },
+    /// A new block was imported by the node.
+    BlockAnnounce { header: types::Header },
+    /// Ask for the headers of the block `from` and of its ancestors, from the highest down, and
+    /// at most `max` of them.
+    HeadersRequest { from: Hash, max: u32 },
+    /// The headers answering a `HeadersRequest`, which is empty if the node does not know `from`.
+    Headers { headers: Vec<types::Header> },
+    /// Ask for the extrinsics of the blocks with the `hashes`.
+    BodiesRequest { hashes: Vec<Hash> },
+    /// The extrinsics answering a `BodiesRequest`, in the order of the request, up to the first
+    /// block the node does not know.
+    Bodies { bodies: Vec<Vec<types::Extrinsic>> },
+    /// Ask for the encoded state after the block with the `hash`, from the byte `start` on. A
+    /// state is sent in chunks, since it may not fit in a message.
+    StateRequest { hash: Hash, start: u32 },
+    /// The chunk of the state answering a `StateRequest`, or `None` if the node does not have the
+    /// state of the block.
+    StateResponse { snapshot: Option<StateChunk> },
+    /// Transactions for the transaction pool of the peer.
+    Transactions { extrinsics: Vec<types::Extrinsic> },
+}
+
+impl Encode for Message {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            Message::Handshake {
+                genesis_hash,
+                status,
+            } => {
+                dest.push(0);
+                genesis_hash.encode_to(dest);
+                status.encode_to(dest);
+            }
+            Message::BlockAnnounce { header } => {
+                dest.push(1);
+                header.encode_to(dest);
+            }
+            Message::HeadersRequest { from, max } => {
+                dest.push(2);
+                from.encode_to(dest);
+                max.encode_to(dest);
+            }
+            Message::Headers { headers } => {
+                dest.push(3);
+                headers.encode_to(dest);
+            }
+            Message::BodiesRequest { hashes } => {
+                dest.push(4);
+                hashes.encode_to(dest);
+            }
+            Message::Bodies { bodies } => {
+                dest.push(5);
+                bodies.encode_to(dest);
+            }
+            Message::StateRequest { hash, start } => {
+                dest.push(6);
+                hash.encode_to(dest);
+                start.encode_to(dest);
+            }
+            Message::StateResponse { snapshot } => {
+                dest.push(7);
+                snapshot.encode_to(dest);
+            }
+            Message::Transactions { extrinsics } => {
+                dest.push(8);
+                extrinsics.encode_to(dest);
+            }
+        }
+    }
+}
+
+impl Decode for Message {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        match u8::decode_from(input)? {
+            0 => Ok(Message::Handshake {
+                genesis_hash: Decode::decode_from(input)?,
+                status: Decode::decode_from(input)?,
+            }),
+            1 => Ok(Message::BlockAnnounce {
+                header: Decode::decode_from(input)?,
+            }),
+            2 => Ok(Message::HeadersRequest {
+                from: Decode::decode_from(input)?,
+                max: Decode::decode_from(input)?,
+            }),
+            3 => Ok(Message::Headers {
+                headers: Decode::decode_from(input)?,
+            }),
+            4 => Ok(Message::BodiesRequest {
+                hashes: Decode::decode_from(input)?,
+            }),
+            5 => Ok(Message::Bodies {
+                bodies: Decode::decode_from(input)?,
+            }),
+            6 => Ok(Message::StateRequest {
+                hash: Decode::decode_from(input)?,
+                start: Decode::decode_from(input)?,
+            }),
+            7 => Ok(Message::StateResponse {
+                snapshot: Decode::decode_from(input)?,
+            }),
+            8 => Ok(Message::Transactions {
+                extrinsics: Decode::decode_from(input)?,
+            }),
+            _ => Err("invalid message"),
+        }
+    }
+}
+
+/// Write `message` to `stream`, prefixed with its length. A message longer than
+/// `MAX_MESSAGE_LENGTH`, which the peer would reject, is not written at all.
+pub fn write_message(stream: &mut impl Write, message: &Message) -> io::Result<()> {
+    let payload = message.encode();
+    if payload.len() > MAX_MESSAGE_LENGTH as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Message is too long",
+        ));
+    }
+    let mut frame = (payload.len() as u32).encode();
+    frame.extend_from_slice(&payload);
+    stream.write_all(&frame)
+}
+
+/// Read a message written by `write_message` from `stream`.
+pub fn read_message(stream: &mut impl Read) -> io::Result<Message> {
+    let mut len = [0; 4];
+    stream.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len);
+    if len > MAX_MESSAGE_LENGTH {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Message is too long",
+        ));
+    }
+
+    let mut payload = vec![0; len as usize];
+    stream.read_exact(&mut payload)?;
+    Message::decode(&payload).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// What happens on the network, which the node reacts to.
+pub enum Event {
+    /// A peer of the same chain connected, with the status of its handshake.
+    Connected { peer: PeerId, status: Status },
+    /// A peer sent a message, besides its handshake.
+    Message { peer: PeerId, message: Message },
+    /// A peer disconnected.
+    Disconnected { peer: PeerId },
+}
+
+/// The state shared by the network and the threads of its connections.
+struct Shared {
+    /// The hash of the genesis block, which peers must share.
+    genesis_hash: Hash,
+    /// The status of the node, which is sent in the handshake.
+    status: Mutex<Status>,
+    /// The connected peers, with the stream to write messages to them. Each stream has its own
+    /// lock, so that a slow peer does not hold up the writes to the others.
+    peers: Mutex<BTreeMap<PeerId, Arc<Mutex<TcpStream>>>>,
+    next_peer: AtomicU64,
+    events: mpsc::Sender<Event>,
+}
+
+/// This is the Network.
+/// It accepts connections on a local address, connects to other nodes, and turns the messages of
+/// its peers into `Event`s.
+pub struct Network {
+    local_addr: SocketAddr,
+    shared: Arc<Shared>,
+    events: mpsc::Receiver<Event>,
+}
+
+impl Network {
+    /// Start listening on `addr` for peers of the chain with the `genesis_hash`, where the node has
+    /// the `status`.
+    pub fn start(addr: impl ToSocketAddrs, genesis_hash: Hash, status: Status) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let (sender, events) = mpsc::channel();
+        let shared = Arc::new(Shared {
+            genesis_hash,
+            status: Mutex::new(status),
+            peers: Mutex::new(BTreeMap::new()),
+            next_peer: AtomicU64::new(0),
+            events: sender,
+        });
+
+        let accepting = shared.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let shared = accepting.clone();
+                thread::spawn(move || run_connection(shared, stream));
+            }
+        });
+
+        Ok(Self {
+            local_addr,
+            shared,
+            events,
+        })
+    }
+
+    /// The address the network accepts connections on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Connect to the node listening on `addr`.
+    pub fn connect(&self, addr: impl ToSocketAddrs) -> io::Result<()> {
+        let stream = TcpStream::connect(addr)?;
+        let shared = self.shared.clone();
+        thread::spawn(move || run_connection(shared, stream));
+        Ok(())
+    }
+
+    /// Record the status of the node, for the handshake of the next connections.
+    pub fn set_status(&self, status: Status) {
+        *self.shared.status.lock().expect("lock is not poisoned") = status;
+    }
+
+    /// Send `message` to `peer`. A peer which can not be written to is disconnected, but a message
+    /// which is too long is dropped without writing anything.
+    pub fn send(&self, peer: PeerId, message: &Message) {
+        let stream = self
+            .shared
+            .peers
+            .lock()
+            .expect("lock is not poisoned")
+            .get(&peer)
+            .cloned();
+        let Some(stream) = stream else {
+            return;
+        };
+
+        let mut stream = stream.lock().expect("lock is not poisoned");
+        match write_message(&mut *stream, message) {
+            Err(e) if e.kind() != io::ErrorKind::InvalidInput => {
+                let _ = stream.shutdown(std::net::Shutdown::Both);
+                drop(stream);
+                self.shared
+                    .peers
+                    .lock()
+                    .expect("lock is not poisoned")
+                    .remove(&peer);
+            }
+            _ => {}
+        }
+    }
+
+    /// Send `message` to every peer, except `except`, e.g. the peer the message came from.
+    pub fn broadcast(&self, message: &Message, except: Option<PeerId>) {
+        let peers = self
+            .shared
+            .peers
+            .lock()
+            .expect("lock is not poisoned")
+            .keys()
+            .copied()
+            .filter(|peer| Some(*peer) != except)
+            .collect::<Vec<_>>();
+        for peer in peers {
+            self.send(peer, message);
+        }
+    }
+
+    /// Wait up to `timeout` for the next event.
+    pub fn next_event(&self, timeout: Duration) -> Option<Event> {
+        self.events.recv_timeout(timeout).ok()
+    }
+}
+
+/// Exchange handshakes with the node at the other end of `stream`, then forward its messages
+/// until the connection is closed.
+fn run_connection(shared: Arc<Shared>, mut stream: TcpStream) {
+    if stream.set_write_timeout(Some(WRITE_TIMEOUT)).is_err() {
+        return;
+    }
+    let handshake = Message::Handshake {
+        genesis_hash: shared.genesis_hash,
+        status: *shared.status.lock().expect("lock is not poisoned"),
+    };
+    if write_message(&mut stream, &handshake).is_err() {
+        return;
+    }
+    let status = match read_message(&mut stream) {
+        Ok(Message::Handshake {
+            genesis_hash,
+            status,
+        }) if genesis_hash == shared.genesis_hash => status,
+        // Peers of another chain, or which do not start with a handshake, are dropped.
+        _ => return,
+    };
+
+    let Ok(writer) = stream.try_clone() else {
+        return;
+    };
+    let peer = shared.next_peer.fetch_add(1, Ordering::Relaxed);
+    shared
+        .peers
+        .lock()
+        .expect("lock is not poisoned")
+        .insert(peer, Arc::new(Mutex::new(writer)));
+    let _ = shared.events.send(Event::Connected { peer, status });
+
+    while let Ok(message) = read_message(&mut stream) {
+        if shared
+            .events
+            .send(Event::Message { peer, message })
+            .is_err()
+        {
+            break;
+        }
+    }
+
+    shared
+        .peers
+        .lock()
+        .expect("lock is not poisoned")
+        .remove(&peer);
+    let _ = shared.events.send(Event::Disconnected { peer });
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{
+        read_message, write_message, Event, Message, Network, StateChunk, Status,
+        MAX_MESSAGE_LENGTH,
+    };
+    use crate::{
+        support::{self, codec::Encode},
+        timestamp_inherent, types,
+    };
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    // The status of a node whose best and finalized block is the block `number`, with the hash
+    // `[number; 32]`.
+    fn status(number: u8) -> Status {
+        Status {
+            best_number: number as types::BlockNumber,
+            best_hash: [number; 32],
+            finalized_number: number as types::BlockNumber,
+            finalized_hash: [number; 32],
+        }
+    }
+
+    fn block() -> types::Block {
+        let extrinsics = vec![timestamp_inherent(0, 1)];
+        let header = support::Header {
+            block_number: 1,
+            parent_hash: [1; 32],
+            state_root: [2; 32],
+            extrinsics_root: support::extrinsics_root(&extrinsics),
+            digest: Default::default(),
+        };
+        types::Block { header, extrinsics }
+    }
+
+    #[test]
+    fn messages_are_length_prefixed() {
+        let message = Message::StateResponse {
+            snapshot: Some(StateChunk {
+                block: block(),
+                total: 5,
+                data: vec![1, 2, 3],
+            }),
+        };
+        let mut stream = Vec::new();
+        write_message(&mut stream, &message).unwrap();
+        let request = Message::StateRequest {
+            hash: [2; 32],
+            start: 3,
+        };
+        write_message(&mut stream, &request).unwrap();
+
+        let mut reader = stream.as_slice();
+        let Message::StateResponse {
+            snapshot: Some(chunk),
+        } = read_message(&mut reader).unwrap()
+        else {
+            panic!("expected a state response");
+        };
+        assert_eq!(chunk.block.encode(), block().encode());
+        assert_eq!(chunk.total, 5);
+        assert_eq!(chunk.data, vec![1, 2, 3]);
+        assert!(matches!(
+            read_message(&mut reader).unwrap(),
+            Message::StateRequest {
+                hash: [2, ..],
+                start: 3
+            }
+        ));
+        assert!(read_message(&mut reader).is_err());
+
+        let too_long = (MAX_MESSAGE_LENGTH + 1).encode();
+        assert!(read_message(&mut too_long.as_slice()).is_err());
+    }
+
+    #[test]
+    fn too_long_messages_are_not_written() {
+        let message = Message::StateResponse {
+            snapshot: Some(StateChunk {
+                block: block(),
+                total: MAX_MESSAGE_LENGTH,
+                data: vec![0; MAX_MESSAGE_LENGTH as usize],
+            }),
+        };
+        let mut stream = Vec::new();
+        assert!(write_message(&mut stream, &message).is_err());
+        assert!(stream.is_empty());
+    }
+
+    #[test]
+    fn peers_exchange_messages() {
+        let a = Network::start("127.0.0.1:0", [0; 32], status(0)).unwrap();
+        let b = Network::start("127.0.0.1:0", [0; 32], status(3)).unwrap();
+        a.connect(b.local_addr()).unwrap();
+
+        let Some(Event::Connected {
+            peer,
+            status: peer_status,
+        }) = a.next_event(TIMEOUT)
+        else {
+            panic!("expected a connection");
+        };
+        assert_eq!(peer_status, status(3));
+        let Some(Event::Connected { .. }) = b.next_event(TIMEOUT) else {
+            panic!("expected a connection");
+        };
+
+        // A message which is too long is dropped, but the peer stays connected.
+        let too_long = Message::Transactions {
+            extrinsics: vec![timestamp_inherent(0, 1); MAX_MESSAGE_LENGTH as usize / 8],
+        };
+        a.send(peer, &too_long);
+        a.send(
+            peer,
+            &Message::StateRequest {
+                hash: [3; 32],
+                start: 0,
+            },
+        );
+        let Some(Event::Message { peer, message }) = b.next_event(TIMEOUT) else {
+            panic!("expected a message");
+        };
+        assert!(matches!(
+            message,
+            Message::StateRequest { hash: [3, ..], .. }
+        ));
+
+        b.broadcast(&Message::StateResponse { snapshot: None }, Some(peer));
+        b.broadcast(&Message::StateResponse { snapshot: None }, None);
+        assert!(matches!(
+            a.next_event(TIMEOUT),
+            Some(Event::Message {
+                message: Message::StateResponse { snapshot: None },
+                ..
+            })
+        ));
+        assert!(a.next_event(Duration::from_millis(100)).is_none());
+    }
+
+    #[test]
+    fn peers_of_another_chain_are_rejected() {
+        let a = Network::start("127.0.0.1:0", [0; 32], status(0)).unwrap();
+        let b = Network::start("127.0.0.1:0", [1; 32], status(1)).unwrap();
+        a.connect(b.local_addr()).unwrap();
+
+        assert!(a.next_event(Duration::from_millis(500)).is_none());
+        assert!(b.next_event(Duration::from_millis(500)).is_none());
+    }
+}
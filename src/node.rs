@@ -0,0 +1,557 @@
+//! A node of the development chain, which runs alongside other nodes on the same machine.
+//!
+//! The node authors blocks with Aura in the slots of its authorities, and gossips its blocks and
+//! transactions with its peers over the `network`. The blocks of the peers it does not know are
+//! downloaded and imported by the `sync`. Clients can read the chain and submit transactions to
+//! the node with the `rpc` server.
+
+use std::{
+    net::SocketAddr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    aura::{self, Slot},
+    balances,
+    chain::{Chain, ChainConfig},
+    consensus::aura::Aura,
+    network::{Event, Message, Network, PeerId},
+    rpc::{self, server::Server, subscriptions::Subscriptions},
+    support::{
+        crypto::Pair,
+        hashing::{to_hex, Hash},
+    },
+    sync::{self, ChainSync, SyncMode},
+    transaction_pool::TransactionPool,
+    types, DevAccount, Runtime, RuntimeCall,
+};
+
+/// How long the node waits for the events of the network before it serves the RPC clients.
+const RPC_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How to run a node, from the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeConfig {
+    /// The address to accept connections on.
+    pub listen: SocketAddr,
+    /// The addresses of the nodes to connect to.
+    pub peers: Vec<SocketAddr>,
+    /// The names of the development accounts the node authors blocks for.
+    pub authorities: Vec<String>,
+    /// A transfer to submit once the node is started: the names of the development accounts
+    /// sending and receiving it, and the amount.
+    pub transfer: Option<(String, String, types::Balance)>,
+    /// How the node syncs its chain with its peers.
+    pub sync: SyncMode,
+    /// The address to serve the RPC server on, which must be on localhost.
+    pub rpc: Option<SocketAddr>,
+}
+
+impl NodeConfig {
+    /// Parse the arguments of a node: `--listen <addr>`, any number of `--peer <addr>` and
+    /// `--authority <name>`, `--transfer <from>:<to>:<amount>`, `--sync <full|fast>` and
+    /// `--rpc <addr>`.
+    /// Returns `None` without `--listen`, as the arguments are not the ones of a node.
+    pub fn from_args(args: impl Iterator<Item = String>) -> Result<Option<Self>, &'static str> {
+        let args = args.collect::<Vec<_>>();
+        if !args.iter().any(|arg| arg == "--listen") {
+            return Ok(None);
+        }
+
+        let mut listen = None;
+        let mut config = NodeConfig {
+            listen: SocketAddr::from(([127, 0, 0, 1], 0)),
+            peers: Vec::new(),
+            authorities: Vec::new(),
+            transfer: None,
+            sync: SyncMode::Full,
+            rpc: None,
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let value = args.next().ok_or("Missing value of a node argument")?;
+            match arg.as_str() {
+                "--listen" => listen = Some(value.parse().map_err(|_| "Invalid listen address")?),
+                "--peer" => config
+                    .peers
+                    .push(value.parse().map_err(|_| "Invalid peer address")?),
+                "--authority" => config.authorities.push(value),
+                "--transfer" => config.transfer = Some(parse_transfer(&value)?),
+                "--sync" => {
+                    config.sync = match value.as_str() {
+                        "full" => SyncMode::Full,
+                        "fast" => SyncMode::Fast,
+                        _ => return Err("Invalid sync mode"),
+                    }
+                }
+                "--rpc" => {
+                    let rpc: SocketAddr = value.parse().map_err(|_| "Invalid RPC address")?;
+                    // The RPC server accepts transactions, so it is not exposed beyond the machine.
+                    if !rpc.ip().is_loopback() {
+                        return Err("RPC address is not on localhost");
+                    }
+                    config.rpc = Some(rpc);
+                }
+                _ => return Err("Unknown node argument"),
+            }
+        }
+        config.listen = listen.ok_or("Missing listen address")?;
+        Ok(Some(config))
+    }
+}
+
+/// Parse a transfer given as `<from>:<to>:<amount>`.
+fn parse_transfer(value: &str) -> Result<(String, String, types::Balance), &'static str> {
+    let mut parts = value.split(':');
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(from), Some(to), Some(amount), None) => {
+            let amount = amount.parse().map_err(|_| "Invalid transfer amount")?;
+            Ok((from.to_string(), to.to_string(), amount))
+        }
+        _ => Err("Invalid transfer"),
+    }
+}
+
+/// This is the Node.
+/// It owns the chain, the transaction pool and the network, and reacts to the slots of the
+/// authorities and to the events of the network.
+pub struct Node {
+    chain: Chain,
+    pool: TransactionPool<Runtime>,
+    engine: Aura,
+    network: Network,
+    sync: ChainSync,
+    subscriptions: Subscriptions,
+}
+
+impl Node {
+    /// Create a node importing and authoring blocks with the Aura `engine`, and syncing its chain
+    /// with its peers in the `sync_mode`.
+    pub fn new(chain: Chain, engine: Aura, network: Network, sync_mode: SyncMode) -> Self {
+        Self {
+            chain,
+            pool: TransactionPool::new(),
+            engine,
+            network,
+            sync: ChainSync::new(sync_mode),
+            subscriptions: Subscriptions::new(),
+        }
+    }
+
+    /// Submit `extrinsic` to the pool, and propagate it to the peers, except `from`, the peer it
+    /// came from.
+    pub fn submit(
+        &mut self,
+        extrinsic: types::Extrinsic,
+        from: Option<PeerId>,
+    ) -> Result<Hash, &'static str> {
+        let hash = self
+            .pool
+            .submit(self.chain.best_state(), extrinsic.clone())?;
+        let message = Message::Transactions {
+            extrinsics: vec![extrinsic],
+        };
+        self.network.broadcast(&message, from);
+        Ok(hash)
+    }
+
+    /// Author a block on the best block in `slot`, if the slot belongs to one of the authorities
+    /// of the node, and announce it.
+    pub fn on_slot(&mut self, slot: Slot) {
+        if !self
+            .engine
+            .can_author(&self.chain, &self.chain.best_hash(), slot)
+        {
+            return;
+        }
+
+        let now = slot * <Runtime as aura::Config>::SLOT_DURATION;
+        let inherents = crate::inherents(now);
+        match crate::author_block(&mut self.chain, &mut self.pool, &self.engine, inherents) {
+            Ok(imported) => {
+                self.log_imported(&imported.hash);
+                self.subscriptions.notify(&self.chain, &imported.enacted);
+                self.announce_best(None);
+            }
+            Err(e) => eprintln!("Failed to author a block in slot {slot}: {e}"),
+        }
+    }
+
+    /// React to an event of the network.
+    pub fn handle_event(&mut self, event: Event) {
+        match event {
+            Event::Connected { peer, status } => {
+                println!("Peer {peer} connected, at block #{}", status.best_number);
+                let request = self.sync.peer_connected(&self.chain, peer, status);
+                self.send(peer, request);
+            }
+            Event::Message { peer, message } => match message {
+                Message::BlockAnnounce { header } => {
+                    let request = self.sync.block_announced(&self.chain, peer, &header);
+                    self.send(peer, request);
+                }
+                Message::HeadersRequest { .. }
+                | Message::BodiesRequest { .. }
+                | Message::StateRequest { .. } => {
+                    let response = sync::respond(&self.chain, &message);
+                    self.send(peer, response);
+                }
+                Message::Headers { .. }
+                | Message::Bodies { .. }
+                | Message::StateResponse { .. } => self.on_sync_response(peer, message),
+                Message::Transactions { extrinsics } => {
+                    for extrinsic in extrinsics {
+                        // Transactions the pool already has, or which are invalid, are not
+                        // propagated any further.
+                        let _ = self.submit(extrinsic, Some(peer));
+                    }
+                }
+                Message::Handshake { .. } => {}
+            },
+            Event::Disconnected { peer } => {
+                println!("Peer {peer} disconnected");
+                self.sync.peer_disconnected(peer);
+            }
+        }
+    }
+
+    /// Respond to the request of an RPC client. A new subscription is notified of the current
+    /// values right after the response.
+    pub fn handle_rpc(&mut self, request: rpc::server::Request) {
+        let response = rpc::handle(self, request.body(), request.sink());
+        request.respond(response);
+        self.subscriptions.notify(&self.chain, &[]);
+    }
+
+    /// Send `message` to `peer`, if there is one.
+    fn send(&self, peer: PeerId, message: Option<Message>) {
+        if let Some(message) = message {
+            self.network.send(peer, &message);
+        }
+    }
+
+    /// Hand the response of `peer` to the sync, and update the pool with the blocks or the state
+    /// it imported.
+    fn on_sync_response(&mut self, peer: PeerId, response: Message) {
+        let best_hash = self.chain.best_hash();
+        let progress = self
+            .sync
+            .on_response(&mut self.chain, &self.engine, peer, response);
+
+        if let Some(hash) = progress.state {
+            println!("Imported the state of block {}", to_hex(&hash));
+            // The transactions of the pool are checked against the new state.
+            self.pool.prune(self.chain.best_state(), &[]);
+            self.subscriptions.notify(&self.chain, &[hash]);
+        }
+        for imported in &progress.imported {
+            crate::update_pool(&self.chain, &mut self.pool, imported);
+            self.log_imported(&imported.hash);
+            self.subscriptions.notify(&self.chain, &imported.enacted);
+        }
+        if let Some(e) = progress.error {
+            eprintln!("Failed to sync with peer {peer}: {e}");
+        }
+        self.send(peer, progress.request);
+
+        if self.chain.best_hash() != best_hash {
+            self.announce_best(Some(peer));
+        }
+    }
+
+    /// Log the import of the block with the `hash`.
+    fn log_imported(&self, hash: &Hash) {
+        let number = self
+            .chain
+            .block(hash)
+            .map_or(0, |block| block.header.block_number);
+        println!(
+            "Imported block #{} {}, finalized {}",
+            number,
+            to_hex(hash),
+            to_hex(&self.chain.finalized_hash()),
+        );
+    }
+
+    /// Announce the best block to the peers except `from`, e.g. the peer it was synced from, and
+    /// record the status of the chain for the handshakes.
+    fn announce_best(&self, from: Option<PeerId>) {
+        let best_hash = self.chain.best_hash();
+        let header = self
+            .chain
+            .block(&best_hash)
+            .expect("the best block is known")
+            .header
+            .clone();
+        self.network.set_status(sync::status(&self.chain));
+        self.network
+            .broadcast(&Message::BlockAnnounce { header }, from);
+    }
+}
+
+impl rpc::Backend for Node {
+    fn chain(&self) -> &Chain {
+        &self.chain
+    }
+
+    fn pending_extrinsics(&self) -> Vec<types::Extrinsic> {
+        self.pool.ready()
+    }
+
+    fn submit_extrinsic(&mut self, extrinsic: types::Extrinsic) -> Result<Hash, &'static str> {
+        self.submit(extrinsic, None)
+    }
+
+    fn subscriptions(&mut self) -> &mut Subscriptions {
+        &mut self.subscriptions
+    }
+}
+
+// The current time, in milliseconds since the unix epoch.
+fn now() -> types::Moment {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("time is after the unix epoch")
+        .as_millis() as types::Moment
+}
+
+/// Run a node of the development chain with `config`, until the process is stopped.
+pub fn run(config: NodeConfig) {
+    // Blocks two blocks under the best block are finalized, and the state of the ten last
+    // finalized blocks is kept.
+    let chain_config = ChainConfig {
+        finality_depth: Some(2),
+        state_pruning: Some(10),
+    };
+    let chain = Chain::new(crate::dev_genesis(), chain_config);
+    let genesis_hash = chain.best_hash();
+    let network = match Network::start(config.listen, genesis_hash, sync::status(&chain)) {
+        Ok(network) => network,
+        Err(e) => {
+            eprintln!("Failed to listen on {}: {e}", config.listen);
+            std::process::exit(1);
+        }
+    };
+    println!("Listening on {}", network.local_addr());
+    let rpc = config.rpc.map(|addr| match Server::start(addr) {
+        Ok(rpc) => {
+            println!("RPC server listening on {}", rpc.local_addr());
+            rpc
+        }
+        Err(e) => {
+            eprintln!("Failed to serve the RPC server on {addr}: {e}");
+            std::process::exit(1);
+        }
+    });
+    for peer in &config.peers {
+        // The peer may not be started yet, in which case it connects to this node later.
+        if let Err(e) = network.connect(peer) {
+            eprintln!("Failed to connect to {peer}: {e}");
+        }
+    }
+
+    let keys = config
+        .authorities
+        .iter()
+        .map(|name| Pair::from_seed(name))
+        .collect();
+    let mut node = Node::new(chain, Aura::new(keys), network, config.sync);
+
+    if let Some((from, to, amount)) = config.transfer {
+        let mut from = DevAccount::new(&from);
+        from.next_nonce = node.chain.best_state().system.get_nonce(&from.id());
+        let call = RuntimeCall::balances(balances::Call::transfer {
+            to: DevAccount::new(&to).id(),
+            amount,
+        });
+        if let Err(e) = node.submit(from.sign(call), None) {
+            eprintln!("Failed to submit the transfer: {e}");
+        }
+    }
+
+    let slot_duration = <Runtime as aura::Config>::SLOT_DURATION;
+    loop {
+        let slot = now() / slot_duration;
+        node.on_slot(slot);
+
+        // Handle the events of the network and the requests of the RPC clients until the next
+        // slot. The network is waited on for a short time only, so the clients are served
+        // promptly.
+        let next_slot = (slot + 1) * slot_duration;
+        while let Some(wait) = next_slot.checked_sub(now()).filter(|wait| *wait > 0) {
+            let wait = Duration::from_millis(wait).min(RPC_POLL_INTERVAL);
+            if let Some(event) = node.network.next_event(wait) {
+                node.handle_event(event);
+            }
+            while let Some(request) = rpc
+                .as_ref()
+                .and_then(|rpc| rpc.next_request(Duration::ZERO))
+            {
+                node.handle_rpc(request);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::{Node, NodeConfig};
+    use crate::{
+        balances,
+        chain::{Chain, ChainConfig},
+        consensus::aura::Aura,
+        network::{Event, Network},
+        support::crypto::Pair,
+        sync::{self, SyncMode},
+        DevAccount, RuntimeCall,
+    };
+
+    // A node of the development chain, authoring blocks for `authority`, where blocks two blocks
+    // under the best block are finalized.
+    fn new_node(authority: &str, sync_mode: SyncMode) -> Node {
+        let config = ChainConfig {
+            finality_depth: Some(2),
+            state_pruning: None,
+        };
+        let chain = Chain::new(crate::dev_genesis(), config);
+        let network = Network::start("127.0.0.1:0", chain.best_hash(), sync::status(&chain));
+        let engine = Aura::new(vec![Pair::from_seed(authority)]);
+        Node::new(chain, engine, network.unwrap(), sync_mode)
+    }
+
+    // Connect `a` to `b`, and handle the `Connected` event of both nodes.
+    fn connect(a: &mut Node, b: &mut Node) {
+        a.network.connect(b.network.local_addr()).unwrap();
+        for node in [a, b] {
+            let event = node.network.next_event(Duration::from_secs(5)).unwrap();
+            assert!(matches!(event, Event::Connected { .. }));
+            node.handle_event(event);
+        }
+    }
+
+    // Handle the events of both nodes, in turns, until `done` holds.
+    fn run_until(a: &mut Node, b: &mut Node, done: impl Fn(&Node, &Node) -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !done(a, b) {
+            assert!(Instant::now() < deadline, "nodes are stuck");
+            for node in [&mut *a, &mut *b] {
+                if let Some(event) = node.network.next_event(Duration::from_millis(10)) {
+                    node.handle_event(event);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn from_args() {
+        let args = |args: &[&str]| NodeConfig::from_args(args.iter().map(|arg| arg.to_string()));
+
+        assert_eq!(args(&["--pow"]), Ok(None));
+        assert_eq!(
+            args(&[
+                "--listen",
+                "127.0.0.1:30333",
+                "--peer",
+                "127.0.0.1:30334",
+                "--authority",
+                "alice",
+                "--transfer",
+                "alice:bob:10",
+                "--sync",
+                "fast",
+                "--rpc",
+                "127.0.0.1:9944"
+            ]),
+            Ok(Some(NodeConfig {
+                listen: "127.0.0.1:30333".parse().unwrap(),
+                peers: vec!["127.0.0.1:30334".parse().unwrap()],
+                authorities: vec!["alice".to_string()],
+                transfer: Some(("alice".to_string(), "bob".to_string(), 10)),
+                sync: SyncMode::Fast,
+                rpc: Some("127.0.0.1:9944".parse().unwrap()),
+            }))
+        );
+        assert_eq!(
+            args(&["--listen", "127.0.0.1:30333", "--peer"]),
+            Err("Missing value of a node argument")
+        );
+        assert_eq!(
+            args(&["--listen", "127.0.0.1:30333", "--transfer", "alice:bob"]),
+            Err("Invalid transfer")
+        );
+        assert_eq!(
+            args(&["--listen", "127.0.0.1:30333", "--rpc", "0.0.0.0:9944"]),
+            Err("RPC address is not on localhost")
+        );
+    }
+
+    #[test]
+    fn nodes_converge() {
+        let mut alice = new_node("alice", SyncMode::Full);
+        let mut bob = new_node("bob", SyncMode::Full);
+
+        // Bob authors the blocks of slots 1 and 3 before alice connects to him.
+        alice.on_slot(1);
+        assert_eq!(alice.chain.best_state().system.block_number(), 0);
+        bob.on_slot(1);
+        bob.on_slot(3);
+        assert_eq!(bob.chain.best_state().system.block_number(), 2);
+
+        // Alice syncs with the best block of bob from his handshake.
+        connect(&mut alice, &mut bob);
+        run_until(&mut alice, &mut bob, |alice, bob| {
+            alice.chain.best_hash() == bob.chain.best_hash()
+        });
+
+        // A block authored by alice is announced to bob.
+        alice.on_slot(4);
+        run_until(&mut alice, &mut bob, |alice, bob| {
+            alice.chain.best_hash() == bob.chain.best_hash()
+        });
+        assert_eq!(bob.chain.best_state().system.block_number(), 3);
+    }
+
+    #[test]
+    fn new_node_fast_syncs() {
+        let mut alice = new_node("alice", SyncMode::Fast);
+        let mut bob = new_node("bob", SyncMode::Full);
+        let genesis_hash = bob.chain.best_hash();
+        for slot in [1, 3, 5, 7] {
+            bob.on_slot(slot);
+        }
+
+        // Alice imports the state of the finalized block of bob, then the blocks after it.
+        connect(&mut alice, &mut bob);
+        run_until(&mut alice, &mut bob, |alice, bob| {
+            alice.chain.best_hash() == bob.chain.best_hash()
+        });
+        assert!(alice.chain.block(&genesis_hash).is_none());
+        assert_eq!(alice.chain.finalized_hash(), bob.chain.finalized_hash());
+    }
+
+    #[test]
+    fn transactions_are_propagated() {
+        let mut alice = new_node("alice", SyncMode::Full);
+        let mut bob = new_node("bob", SyncMode::Full);
+        connect(&mut alice, &mut bob);
+
+        let mut sender = DevAccount::new("alice");
+        let ext = sender.sign(RuntimeCall::balances(balances::Call::transfer {
+            to: DevAccount::new("bob").id(),
+            amount: 10,
+        }));
+        assert!(alice.submit(ext, None).is_ok());
+        run_until(&mut alice, &mut bob, |_, bob| bob.pool.ready().len() == 1);
+
+        // Bob includes the transaction in the block of his slot, and alice imports it.
+        bob.on_slot(1);
+        assert!(bob.pool.ready().is_empty());
+        run_until(&mut alice, &mut bob, |alice, bob| {
+            alice.chain.best_hash() == bob.chain.best_hash()
+        });
+        assert!(alice.pool.ready().is_empty());
+        assert_eq!(alice.chain.best_state().system.get_nonce(&sender.id()), 1);
+    }
+}
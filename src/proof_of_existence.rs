@@ -1,31 +1,81 @@
 use core::fmt::Debug;
-use std::collections::BTreeMap;
+use std::{borrow::Borrow, collections::BTreeMap};
 
-use crate::support::DispatchResult;
+use crate::support::{
+    codec::{Decode, Encode},
+    ensure_signed,
+    metadata::{FieldMetadata, PalletInfo, VariantMetadata},
+    DispatchResult, Hooks, PalletEvents,
+};
 
 pub trait Config: crate::system::Config {
     /// The type which represents the content that can be claimed using this pallet.
     /// Could be the content directly as bytes, or better yet the hash of that content.
     /// We leave that decision to the runtime developer.
-    type Content: Debug + Ord;
+    type Content: Debug + Ord + Clone;
+}
+
+/// The events of the Proof of Existence Module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<T: Config> {
+    /// `owner` claimed the content `claim`.
+    ClaimCreated {
+        owner: T::AccountId,
+        claim: T::Content,
+    },
+    /// `owner` revoked its claim on the content `claim`.
+    ClaimRevoked {
+        owner: T::AccountId,
+        claim: T::Content,
+    },
+}
+
+impl<T: Config> Encode for Event<T>
+where
+    T::Content: Encode,
+{
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        let (index, owner, claim) = match self {
+            Event::ClaimCreated { owner, claim } => (0u8, owner, claim),
+            Event::ClaimRevoked { owner, claim } => (1, owner, claim),
+        };
+        dest.push(index);
+        owner.encode_to(dest);
+        claim.encode_to(dest);
+    }
 }
 
 /// This is the Proof of Existence Module.
 /// It is a simple module that allows accounts to claim existence of some data.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Pallet<T: Config> {
     claims: BTreeMap<T::Content, T::AccountId>,
+    /// The events deposited by the calls of the pallet, until the runtime takes them. They are
+    /// not part of the encoded state.
+    events: Vec<Event<T>>,
 }
 
 #[macros::call]
+#[errors(
+    "Bad origin",
+    "Claim already exists",
+    "Claim does not exists",
+    "Caller is not the owner of the claim"
+)]
 impl<T: Config> Pallet<T> {
     /// Create a new claim on behalf of the `caller`.
     /// This function will return an error if someone already has claimed that content.
-    pub fn create_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
+    #[call_index(0)]
+    pub fn create_claim(&mut self, origin: T::RuntimeOrigin, claim: T::Content) -> DispatchResult {
+        let caller = ensure_signed(origin)?;
         match self.get_claim(&claim) {
-            Some(_) => Err(&"Claim already exists"),
+            Some(_) => Err("Claim already exists"),
             None => {
-                self.claims.insert(claim, caller);
+                self.claims.insert(claim.clone(), caller.clone());
+                self.events.push(Event::ClaimCreated {
+                    owner: caller,
+                    claim,
+                });
                 Ok(())
             }
         }
@@ -34,7 +84,9 @@ impl<T: Config> Pallet<T> {
     /// Revoke an existing claim on some content.
     /// This function should only succeed if the caller is the owner of an existing claim.
     /// It will return an error if the claim does not exist, or if the caller is not the owner.
-    pub fn revoke_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
+    #[call_index(1)]
+    pub fn revoke_claim(&mut self, origin: T::RuntimeOrigin, claim: T::Content) -> DispatchResult {
+        let caller = ensure_signed(origin)?;
         let claim_owner = self.get_claim(&claim).ok_or("Claim does not exists")?;
 
         if claim_owner != &caller {
@@ -42,6 +94,10 @@ impl<T: Config> Pallet<T> {
         }
 
         self.claims.remove(&claim);
+        self.events.push(Event::ClaimRevoked {
+            owner: caller,
+            claim,
+        });
         Ok(())
     }
 }
@@ -50,17 +106,86 @@ impl<T: Config> Pallet<T> {
     pub fn new() -> Self {
         Self {
             claims: BTreeMap::new(),
+            events: Vec::new(),
         }
     }
 
-    /// Get the owner (if any) of a claim.
-    pub fn get_claim(&self, claim: &T::Content) -> Option<&T::AccountId> {
+    /// Get the owner (if any) of a claim, which can be looked up by any borrowed form of the
+    /// content, e.g. a `str` for `String` contents.
+    pub fn get_claim<Q>(&self, claim: &Q) -> Option<&T::AccountId>
+    where
+        T::Content: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
         self.claims.get(claim)
     }
 }
 
+impl<T: Config> Encode for Pallet<T>
+where
+    T::Content: Encode,
+{
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.claims.encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for Pallet<T>
+where
+    T::AccountId: Decode,
+    T::Content: Decode,
+{
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        Ok(Self {
+            claims: Decode::decode_from(input)?,
+            events: Vec::new(),
+        })
+    }
+}
+
+impl<T: Config> PalletEvents for Pallet<T> {
+    type Event = Event<T>;
+
+    fn take_events(&mut self) -> Vec<Event<T>> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+impl<T: Config> PalletInfo for Pallet<T> {
+    fn events() -> Vec<VariantMetadata> {
+        vec![
+            VariantMetadata {
+                name: "ClaimCreated",
+                index: 0,
+                fields: vec![
+                    FieldMetadata::new::<T::AccountId>("owner"),
+                    FieldMetadata::new::<T::Content>("claim"),
+                ],
+            },
+            VariantMetadata {
+                name: "ClaimRevoked",
+                index: 1,
+                fields: vec![
+                    FieldMetadata::new::<T::AccountId>("owner"),
+                    FieldMetadata::new::<T::Content>("claim"),
+                ],
+            },
+        ]
+    }
+
+    fn storage() -> Vec<FieldMetadata> {
+        vec![FieldMetadata::new::<BTreeMap<T::Content, T::AccountId>>(
+            "claims",
+        )]
+    }
+}
+
+impl<T: Config> Hooks<T, T::BlockNumber> for Pallet<T> {}
+
 #[cfg(test)]
 mod test {
+    use crate::support::RawOrigin;
+
     struct TestConfig;
 
     impl super::Config for TestConfig {
@@ -69,8 +194,10 @@ mod test {
 
     impl crate::system::Config for TestConfig {
         type AccountId = String;
+        type RuntimeOrigin = RawOrigin<String>;
         type BlockNumber = u32;
         type Nonce = u32;
+        type RuntimeEvent = crate::system::Event;
     }
 
     #[test]
@@ -84,7 +211,7 @@ mod test {
     fn create_claim() {
         let mut poe = super::Pallet::<TestConfig>::new();
 
-        let _ = poe.create_claim("alice".to_string(), &"my_document");
+        let _ = poe.create_claim(RawOrigin::Signed("alice".to_string()), "my_document");
 
         assert_eq!(poe.get_claim(&"my_document"), Some(&"alice".to_string()));
     }
@@ -93,8 +220,8 @@ mod test {
     fn create_claim_duplicated_return_claim_exists() {
         let mut poe = super::Pallet::<TestConfig>::new();
 
-        let _ = poe.create_claim("alice".to_string(), &"my_document");
-        let res = poe.create_claim("alice".to_string(), &"my_document");
+        let _ = poe.create_claim(RawOrigin::Signed("alice".to_string()), "my_document");
+        let res = poe.create_claim(RawOrigin::Signed("alice".to_string()), "my_document");
 
         assert_eq!(res, Err("Claim already exists"));
     }
@@ -102,9 +229,9 @@ mod test {
     #[test]
     fn revoke_claim() {
         let mut poe = super::Pallet::<TestConfig>::new();
-        let _ = poe.create_claim("alice".to_string(), &"my_document");
+        let _ = poe.create_claim(RawOrigin::Signed("alice".to_string()), "my_document");
 
-        let res = poe.revoke_claim("alice".to_string(), &"my_document");
+        let res = poe.revoke_claim(RawOrigin::Signed("alice".to_string()), "my_document");
 
         assert_eq!(res, Ok(()));
         assert_eq!(poe.get_claim(&"my_document"), None);
@@ -114,7 +241,10 @@ mod test {
     fn revoke_claim_return_claim_does_not_exists() {
         let mut poe = super::Pallet::<TestConfig>::new();
 
-        let res = poe.revoke_claim("alice".to_string(), &"non existent document");
+        let res = poe.revoke_claim(
+            RawOrigin::Signed("alice".to_string()),
+            "non existent document",
+        );
 
         assert_eq!(res, Err("Claim does not exists"));
     }
@@ -122,9 +252,9 @@ mod test {
     #[test]
     fn revoke_claim_return_caller_isnt_owner_to_revoke() {
         let mut poe = super::Pallet::<TestConfig>::new();
-        let _ = poe.create_claim("alice".to_string(), &"my_document");
+        let _ = poe.create_claim(RawOrigin::Signed("alice".to_string()), "my_document");
 
-        let res = poe.revoke_claim("bob".to_string(), &"my_document");
+        let res = poe.revoke_claim(RawOrigin::Signed("bob".to_string()), "my_document");
 
         assert_eq!(res, Err("Caller is not the owner of the claim"));
     }
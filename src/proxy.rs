@@ -0,0 +1,356 @@
+use std::collections::BTreeMap;
+
+use crate::support::{
+    codec::{Decode, Encode},
+    ensure_signed,
+    metadata::{ConstantMetadata, FieldMetadata, PalletInfo},
+    Dispatch, DispatchResult, GetDispatchInfo, GetPallet, Hooks, InstanceFilter, RawOrigin,
+    DEFAULT_WEIGHT,
+};
+
+pub trait Config:
+    crate::system::Config
+    + Sized
+    + GetPallet<Pallet<Self>>
+    + Dispatch<Caller = Self::RuntimeOrigin, Call = <Self as Config>::RuntimeCall>
+{
+    /// The outer call type of the runtime, which a proxy can dispatch on behalf of an account.
+    type RuntimeCall: GetDispatchInfo;
+    /// The kinds of proxies an account can register. Each kind filters which calls the proxy is
+    /// allowed to make.
+    type ProxyType: InstanceFilter<<Self as Config>::RuntimeCall> + Encode + Clone + PartialEq;
+    /// The maximum number of proxies an account can register.
+    const MAX_PROXIES: u32;
+}
+
+/// A proxy registered by an account.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProxyDefinition<AccountId, ProxyType> {
+    /// The account which can make calls on behalf of the account which registered it.
+    pub delegate: AccountId,
+    /// The kind of proxy, restricting which calls the delegate can make.
+    pub proxy_type: ProxyType,
+}
+
+impl<AccountId: Encode, ProxyType: Encode> Encode for ProxyDefinition<AccountId, ProxyType> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.delegate.encode_to(dest);
+        self.proxy_type.encode_to(dest);
+    }
+}
+
+impl<AccountId: Decode, ProxyType: Decode> Decode for ProxyDefinition<AccountId, ProxyType> {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        Ok(Self {
+            delegate: Decode::decode_from(input)?,
+            proxy_type: Decode::decode_from(input)?,
+        })
+    }
+}
+
+/// This is the Proxy Module.
+/// It allows accounts to register delegates, which can make some calls on their behalf.
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+    /// The proxies registered by each account.
+    #[allow(clippy::type_complexity)]
+    proxies: BTreeMap<T::AccountId, Vec<ProxyDefinition<T::AccountId, T::ProxyType>>>,
+}
+
+#[macros::call]
+#[errors(
+    "Bad origin",
+    "Proxy already exists",
+    "Too many proxies",
+    "Proxy does not exists",
+    "Caller is not a proxy of the account",
+    "Call is not allowed by the proxy type"
+)]
+impl<T: Config> Pallet<T> {
+    /// Register `delegate` as a proxy of the signer of `origin`, of kind `proxy_type`.
+    /// This function will return an error if the proxy is already registered.
+    #[call_index(0)]
+    pub fn add_proxy(
+        &mut self,
+        origin: T::RuntimeOrigin,
+        delegate: T::AccountId,
+        proxy_type: T::ProxyType,
+    ) -> DispatchResult {
+        let who = ensure_signed(origin)?;
+        let proxy = ProxyDefinition {
+            delegate,
+            proxy_type,
+        };
+
+        let proxies = self.proxies.entry(who).or_default();
+        if proxies.contains(&proxy) {
+            return Err("Proxy already exists");
+        }
+        if proxies.len() >= T::MAX_PROXIES as usize {
+            return Err("Too many proxies");
+        }
+
+        proxies.push(proxy);
+        Ok(())
+    }
+
+    /// Unregister `delegate` as a proxy of the signer of `origin`, of kind `proxy_type`.
+    /// This function will return an error if the proxy is not registered.
+    #[call_index(1)]
+    pub fn remove_proxy(
+        &mut self,
+        origin: T::RuntimeOrigin,
+        delegate: T::AccountId,
+        proxy_type: T::ProxyType,
+    ) -> DispatchResult {
+        let who = ensure_signed(origin)?;
+        let proxy = ProxyDefinition {
+            delegate,
+            proxy_type,
+        };
+
+        let proxies = self.proxies.get_mut(&who).ok_or("Proxy does not exists")?;
+        let index = proxies
+            .iter()
+            .position(|p| p == &proxy)
+            .ok_or("Proxy does not exists")?;
+
+        proxies.remove(index);
+        if proxies.is_empty() {
+            self.proxies.remove(&who);
+        }
+        Ok(())
+    }
+
+    /// Dispatch `call` on behalf of `real`, with `real` as the signed origin.
+    /// This function will return an error if the signer of `origin` is not a proxy of `real`
+    /// whose kind allows `call`.
+    // The `call` is boxed since `Call` would otherwise be a recursive type.
+    #[allow(clippy::boxed_local)]
+    #[call_index(2)]
+    #[weight(DEFAULT_WEIGHT.saturating_add(call.get_dispatch_info().weight))]
+    pub fn proxy(
+        runtime: &mut T,
+        origin: T::RuntimeOrigin,
+        real: T::AccountId,
+        call: Box<T::RuntimeCall>,
+    ) -> DispatchResult {
+        let who = ensure_signed(origin)?;
+
+        let pallet: &Pallet<T> = runtime.pallet();
+        let proxies = pallet.proxies(&real);
+        let mut delegated = proxies.iter().filter(|p| p.delegate == who).peekable();
+        if delegated.peek().is_none() {
+            return Err("Caller is not a proxy of the account");
+        }
+        if !delegated.any(|p| p.proxy_type.filter(&call)) {
+            return Err("Call is not allowed by the proxy type");
+        }
+
+        runtime.dispatch(RawOrigin::Signed(real).into(), *call)?;
+        Ok(())
+    }
+}
+
+impl<T: Config> Pallet<T> {
+    /// Create a new instance of the Proxy Module.
+    pub fn new() -> Self {
+        Self {
+            proxies: BTreeMap::new(),
+        }
+    }
+
+    /// Get the proxies registered by `who`.
+    pub fn proxies(&self, who: &T::AccountId) -> &[ProxyDefinition<T::AccountId, T::ProxyType>] {
+        self.proxies.get(who).map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
+impl<T: Config> Encode for Pallet<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.proxies.encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for Pallet<T>
+where
+    T::AccountId: Decode,
+    T::ProxyType: Decode,
+{
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        Ok(Self {
+            proxies: Decode::decode_from(input)?,
+        })
+    }
+}
+
+impl<T: Config> PalletInfo for Pallet<T> {
+    fn storage() -> Vec<FieldMetadata> {
+        vec![FieldMetadata::new::<
+            BTreeMap<T::AccountId, Vec<ProxyDefinition<T::AccountId, T::ProxyType>>>,
+        >("proxies")]
+    }
+
+    fn constants() -> Vec<ConstantMetadata> {
+        vec![ConstantMetadata::new("MAX_PROXIES", T::MAX_PROXIES)]
+    }
+}
+
+impl<T: Config> Hooks<T, T::BlockNumber> for Pallet<T> {
+    fn integrity_test() {
+        assert!(
+            T::MAX_PROXIES > 0,
+            "an account must be able to register a proxy"
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::support::{
+        codec::Encode, Dispatch, DispatchResultWithPostInfo, GetPallet, InstanceFilter, RawOrigin,
+    };
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum TestProxyType {
+        Any,
+        Transfers,
+    }
+
+    impl InstanceFilter<&'static str> for TestProxyType {
+        fn filter(&self, call: &&'static str) -> bool {
+            match self {
+                TestProxyType::Any => true,
+                TestProxyType::Transfers => call.starts_with("transfer"),
+            }
+        }
+    }
+
+    impl Encode for TestProxyType {
+        fn encode_to(&self, dest: &mut Vec<u8>) {
+            dest.push(self.clone() as u8);
+        }
+    }
+
+    struct TestRuntime {
+        proxy: super::Pallet<TestRuntime>,
+        dispatched: Vec<(RawOrigin<String>, &'static str)>,
+    }
+
+    impl super::Config for TestRuntime {
+        type RuntimeCall = &'static str;
+        type ProxyType = TestProxyType;
+        const MAX_PROXIES: u32 = 2;
+    }
+
+    impl crate::system::Config for TestRuntime {
+        type AccountId = String;
+        type RuntimeOrigin = RawOrigin<String>;
+        type BlockNumber = u32;
+        type Nonce = u32;
+        type RuntimeEvent = crate::system::Event;
+    }
+
+    impl GetPallet<super::Pallet<Self>> for TestRuntime {
+        fn pallet(&self) -> &super::Pallet<Self> {
+            &self.proxy
+        }
+        fn pallet_mut(&mut self) -> &mut super::Pallet<Self> {
+            &mut self.proxy
+        }
+    }
+
+    impl Dispatch for TestRuntime {
+        type Caller = RawOrigin<String>;
+        type Call = &'static str;
+
+        fn dispatch(
+            &mut self,
+            caller: Self::Caller,
+            call: Self::Call,
+        ) -> DispatchResultWithPostInfo {
+            self.dispatched.push((caller, call));
+            Ok(().into())
+        }
+    }
+
+    fn new_runtime() -> TestRuntime {
+        TestRuntime {
+            proxy: super::Pallet::new(),
+            dispatched: vec![],
+        }
+    }
+
+    fn signed(who: &str) -> RawOrigin<String> {
+        RawOrigin::Signed(who.to_string())
+    }
+
+    #[test]
+    fn add_and_remove_proxy() {
+        let mut proxy = super::Pallet::<TestRuntime>::new();
+        let alice = "alice".to_string();
+
+        let res = proxy.add_proxy(signed("alice"), "bob".to_string(), TestProxyType::Any);
+        assert_eq!(res, Ok(()));
+        let res = proxy.add_proxy(signed("alice"), "bob".to_string(), TestProxyType::Any);
+        assert_eq!(res, Err("Proxy already exists"));
+        let res = proxy.add_proxy(signed("alice"), "bob".to_string(), TestProxyType::Transfers);
+        assert_eq!(res, Ok(()));
+        let res = proxy.add_proxy(signed("alice"), "charlie".to_string(), TestProxyType::Any);
+        assert_eq!(res, Err("Too many proxies"));
+        assert_eq!(proxy.proxies(&alice).len(), 2);
+
+        let res = proxy.remove_proxy(signed("alice"), "bob".to_string(), TestProxyType::Any);
+        assert_eq!(res, Ok(()));
+        let res = proxy.remove_proxy(signed("alice"), "bob".to_string(), TestProxyType::Any);
+        assert_eq!(res, Err("Proxy does not exists"));
+        let res = proxy.remove_proxy(signed("alice"), "bob".to_string(), TestProxyType::Transfers);
+        assert_eq!(res, Ok(()));
+        assert!(proxy.proxies(&alice).is_empty());
+    }
+
+    #[test]
+    fn proxy_dispatches_as_real() {
+        let mut runtime = new_runtime();
+        let _ =
+            runtime
+                .proxy
+                .add_proxy(signed("alice"), "bob".to_string(), TestProxyType::Transfers);
+
+        let res = super::Pallet::proxy(
+            &mut runtime,
+            signed("bob"),
+            "alice".to_string(),
+            Box::new("transfer"),
+        );
+
+        assert_eq!(res, Ok(()));
+        assert_eq!(runtime.dispatched, vec![(signed("alice"), "transfer")]);
+    }
+
+    #[test]
+    fn proxy_return_call_is_not_allowed() {
+        let mut runtime = new_runtime();
+        let _ =
+            runtime
+                .proxy
+                .add_proxy(signed("alice"), "bob".to_string(), TestProxyType::Transfers);
+
+        let res = super::Pallet::proxy(
+            &mut runtime,
+            signed("bob"),
+            "alice".to_string(),
+            Box::new("create_claim"),
+        );
+        assert_eq!(res, Err("Call is not allowed by the proxy type"));
+
+        let res = super::Pallet::proxy(
+            &mut runtime,
+            signed("charlie"),
+            "alice".to_string(),
+            Box::new("transfer"),
+        );
+        assert_eq!(res, Err("Caller is not a proxy of the account"));
+        assert!(runtime.dispatched.is_empty());
+    }
+}
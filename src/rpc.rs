@@ -0,0 +1,902 @@
+//! A JSON-RPC 2.0 server, which clients like a frontend use to read the chain and the state of the
+//! runtime, and to submit transactions to the node.
+//!
+//! The `server` receives the requests over HTTP or WebSocket, and the node responds to them with
+//! `handle`, as the `Backend` of the methods:
+//!
+//! - `chain_getHeader([hash])` and `chain_getBlock([hash])`: a block of the chain, the best block
+//!   without a hash, or `null` if it is unknown.
+//! - `state_getBalance(account, [hash])`, `state_getNonce(account, [hash])` and
+//!   `state_getClaim(claim, [hash])`: the balance and the nonce of an account and the owner of a
+//!   claim, in the state after a block, the best block without a hash.
+//! - `payment_queryInfo(extrinsic, [hash])`: the weight and the fee of an encoded extrinsic, as
+//!   `{"weight", "partialFee"}`, if it was included after a block, the best block without a hash.
+//! - `system_dryRun(extrinsic, [hash])`: apply an encoded extrinsic on a copy of the state after
+//!   a block, the best block without a hash, as if it was included in the next block. Returns
+//!   `{"result", "events", "weight"}`, where the result is `{"ok": null}` if the call succeeds,
+//!   `{"error": message}` if it fails, and `{"invalid": message}` if the extrinsic is invalid.
+//! - `state_getMetadata`: the metadata of the runtime, which describes its pallets with their
+//!   calls, events, errors, storage items and constants, see `metadata`.
+//! - `system_accountNextIndex(account)`: the nonce of the next transaction of an account, after its
+//!   transactions in the pool.
+//! - `author_submitExtrinsic(extrinsic)`: submit an encoded extrinsic, as a hex string, to the
+//!   pool. Returns the hash of the transaction.
+//!
+//! The state is read through the `RuntimeApi` of the runtime, with any state the chain keeps.
+//!
+//! Over a WebSocket, clients can also subscribe to new blocks, storage and events, see
+//! `subscriptions`.
+//!
+//! Hashes and encoded data are hex strings prefixed with `0x`, like the ones of `to_hex`.
+
+pub mod json;
+pub mod server;
+pub mod subscriptions;
+pub mod websocket;
+
+use crate::{
+    chain::Chain,
+    support::{
+        codec::{Decode, Encode},
+        hashing::{self, to_hex, Hash},
+        metadata::{FieldMetadata, RuntimeMetadata, VariantMetadata},
+        EventRecord, Phase, RuntimeApi,
+    },
+    types, Runtime, RuntimeEvent,
+};
+use json::Value;
+use server::Sink;
+use subscriptions::Subscriptions;
+
+/// The maximum length of the JSON text of a request.
+pub const MAX_REQUEST_LENGTH: usize = 1024 * 1024;
+
+/// The request is not valid JSON.
+const PARSE_ERROR: i64 = -32700;
+/// The request is not a valid JSON-RPC request.
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The call failed on the node, e.g. the transaction is invalid.
+const SERVER_ERROR: i64 = -32000;
+
+/// The error of a call, with its JSON-RPC code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Error {
+    pub code: i64,
+    pub message: &'static str,
+}
+
+impl Error {
+    fn invalid_params(message: &'static str) -> Self {
+        Self {
+            code: INVALID_PARAMS,
+            message,
+        }
+    }
+
+    fn server(message: &'static str) -> Self {
+        Self {
+            code: SERVER_ERROR,
+            message,
+        }
+    }
+}
+
+/// What the methods read from and submit to: the chain and the transaction pool of the node.
+pub trait Backend {
+    /// The chain of the node.
+    fn chain(&self) -> &Chain;
+
+    /// The transactions of the pool.
+    fn pending_extrinsics(&self) -> Vec<types::Extrinsic>;
+
+    /// Submit `extrinsic` to the pool, and propagate it to the peers.
+    fn submit_extrinsic(&mut self, extrinsic: types::Extrinsic) -> Result<Hash, &'static str>;
+
+    /// The subscriptions of the clients, which the node notifies as it imports blocks.
+    fn subscriptions(&mut self) -> &mut Subscriptions;
+}
+
+/// Respond to the JSON text of a request, or of a batch of requests, sent through the WebSocket
+/// of `sink`, if any. Returns `None` when there is nothing to respond, as all the requests are
+/// notifications.
+pub fn handle(backend: &mut dyn Backend, body: &str, sink: Option<&Sink>) -> Option<String> {
+    let request = match json::parse(body) {
+        Ok(request) => request,
+        Err(_) => {
+            let error = Error {
+                code: PARSE_ERROR,
+                message: "Parse error",
+            };
+            return Some(response(Value::Null, Err(error)).to_string());
+        }
+    };
+
+    match request {
+        Value::Array(requests) if !requests.is_empty() => {
+            let responses: Vec<Value> = requests
+                .iter()
+                .filter_map(|request| handle_request(backend, request, sink))
+                .collect();
+            (!responses.is_empty()).then(|| Value::Array(responses).to_string())
+        }
+        request => handle_request(backend, &request, sink).map(|response| response.to_string()),
+    }
+}
+
+/// The response with the `id` of a request, and its `result`.
+fn response(id: Value, result: Result<Value, Error>) -> Value {
+    let result = match result {
+        Ok(result) => ("result", result),
+        Err(error) => (
+            "error",
+            Value::object([
+                ("code", error.code.into()),
+                ("message", error.message.into()),
+            ]),
+        ),
+    };
+    Value::object([("jsonrpc", "2.0".into()), result, ("id", id)])
+}
+
+/// Respond to a single request, unless it is a notification, i.e. it has no id.
+fn handle_request(
+    backend: &mut dyn Backend,
+    request: &Value,
+    sink: Option<&Sink>,
+) -> Option<Value> {
+    let id = request.get("id").cloned();
+    let method = request.get("method").and_then(Value::as_str);
+    let (Some(method), Some("2.0")) = (method, request.get("jsonrpc").and_then(Value::as_str))
+    else {
+        let error = Error {
+            code: INVALID_REQUEST,
+            message: "Invalid request",
+        };
+        return Some(response(id.unwrap_or(Value::Null), Err(error)));
+    };
+
+    let result = match request.get("params") {
+        None => call(backend, method, &[], sink),
+        Some(Value::Array(params)) => call(backend, method, params, sink),
+        Some(_) => Err(Error::invalid_params("Params must be an array")),
+    };
+    id.map(|id| response(id, result))
+}
+
+/// Call `method` with the `params`.
+fn call(
+    backend: &mut dyn Backend,
+    method: &str,
+    params: &[Value],
+    sink: Option<&Sink>,
+) -> Result<Value, Error> {
+    let subscriptions = backend.subscriptions();
+    if let Some(result) = subscriptions
+        .subscribe(method, params, sink)
+        .or_else(|| subscriptions.unsubscribe(method, params, sink))
+    {
+        return result;
+    }
+
+    let chain = backend.chain();
+    match method {
+        "chain_getHeader" => {
+            let hash = block_hash(chain, param(params, 0))?;
+            Ok(chain.block(&hash).map(|block| header(&block.header)).into())
+        }
+        "chain_getBlock" => {
+            let hash = block_hash(chain, param(params, 0))?;
+            Ok(chain.block(&hash).map(block).into())
+        }
+        "state_getBalance" => {
+            let account = string_param(params, 0, "Invalid account")?.to_string();
+            let state = state(chain, param(params, 1))?;
+            Ok(state.balance_of(&account).into())
+        }
+        "state_getNonce" => {
+            let account = string_param(params, 0, "Invalid account")?.to_string();
+            let state = state(chain, param(params, 1))?;
+            Ok(state.account_nonce(&account).into())
+        }
+        "state_getClaim" => {
+            let claim = string_param(params, 0, "Invalid claim")?;
+            let state = state(chain, param(params, 1))?;
+            Ok(state.claim_owner(claim).into())
+        }
+        "payment_queryInfo" => {
+            let extrinsic = extrinsic_param(params, 0)?;
+            let state = state(chain, param(params, 1))?;
+            let info = state.query_fee_info(&extrinsic).map_err(Error::server)?;
+            Ok(Value::object([
+                ("weight", info.weight.into()),
+                ("partialFee", info.partial_fee.into()),
+            ]))
+        }
+        "system_dryRun" => {
+            let extrinsic = extrinsic_param(params, 0)?;
+            let state = state(chain, param(params, 1))?;
+            let dry_run = state.dry_run(extrinsic);
+            let result = match dry_run.result {
+                Ok(Ok(_)) => ("ok", Value::Null),
+                Ok(Err(error)) => ("error", error.into()),
+                Err(error) => ("invalid", error.into()),
+            };
+            Ok(Value::object([
+                ("result", Value::object([result])),
+                ("events", events(&dry_run.events)),
+                ("weight", dry_run.weight.into()),
+            ]))
+        }
+        "state_getMetadata" => Ok(metadata(&Runtime::metadata())),
+        "system_accountNextIndex" => {
+            let account = string_param(params, 0, "Invalid account")?.to_string();
+            let mut nonce = chain.best_state().account_nonce(&account);
+            // The transactions of the account in the pool come first.
+            let pending = backend.pending_extrinsics();
+            while pending.iter().any(|ext| {
+                ext.signer
+                    .as_ref()
+                    .is_some_and(|signer| signer.who == account && signer.nonce == nonce)
+            }) {
+                nonce += 1;
+            }
+            Ok(nonce.into())
+        }
+        "author_submitExtrinsic" => {
+            let extrinsic = extrinsic_param(params, 0)?;
+            let hash = backend.submit_extrinsic(extrinsic).map_err(Error::server)?;
+            Ok(to_hex(&hash).into())
+        }
+        _ => Err(Error {
+            code: METHOD_NOT_FOUND,
+            message: "Method not found",
+        }),
+    }
+}
+
+/// The param at `index`, if it is given and not `null`.
+fn param(params: &[Value], index: usize) -> Option<&Value> {
+    params.get(index).filter(|param| **param != Value::Null)
+}
+
+/// The string param at `index`, or the `error` if it is missing.
+fn string_param<'a>(
+    params: &'a [Value],
+    index: usize,
+    error: &'static str,
+) -> Result<&'a str, Error> {
+    param(params, index)
+        .and_then(Value::as_str)
+        .ok_or(Error::invalid_params(error))
+}
+
+/// The encoded extrinsic param at `index`.
+fn extrinsic_param(params: &[Value], index: usize) -> Result<types::Extrinsic, Error> {
+    string_param(params, index, "Invalid extrinsic")
+        .ok()
+        .and_then(bytes_from_hex)
+        .and_then(|bytes| types::Extrinsic::decode(&bytes).ok())
+        .ok_or(Error::invalid_params("Invalid extrinsic"))
+}
+
+/// The hash of the block given by `param`, or of the best block without it.
+fn block_hash(chain: &Chain, param: Option<&Value>) -> Result<Hash, Error> {
+    match param {
+        None => Ok(chain.best_hash()),
+        Some(param) => param
+            .as_str()
+            .and_then(hashing::from_hex)
+            .ok_or(Error::invalid_params("Invalid block hash")),
+    }
+}
+
+/// The state after the block given by `param`, or after the best block without it.
+fn state<'a>(chain: &'a Chain, param: Option<&Value>) -> Result<&'a Runtime, Error> {
+    let hash = block_hash(chain, param)?;
+    chain
+        .state(&hash)
+        .ok_or(Error::server("State of the block is not available"))
+}
+
+/// Parse bytes formatted by `to_hex`.
+fn bytes_from_hex(hex: &str) -> Option<Vec<u8>> {
+    let hex = hex.strip_prefix("0x")?;
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// The JSON of a header. The items of the digest are encoded.
+fn header(header: &types::Header) -> Value {
+    let logs: Vec<String> = header
+        .digest
+        .logs
+        .iter()
+        .map(|item| to_hex(&item.encode()))
+        .collect();
+    Value::object([
+        ("parentHash", to_hex(&header.parent_hash).into()),
+        ("number", header.block_number.into()),
+        ("stateRoot", to_hex(&header.state_root).into()),
+        ("extrinsicsRoot", to_hex(&header.extrinsics_root).into()),
+        ("digest", Value::object([("logs", logs.into())])),
+    ])
+}
+
+/// The JSON of event records, in order. The phase is `{"applyExtrinsic": index}`,
+/// `"initialization"` or `"finalization"`, and the events are encoded.
+fn events(records: &[EventRecord<RuntimeEvent>]) -> Value {
+    let events = records
+        .iter()
+        .map(|record| {
+            let phase = match record.phase {
+                Phase::ApplyExtrinsic(index) => Value::object([("applyExtrinsic", index.into())]),
+                Phase::Finalization => "finalization".into(),
+                Phase::Initialization => "initialization".into(),
+            };
+            Value::object([
+                ("phase", phase),
+                ("event", to_hex(&record.event.encode()).into()),
+            ])
+        })
+        .collect();
+    Value::Array(events)
+}
+
+/// The JSON of a block. The extrinsics are encoded.
+fn block(block: &types::Block) -> Value {
+    let extrinsics: Vec<String> = block
+        .extrinsics
+        .iter()
+        .map(|ext| to_hex(&ext.encode()))
+        .collect();
+    Value::object([(
+        "block",
+        Value::object([
+            ("header", header(&block.header)),
+            ("extrinsics", extrinsics.into()),
+        ]),
+    )])
+}
+
+/// The JSON of the metadata of the runtime, as
+/// `{"pallets": [{"name", "index", "calls", "events", "errors", "storage", "constants"}]}`. Calls
+/// and events are `{"name", "index", "fields"}`, and their fields and the storage items are
+/// `{"name", "type"}`. Constants are `{"name", "type", "value"}`, with the value formatted as in
+/// Rust.
+pub fn metadata(metadata: &RuntimeMetadata) -> Value {
+    let fields = |fields: &[FieldMetadata]| {
+        let fields = fields
+            .iter()
+            .map(|field| {
+                Value::object([
+                    ("name", field.name.into()),
+                    ("type", field.type_name.as_str().into()),
+                ])
+            })
+            .collect();
+        Value::Array(fields)
+    };
+    let variants = |variants: &[VariantMetadata]| {
+        let variants = variants
+            .iter()
+            .map(|variant| {
+                Value::object([
+                    ("name", variant.name.into()),
+                    ("index", variant.index.into()),
+                    ("fields", fields(&variant.fields)),
+                ])
+            })
+            .collect();
+        Value::Array(variants)
+    };
+    let pallets = metadata
+        .pallets
+        .iter()
+        .map(|pallet| {
+            let constants = pallet
+                .constants
+                .iter()
+                .map(|constant| {
+                    Value::object([
+                        ("name", constant.name.into()),
+                        ("type", constant.type_name.as_str().into()),
+                        ("value", constant.value.as_str().into()),
+                    ])
+                })
+                .collect();
+            Value::object([
+                ("name", pallet.name.into()),
+                ("index", pallet.index.into()),
+                ("calls", variants(&pallet.calls)),
+                ("events", variants(&pallet.events)),
+                ("errors", pallet.errors.clone().into()),
+                ("storage", fields(&pallet.storage)),
+                ("constants", Value::Array(constants)),
+            ])
+        })
+        .collect();
+    Value::object([("pallets", Value::Array(pallets))])
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        handle, json, json::Value, server::Sink, subscriptions::Subscriptions, websocket, Backend,
+    };
+    use crate::{
+        balances,
+        chain::{Chain, ChainConfig},
+        proof_of_existence,
+        support::{codec::Encode, hashing::to_hex, hashing::Hash, RuntimeApi},
+        transaction_pool::TransactionPool,
+        types, DevAccount, Runtime, RuntimeCall, BLOCK_TIME, DEV_POW,
+    };
+
+    // The chain and the pool of a node, without its network.
+    struct TestBackend {
+        chain: Chain,
+        pool: TransactionPool<Runtime>,
+        subscriptions: Subscriptions,
+    }
+
+    impl Backend for TestBackend {
+        fn chain(&self) -> &Chain {
+            &self.chain
+        }
+
+        fn pending_extrinsics(&self) -> Vec<types::Extrinsic> {
+            self.pool.ready()
+        }
+
+        fn submit_extrinsic(&mut self, extrinsic: types::Extrinsic) -> Result<Hash, &'static str> {
+            self.pool.submit(self.chain.best_state(), extrinsic)
+        }
+
+        fn subscriptions(&mut self) -> &mut Subscriptions {
+            &mut self.subscriptions
+        }
+    }
+
+    impl TestBackend {
+        fn new() -> Self {
+            Self {
+                chain: Chain::new(crate::dev_genesis(), ChainConfig::default()),
+                pool: TransactionPool::new(),
+                subscriptions: Subscriptions::new(),
+            }
+        }
+
+        // Author a block with the transactions of the pool, and notify the subscriptions.
+        fn author_block(&mut self, block_number: u64) {
+            let inherents = crate::inherents(block_number * BLOCK_TIME);
+            let imported =
+                crate::author_block(&mut self.chain, &mut self.pool, &DEV_POW, inherents).unwrap();
+            self.subscriptions.notify(&self.chain, &imported.enacted);
+        }
+
+        // Call `method` with the JSON `params`, and return the JSON of the response.
+        fn call(&mut self, method: &str, params: &str) -> String {
+            let request =
+                format!(r#"{{"jsonrpc":"2.0","id":1,"method":"{method}","params":{params}}}"#);
+            handle(self, &request, None).unwrap()
+        }
+    }
+
+    // The JSON of the successful response with the `result`.
+    fn result(result: &str) -> String {
+        format!(r#"{{"jsonrpc":"2.0","result":{result},"id":1}}"#)
+    }
+
+    // The JSON of the error response with the `code` and the `message`.
+    fn error(code: i64, message: &str) -> String {
+        format!(r#"{{"jsonrpc":"2.0","error":{{"code":{code},"message":"{message}"}},"id":1}}"#)
+    }
+
+    #[test]
+    fn blocks_and_state_are_read() {
+        let mut backend = TestBackend::new();
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+        let genesis_hash = to_hex(&backend.chain.best_hash());
+        for call in [
+            RuntimeCall::balances(balances::Call::transfer {
+                to: bob.id(),
+                amount: 10,
+            }),
+            RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+                claim: "my document".to_string(),
+            }),
+        ] {
+            backend
+                .pool
+                .submit(backend.chain.best_state(), alice.sign(call))
+                .unwrap();
+        }
+        backend.author_block(1);
+        let best = backend
+            .chain
+            .block(&backend.chain.best_hash())
+            .unwrap()
+            .clone();
+
+        let header = json::parse(&backend.call("chain_getHeader", "[]")).unwrap();
+        let header = header.get("result").unwrap();
+        assert_eq!(header.get("number"), Some(&1u128.into()));
+        assert_eq!(header.get("parentHash"), Some(&genesis_hash.clone().into()));
+        let block = json::parse(&backend.call("chain_getBlock", "[]")).unwrap();
+        let extrinsics = block
+            .get("result")
+            .unwrap()
+            .get("block")
+            .unwrap()
+            .get("extrinsics");
+        assert_eq!(
+            extrinsics,
+            Some(&json::Value::from(
+                best.extrinsics
+                    .iter()
+                    .map(|ext| to_hex(&ext.encode()))
+                    .collect::<Vec<_>>()
+            ))
+        );
+        let unknown = format!(r#"["{}"]"#, to_hex(&[0; 32]));
+        assert_eq!(backend.call("chain_getBlock", &unknown), result("null"));
+
+        // The state is read after the best block, or after a given block.
+        let bob_balance = format!(r#"["{}"]"#, bob.id());
+        assert_eq!(backend.call("state_getBalance", &bob_balance), result("10"));
+        let at_genesis = format!(r#"["{}", "{genesis_hash}"]"#, bob.id());
+        assert_eq!(backend.call("state_getBalance", &at_genesis), result("0"));
+        assert_eq!(
+            backend.call("state_getClaim", r#"["my document"]"#),
+            result(&format!(r#""{}""#, alice.id()))
+        );
+        assert_eq!(
+            backend.call("state_getClaim", r#"["other document"]"#),
+            result("null")
+        );
+        assert_eq!(
+            backend.call(
+                "state_getBalance",
+                &format!(r#"["{}", "{}"]"#, bob.id(), to_hex(&[0; 32]))
+            ),
+            error(-32000, "State of the block is not available")
+        );
+    }
+
+    #[test]
+    fn extrinsics_are_submitted() {
+        let mut backend = TestBackend::new();
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+        let account = format!(r#"["{}"]"#, alice.id());
+        assert_eq!(
+            backend.call("system_accountNextIndex", &account),
+            result("0")
+        );
+
+        let ext = alice.sign(RuntimeCall::balances(balances::Call::transfer {
+            to: bob.id(),
+            amount: 10,
+        }));
+        let params = format!(r#"["{}"]"#, to_hex(&ext.encode()));
+        let hash = to_hex(&crate::support::hashing::hash(&ext.encode()));
+        assert_eq!(
+            backend.call("author_submitExtrinsic", &params),
+            result(&format!(r#""{hash}""#))
+        );
+        assert_eq!(
+            backend.call("author_submitExtrinsic", &params),
+            error(-32000, "Transaction is already in the pool")
+        );
+        assert_eq!(
+            backend.call("author_submitExtrinsic", r#"["0x00"]"#),
+            error(-32602, "Invalid extrinsic")
+        );
+
+        // The next nonce of alice comes after her transaction in the pool.
+        assert_eq!(
+            backend.call("system_accountNextIndex", &account),
+            result("1")
+        );
+        backend.author_block(1);
+        assert_eq!(
+            backend.call("system_accountNextIndex", &account),
+            result("1")
+        );
+    }
+
+    #[test]
+    fn runtime_api_is_called_at_a_block() {
+        let mut backend = TestBackend::new();
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+        let genesis_hash = to_hex(&backend.chain.best_hash());
+        let ext = alice.sign(RuntimeCall::balances(balances::Call::transfer {
+            to: bob.id(),
+            amount: 10,
+        }));
+        backend.submit_extrinsic(ext.clone()).unwrap();
+        backend.author_block(1);
+
+        let nonce = format!(r#"["{}"]"#, alice.id());
+        assert_eq!(backend.call("state_getNonce", &nonce), result("1"));
+        let at_genesis = format!(r#"["{}", "{genesis_hash}"]"#, alice.id());
+        assert_eq!(backend.call("state_getNonce", &at_genesis), result("0"));
+
+        let info = backend.chain.best_state().query_fee_info(&ext).unwrap();
+        let params = format!(r#"["{}", "{genesis_hash}"]"#, to_hex(&ext.encode()));
+        assert_eq!(
+            backend.call("payment_queryInfo", &params),
+            result(&format!(
+                r#"{{"weight":{},"partialFee":{}}}"#,
+                info.weight, info.partial_fee
+            ))
+        );
+        assert_eq!(
+            backend.call("payment_queryInfo", r#"["0x00"]"#),
+            error(-32602, "Invalid extrinsic")
+        );
+    }
+
+    #[test]
+    fn extrinsics_are_dry_run() {
+        let mut backend = TestBackend::new();
+        let mut alice = DevAccount::new("alice");
+        let best_hash = backend.chain.best_hash();
+        let claim = RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+            claim: "my document".to_string(),
+        });
+        let ext = alice.sign(claim.clone());
+        let params = format!(r#"["{}"]"#, to_hex(&ext.encode()));
+
+        let dry_run = json::parse(&backend.call("system_dryRun", &params)).unwrap();
+        let dry_run = dry_run.get("result").unwrap();
+        assert_eq!(dry_run.get("result").unwrap().to_string(), r#"{"ok":null}"#);
+        let expected = backend.chain.best_state().dry_run(ext.clone());
+        assert_eq!(
+            dry_run.get("events"),
+            Some(&super::events(&expected.events))
+        );
+        assert_eq!(dry_run.get("weight"), Some(&expected.weight.into()));
+        // The chain did not change.
+        assert_eq!(backend.chain.best_hash(), best_hash);
+        assert_eq!(
+            backend.call("state_getClaim", r#"["my document"]"#),
+            result("null")
+        );
+
+        // Once the claim exists, creating it again fails.
+        backend.submit_extrinsic(ext).unwrap();
+        backend.author_block(1);
+        let ext = alice.sign(claim);
+        let params = format!(r#"["{}"]"#, to_hex(&ext.encode()));
+        let dry_run = json::parse(&backend.call("system_dryRun", &params)).unwrap();
+        assert_eq!(
+            dry_run
+                .get("result")
+                .unwrap()
+                .get("result")
+                .unwrap()
+                .to_string(),
+            r#"{"error":"Claim already exists"}"#
+        );
+        let at_genesis = format!(r#"["{}", "{}"]"#, to_hex(&ext.encode()), to_hex(&best_hash));
+        let dry_run = json::parse(&backend.call("system_dryRun", &at_genesis)).unwrap();
+        assert_eq!(
+            dry_run
+                .get("result")
+                .unwrap()
+                .get("result")
+                .unwrap()
+                .to_string(),
+            r#"{"invalid":"invalid transaction nonce"}"#
+        );
+    }
+
+    #[test]
+    fn metadata_is_served() {
+        let mut backend = TestBackend::new();
+        let response = json::parse(&backend.call("state_getMetadata", "[]")).unwrap();
+        let metadata = response.get("result").unwrap();
+        assert_eq!(metadata, &super::metadata(&Runtime::metadata()));
+
+        let Some(Value::Array(pallets)) = metadata.get("pallets") else {
+            panic!("pallets are an array");
+        };
+        let poe = &pallets[2];
+        assert_eq!(poe.get("name"), Some(&"proof_of_existence".into()));
+        assert_eq!(poe.get("index"), Some(&2u8.into()));
+        let Some(Value::Array(calls)) = poe.get("calls") else {
+            panic!("calls are an array");
+        };
+        assert_eq!(
+            calls[0].to_string(),
+            r#"{"name":"create_claim","index":0,"fields":[{"name":"claim","type":"String"}]}"#
+        );
+        let Some(Value::Array(constants)) = pallets[5].get("constants") else {
+            panic!("constants are an array");
+        };
+        assert_eq!(
+            constants[0].to_string(),
+            r#"{"name":"MAX_PROXIES","type":"u32","value":"10"}"#
+        );
+    }
+
+    #[test]
+    fn invalid_requests_are_rejected() {
+        let mut backend = TestBackend::new();
+        assert_eq!(
+            handle(&mut backend, "{", None),
+            Some(
+                r#"{"jsonrpc":"2.0","error":{"code":-32700,"message":"Parse error"},"id":null}"#
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            handle(&mut backend, r#"{"id":1,"method":"chain_getHeader"}"#, None),
+            Some(error(-32600, "Invalid request"))
+        );
+        assert_eq!(
+            backend.call("chain_getState", "[]"),
+            error(-32601, "Method not found")
+        );
+        assert_eq!(
+            backend.call("chain_getHeader", r#"{"hash":null}"#),
+            error(-32602, "Params must be an array")
+        );
+        assert_eq!(
+            backend.call("chain_getHeader", r#"["0x01"]"#),
+            error(-32602, "Invalid block hash")
+        );
+        assert_eq!(
+            backend.call("state_getBalance", "[]"),
+            error(-32602, "Invalid account")
+        );
+    }
+
+    #[test]
+    fn batches_and_notifications() {
+        let mut backend = TestBackend::new();
+        let notification = r#"{"jsonrpc":"2.0","method":"chain_getHeader"}"#;
+        assert_eq!(handle(&mut backend, notification, None), None);
+        assert_eq!(
+            handle(&mut backend, &format!("[{notification}]"), None),
+            None
+        );
+
+        let batch =
+            format!(r#"[{notification}, {{"jsonrpc":"2.0","id":1,"method":"chain_getClaim"}}, 1]"#);
+        assert_eq!(
+            handle(&mut backend, &batch, None),
+            Some(format!(
+                "[{},{}]",
+                error(-32601, "Method not found"),
+                r#"{"jsonrpc":"2.0","error":{"code":-32600,"message":"Invalid request"},"id":null}"#
+            ))
+        );
+        assert_eq!(
+            handle(&mut backend, "[]", None),
+            Some(r#"{"jsonrpc":"2.0","error":{"code":-32600,"message":"Invalid request"},"id":null}"#.to_string())
+        );
+    }
+
+    #[test]
+    fn subscriptions_are_notified() {
+        let mut backend = TestBackend::new();
+        let (mut alice, bob) = (DevAccount::new("alice"), DevAccount::new("bob"));
+        let (sink, buffer) = Sink::buffer();
+        let subscribe = |backend: &mut TestBackend, method: &str, params: &str| {
+            let request =
+                format!(r#"{{"jsonrpc":"2.0","id":1,"method":"{method}","params":{params}}}"#);
+            handle(backend, &request, Some(&sink)).unwrap()
+        };
+        // The notifications sent since the last call, as `(method, subscription, result)`.
+        let notifications = || {
+            let frames = std::mem::take(&mut *buffer.lock().unwrap());
+            websocket::server_messages(&frames)
+                .iter()
+                .map(|message| {
+                    let message = json::parse(message).unwrap();
+                    let params = message.get("params").unwrap();
+                    (
+                        message.get("method").unwrap().as_str().unwrap().to_string(),
+                        params.get("subscription").unwrap().to_string(),
+                        params.get("result").unwrap().clone(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            backend.call("chain_subscribeNewHeads", "[]"),
+            error(-32000, "Subscriptions are only available over WebSocket")
+        );
+        assert_eq!(
+            subscribe(&mut backend, "state_subscribeStorage", "[[{}]]"),
+            error(-32602, "Invalid storage key")
+        );
+        let keys = format!(
+            r#"[[{{"balance":"{}"}}, {{"claim":"my document"}}]]"#,
+            bob.id()
+        );
+        for (i, (method, params)) in [
+            ("chain_subscribeNewHeads", "[]"),
+            ("chain_subscribeFinalizedHeads", "[]"),
+            ("state_subscribeStorage", &keys),
+            ("state_subscribeEvents", "[]"),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            assert_eq!(
+                subscribe(&mut backend, method, params),
+                result(&i.to_string())
+            );
+        }
+
+        // The new subscriptions are notified of the current finalized block and storage values.
+        backend.subscriptions.notify(&backend.chain, &[]);
+        let genesis = json::Value::from(to_hex(&backend.chain.best_hash()));
+        let notified = notifications();
+        assert_eq!(notified.len(), 2);
+        assert_eq!(notified[0].0, "chain_finalizedHead");
+        assert_eq!(notified[0].2.get("number"), Some(&0u128.into()));
+        assert_eq!(
+            notified[1].2.to_string(),
+            format!(
+                r#"{{"block":{genesis},"changes":[[{{"balance":"{}"}},0],[{{"claim":"my document"}},null]]}}"#,
+                bob.id()
+            )
+        );
+
+        let transfer = alice.sign(RuntimeCall::balances(balances::Call::transfer {
+            to: bob.id(),
+            amount: 10,
+        }));
+        backend.submit_extrinsic(transfer).unwrap();
+        backend.author_block(1);
+        let best = json::Value::from(to_hex(&backend.chain.best_hash()));
+        let notified = notifications();
+        let methods: Vec<&str> = notified.iter().map(|n| n.0.as_str()).collect();
+        assert_eq!(methods, ["chain_newHead", "state_storage", "state_events"]);
+        assert_eq!(notified[0].2.get("number"), Some(&1u128.into()));
+        // Only the values which changed are notified.
+        assert_eq!(
+            notified[1].2.to_string(),
+            format!(
+                r#"{{"block":{best},"changes":[[{{"balance":"{}"}},10]]}}"#,
+                bob.id()
+            )
+        );
+        let Some(json::Value::Array(events)) = notified[2].2.get("events") else {
+            panic!("events are an array");
+        };
+        // The outcome of the two inherents, and the transfer with its outcome.
+        assert_eq!(events.len(), 4);
+        assert_eq!(
+            events[2].get("phase").unwrap().to_string(),
+            r#"{"applyExtrinsic":2}"#
+        );
+
+        // Only the connection of a subscription can unsubscribe.
+        let (other_sink, _) = Sink::buffer();
+        let unsubscribe =
+            r#"{"jsonrpc":"2.0","id":1,"method":"chain_unsubscribeNewHeads","params":[0]}"#;
+        assert_eq!(
+            handle(&mut backend, unsubscribe, Some(&other_sink)),
+            Some(result("false"))
+        );
+        assert_eq!(
+            subscribe(&mut backend, "chain_unsubscribeNewHeads", "[0]"),
+            result("true")
+        );
+        backend.author_block(2);
+        let methods: Vec<String> = notifications().into_iter().map(|n| n.0).collect();
+        assert_eq!(methods, ["state_events"]);
+    }
+}
@@ -0,0 +1,380 @@
+//! A minimal JSON implementation for the RPC server: a `Value` is parsed from the text of a
+//! request, and formatted as the text of its response.
+
+use std::fmt;
+
+/// How deep arrays and objects can be nested, so parsing untrusted text can not overflow the
+/// stack.
+const MAX_DEPTH: usize = 64;
+
+/// A JSON value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    /// A number, kept as its JSON text so that integers of any size are exact.
+    Number(String),
+    String(String),
+    Array(Vec<Value>),
+    /// The members of an object, in order.
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// An object with the `members`.
+    pub fn object<'a>(members: impl IntoIterator<Item = (&'a str, Value)>) -> Self {
+        Value::Object(
+            members
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    /// The member `key` of an object.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// The text of a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+macro_rules! impl_from_int {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for Value {
+                fn from(value: $t) -> Self {
+                    Value::Number(value.to_string())
+                }
+            }
+        )*
+    };
+}
+
+impl_from_int!(u8, u16, u32, u64, u128, i64);
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(value: Vec<T>) -> Self {
+        Value::Array(value.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Write `s` as a JSON string, with its quotes.
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    f.write_str("\"")
+}
+
+/// Formats the value as compact JSON text.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("null"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Number(n) => f.write_str(n),
+            Value::String(s) => write_string(f, s),
+            Value::Array(items) => {
+                f.write_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                f.write_str("]")
+            }
+            Value::Object(members) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+/// Parse `text`, which must contain a single JSON value.
+pub fn parse(text: &str) -> Result<Value, &'static str> {
+    let mut parser = Parser {
+        input: text.as_bytes(),
+        pos: 0,
+    };
+    let value = parser.value(0)?;
+    parser.skip_whitespace();
+    if parser.pos != parser.input.len() {
+        return Err("json has text left after the value");
+    }
+    Ok(value)
+}
+
+/// Parses JSON text, one value at a time.
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Result<u8, &'static str> {
+        let byte = self.peek().ok_or("unexpected end of json")?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn expect(&mut self, literal: &str) -> Result<(), &'static str> {
+        if !self.input[self.pos..].starts_with(literal.as_bytes()) {
+            return Err("invalid json literal");
+        }
+        self.pos += literal.len();
+        Ok(())
+    }
+
+    /// Parse the value at the current position, nested in `depth` arrays and objects.
+    fn value(&mut self, depth: usize) -> Result<Value, &'static str> {
+        if depth > MAX_DEPTH {
+            return Err("json is nested too deep");
+        }
+        self.skip_whitespace();
+        match self.peek().ok_or("unexpected end of json")? {
+            b'n' => self.expect("null").map(|_| Value::Null),
+            b't' => self.expect("true").map(|_| Value::Bool(true)),
+            b'f' => self.expect("false").map(|_| Value::Bool(false)),
+            b'"' => self.string().map(Value::String),
+            b'[' => self.array(depth),
+            b'{' => self.object(depth),
+            b'-' | b'0'..=b'9' => self.number(),
+            _ => Err("invalid json value"),
+        }
+    }
+
+    fn digits(&mut self) -> Result<(), &'static str> {
+        let start = self.pos;
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return Err("invalid json number");
+        }
+        Ok(())
+    }
+
+    fn number(&mut self) -> Result<Value, &'static str> {
+        let start = self.pos;
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        if self.peek() == Some(b'0') {
+            self.pos += 1;
+        } else {
+            self.digits()?;
+        }
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            self.digits()?;
+        }
+        if let Some(b'e' | b'E') = self.peek() {
+            self.pos += 1;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.pos += 1;
+            }
+            self.digits()?;
+        }
+        let text = std::str::from_utf8(&self.input[start..self.pos]).expect("digits are ascii");
+        Ok(Value::Number(text.to_string()))
+    }
+
+    fn hex4(&mut self) -> Result<u32, &'static str> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = (self.next()? as char)
+                .to_digit(16)
+                .ok_or("invalid json escape")?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+
+    fn string(&mut self) -> Result<String, &'static str> {
+        self.pos += 1;
+        let mut bytes = Vec::new();
+        loop {
+            match self.next()? {
+                b'"' => break,
+                b'\\' => {
+                    let c = match self.next()? {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            let mut code = self.hex4()?;
+                            // Characters outside the basic plane are escaped as surrogate pairs.
+                            if (0xd800..0xdc00).contains(&code) {
+                                self.expect("\\u").map_err(|_| "invalid json escape")?;
+                                let low = self.hex4()?;
+                                if !(0xdc00..0xe000).contains(&low) {
+                                    return Err("invalid json escape");
+                                }
+                                code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                            }
+                            char::from_u32(code).ok_or("invalid json escape")?
+                        }
+                        _ => return Err("invalid json escape"),
+                    };
+                    bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                byte if byte < 0x20 => return Err("invalid json string"),
+                byte => bytes.push(byte),
+            }
+        }
+        // The input is a `str`, and escapes are valid characters, so this never fails.
+        String::from_utf8(bytes).map_err(|_| "invalid json string")
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Value, &'static str> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Value::Array(items));
+        }
+        loop {
+            items.push(self.value(depth + 1)?);
+            self.skip_whitespace();
+            match self.next()? {
+                b',' => continue,
+                b']' => return Ok(Value::Array(items)),
+                _ => return Err("invalid json array"),
+            }
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Value, &'static str> {
+        self.pos += 1;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Value::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err("invalid json object");
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            if self.next()? != b':' {
+                return Err("invalid json object");
+            }
+            members.push((key, self.value(depth + 1)?));
+            self.skip_whitespace();
+            match self.next()? {
+                b',' => continue,
+                b'}' => return Ok(Value::Object(members)),
+                _ => return Err("invalid json object"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse, Value};
+
+    #[test]
+    fn parse_and_format() {
+        let text = r#" { "jsonrpc" : "2.0", "id": -1.5e3, "params": [null, true, false, [], {}],
+            "big": 340282366920938463463374607431768211455 } "#;
+        let value = parse(text).unwrap();
+        assert_eq!(value.get("jsonrpc").and_then(Value::as_str), Some("2.0"));
+        assert_eq!(value.get("id"), Some(&Value::Number("-1.5e3".to_string())));
+        assert_eq!(value.get("big"), Some(&Value::from(u128::MAX)));
+        assert_eq!(
+            value.to_string(),
+            r#"{"jsonrpc":"2.0","id":-1.5e3,"params":[null,true,false,[],{}],"big":340282366920938463463374607431768211455}"#
+        );
+        assert_eq!(parse(&value.to_string()), Ok(value));
+    }
+
+    #[test]
+    fn strings_are_escaped() {
+        let value = parse(r#""a\"b\\c\/d\n\u00e9\ud83d\ude00\u0001""#).unwrap();
+        assert_eq!(value, Value::from("a\"b\\c/d\né😀\u{1}"));
+        assert_eq!(value.to_string(), r#""a\"b\\c/d\né😀\u0001""#);
+    }
+
+    #[test]
+    fn invalid_json_is_rejected() {
+        assert_eq!(parse(""), Err("unexpected end of json"));
+        assert_eq!(parse("[1,]"), Err("invalid json value"));
+        assert_eq!(parse("{\"a\" 1}"), Err("invalid json object"));
+        assert_eq!(parse("01"), Err("json has text left after the value"));
+        assert_eq!(parse("nul"), Err("invalid json literal"));
+        assert_eq!(parse("\"\\ud800\""), Err("invalid json escape"));
+        assert_eq!(parse(&"[".repeat(100)), Err("json is nested too deep"));
+    }
+}
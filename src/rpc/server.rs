@@ -0,0 +1,435 @@
+//! The transport of the RPC server. A client sends JSON-RPC requests over HTTP, as the body of a
+//! `POST`, or over a WebSocket its connection is upgraded to, on the same address.
+//!
+//! Each connection is served by its own thread, which hands the requests to the node as
+//! `Request`s. Over HTTP it waits for the node to respond, while over a WebSocket the node writes
+//! the responses itself, through the `Sink` of the connection, along with the notifications of
+//! its subscriptions.
+
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use super::{websocket, MAX_REQUEST_LENGTH};
+
+/// The maximum length of the request line and of each header of an HTTP request.
+const MAX_LINE_LENGTH: u64 = 8 * 1024;
+
+/// The maximum number of headers of an HTTP request.
+const MAX_HEADERS: usize = 64;
+
+/// The sending half of a WebSocket, which the node writes messages to at any time, e.g. the
+/// notifications of a subscription. Clones write to the same connection.
+#[derive(Clone)]
+pub struct Sink(Arc<Mutex<dyn Write + Send>>);
+
+impl Sink {
+    /// Send `text` as a message. Fails once the connection is closed.
+    pub fn send(&self, text: &str) -> io::Result<()> {
+        let mut stream = self
+            .0
+            .lock()
+            .map_err(|_| invalid_data("Sink is poisoned"))?;
+        websocket::write_text(&mut *stream, text)
+    }
+
+    /// Whether both sinks write to the same connection.
+    pub fn same_connection(&self, other: &Sink) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    // A sink writing the frames of its messages to a buffer, instead of a connection.
+    #[cfg(test)]
+    pub fn buffer() -> (Self, Arc<Mutex<Vec<u8>>>) {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        (Self(buffer.clone()), buffer)
+    }
+}
+
+/// Where the response to a request goes.
+enum Reply {
+    /// Back to the thread of an HTTP connection, which waits for it.
+    Http(mpsc::Sender<Option<String>>),
+    /// Straight to the client of a WebSocket.
+    WebSocket(Sink),
+}
+
+/// The JSON text of a request of a client, which the node must respond to.
+pub struct Request {
+    body: String,
+    reply: Reply,
+}
+
+impl Request {
+    /// The JSON text of the request.
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+
+    /// The sink of the connection of the request, if it is a WebSocket, which can be notified
+    /// later on.
+    pub fn sink(&self) -> Option<&Sink> {
+        match &self.reply {
+            Reply::Http(_) => None,
+            Reply::WebSocket(sink) => Some(sink),
+        }
+    }
+
+    /// Send the JSON text of the response to the client, or `None` if there is nothing to
+    /// respond, e.g. to a notification.
+    pub fn respond(self, response: Option<String>) {
+        // The client may be gone already.
+        match self.reply {
+            Reply::Http(reply) => {
+                let _ = reply.send(response);
+            }
+            Reply::WebSocket(sink) => {
+                if let Some(response) = response {
+                    let _ = sink.send(&response);
+                }
+            }
+        }
+    }
+}
+
+/// This is the RPC Server.
+/// It accepts HTTP and WebSocket connections on a local address, and turns the JSON-RPC requests
+/// of its clients into `Request`s.
+pub struct Server {
+    local_addr: SocketAddr,
+    requests: mpsc::Receiver<Request>,
+}
+
+impl Server {
+    /// Start listening on `addr` for clients.
+    pub fn start(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let (sender, requests) = mpsc::channel();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let requests = sender.clone();
+                thread::spawn(move || {
+                    // The connection is closed on any error, e.g. a malformed request.
+                    let _ = serve(stream, requests);
+                });
+            }
+        });
+
+        Ok(Self {
+            local_addr,
+            requests,
+        })
+    }
+
+    /// The address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Wait at most `timeout` for the next request of a client.
+    pub fn next_request(&self, timeout: Duration) -> Option<Request> {
+        self.requests.recv_timeout(timeout).ok()
+    }
+}
+
+/// The head and body of an HTTP request.
+struct HttpRequest {
+    method: String,
+    /// The headers, with lowercase names.
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl HttpRequest {
+    /// The value of the header `name`, in lowercase.
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn invalid_data(error: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+/// Read a line of an HTTP request, without its line break, or `None` at the end of the stream.
+fn read_line(reader: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    if reader.take(MAX_LINE_LENGTH).read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    if !line.ends_with('\n') {
+        return Err(invalid_data("HTTP line is too long"));
+    }
+    Ok(Some(line.trim_end().to_string()))
+}
+
+/// Read the next HTTP request on a connection, or `None` once the client closed it.
+fn read_http_request(reader: &mut impl BufRead) -> io::Result<Option<HttpRequest>> {
+    let Some(request_line) = read_line(reader)? else {
+        return Ok(None);
+    };
+    let method = request_line
+        .split(' ')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(reader)?.ok_or(invalid_data("HTTP request is truncated"))?;
+        if line.is_empty() {
+            break;
+        }
+        if headers.len() == MAX_HEADERS {
+            return Err(invalid_data("HTTP request has too many headers"));
+        }
+        let (name, value) = line
+            .split_once(':')
+            .ok_or(invalid_data("Invalid HTTP header"))?;
+        headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+    }
+
+    let mut request = HttpRequest {
+        method,
+        headers,
+        body: Vec::new(),
+    };
+    if let Some(len) = request.header("content-length") {
+        let len: usize = len
+            .parse()
+            .map_err(|_| invalid_data("Invalid HTTP content length"))?;
+        if len > MAX_REQUEST_LENGTH {
+            return Err(invalid_data("Request is too long"));
+        }
+        request.body = vec![0; len];
+        reader.read_exact(&mut request.body)?;
+    }
+    Ok(Some(request))
+}
+
+/// Write an HTTP response with the `status` and the JSON `body`, if any. Any origin is allowed,
+/// so the pages of a browser can call the server.
+fn write_http_response(
+    stream: &mut impl Write,
+    status: &str,
+    body: Option<&str>,
+) -> io::Result<()> {
+    let body = body.unwrap_or_default();
+    let response = format!(
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Allow-Methods: POST, OPTIONS\r\n\
+         Access-Control-Allow-Headers: Content-Type\r\n\
+         \r\n\
+         {body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes())
+}
+
+/// Hand the request with the JSON text `body` to the node, and wait for its response.
+fn call(requests: &mpsc::Sender<Request>, body: String) -> io::Result<Option<String>> {
+    let (reply, response) = mpsc::channel();
+    let reply = Reply::Http(reply);
+    requests
+        .send(Request { body, reply })
+        .map_err(|_| stopped())?;
+    response.recv().map_err(|_| stopped())
+}
+
+fn stopped() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "Node is stopped")
+}
+
+/// Serve the HTTP requests of a connection, until it is closed or upgraded to a WebSocket.
+fn serve(stream: TcpStream, requests: mpsc::Sender<Request>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    while let Some(request) = read_http_request(&mut reader)? {
+        let upgrade = request.header("upgrade");
+        if upgrade.is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket")) {
+            let key = request
+                .header("sec-websocket-key")
+                .ok_or(invalid_data("Missing WebSocket key"))?;
+            let response = format!(
+                "HTTP/1.1 101 Switching Protocols\r\n\
+                 Upgrade: websocket\r\n\
+                 Connection: Upgrade\r\n\
+                 Sec-WebSocket-Accept: {}\r\n\
+                 \r\n",
+                websocket::accept_key(key)
+            );
+            writer.write_all(response.as_bytes())?;
+            let writer = Sink(Arc::new(Mutex::new(writer)));
+            return serve_websocket(WebSocket { reader, writer }, requests);
+        }
+
+        match request.method.as_str() {
+            "POST" => {
+                let body = String::from_utf8_lossy(&request.body).into_owned();
+                match call(&requests, body)? {
+                    Some(response) => write_http_response(&mut writer, "200 OK", Some(&response))?,
+                    None => write_http_response(&mut writer, "204 No Content", None)?,
+                }
+            }
+            // The preflight request of a browser.
+            "OPTIONS" => write_http_response(&mut writer, "204 No Content", None)?,
+            _ => write_http_response(&mut writer, "405 Method Not Allowed", None)?,
+        }
+        if request
+            .header("connection")
+            .is_some_and(|connection| connection.eq_ignore_ascii_case("close"))
+        {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// A connection upgraded to a WebSocket, which is read through the buffer of its HTTP requests,
+/// and written through its sink.
+struct WebSocket {
+    reader: BufReader<TcpStream>,
+    writer: Sink,
+}
+
+impl Read for WebSocket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+/// Each write is a whole frame, e.g. the pong to a ping, which must not be interleaved with the
+/// messages the node sends through the sink.
+impl Write for WebSocket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut stream = self
+            .writer
+            .0
+            .lock()
+            .map_err(|_| invalid_data("Sink is poisoned"))?;
+        stream.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Serve the messages of a WebSocket, each of which is a request, until it is closed. The node
+/// responds through the sink, so the responses and the notifications are sent in order.
+fn serve_websocket(mut socket: WebSocket, requests: mpsc::Sender<Request>) -> io::Result<()> {
+    while let Some(message) = websocket::read_message(&mut socket)? {
+        let body = String::from_utf8_lossy(&message).into_owned();
+        let reply = Reply::WebSocket(socket.writer.clone());
+        requests
+            .send(Request { body, reply })
+            .map_err(|_| stopped())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpStream,
+        thread,
+        time::Duration,
+    };
+
+    use super::Server;
+    use crate::rpc::websocket;
+
+    // Respond to the next request of the server with `response`, once checked it is `expected`.
+    fn respond(server: &Server, expected: &str, response: &str) {
+        let request = server.next_request(Duration::from_secs(5)).unwrap();
+        assert_eq!(request.body(), expected);
+        request.respond(Some(response.to_string()));
+    }
+
+    #[test]
+    fn http_requests() {
+        let server = Server::start("127.0.0.1:0").unwrap();
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        let client = thread::spawn(move || {
+            for body in ["[1]", "[2]"] {
+                write!(
+                    stream,
+                    "POST / HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+            stream
+                .write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+                .unwrap();
+            let mut responses = String::new();
+            stream.read_to_string(&mut responses).unwrap();
+            responses
+        });
+
+        // The requests of a connection are responded to in order.
+        respond(&server, "[1]", "{\"a\":1}");
+        respond(&server, "[2]", "{\"b\":2}");
+        let responses = client.join().unwrap();
+        let statuses: Vec<&str> = responses
+            .split("HTTP/1.1 ")
+            .skip(1)
+            .map(|response| response.lines().next().unwrap())
+            .collect();
+        assert_eq!(statuses, ["200 OK", "200 OK", "405 Method Not Allowed"]);
+        assert!(responses.contains("Content-Length: 7\r\n"));
+        assert!(responses.contains("\r\n\r\n{\"a\":1}"));
+        assert!(responses.contains("\r\n\r\n{\"b\":2}"));
+    }
+
+    #[test]
+    fn websocket_requests() {
+        let server = Server::start("127.0.0.1:0").unwrap();
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        let client = thread::spawn(move || {
+            stream
+                .write_all(
+                    b"GET / HTTP/1.1\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                      Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+                )
+                .unwrap();
+            stream
+                .write_all(&websocket::client_frame(true, 0x1, b"[1]"))
+                .unwrap();
+
+            let mut reader = BufReader::new(stream);
+            let mut head = Vec::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                head.push(line.trim_end().to_string());
+            }
+            let mut frame = [0; 9];
+            reader.read_exact(&mut frame).unwrap();
+            (head, frame)
+        });
+
+        respond(&server, "[1]", "{\"a\":1}");
+        let (head, frame) = client.join().unwrap();
+        assert_eq!(head[0], "HTTP/1.1 101 Switching Protocols");
+        assert!(head.contains(&"Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=".to_string()));
+        assert_eq!(frame, *b"\x81\x07{\"a\":1}");
+    }
+}
@@ -0,0 +1,257 @@
+//! The subscriptions of the WebSocket clients, which are notified as the node imports blocks:
+//!
+//! - `chain_subscribeNewHeads`: the header of every block which joins the best chain, as
+//!   `chain_newHead`.
+//! - `chain_subscribeFinalizedHeads`: the header of the finalized block, whenever it changes, as
+//!   `chain_finalizedHead`.
+//! - `state_subscribeStorage(keys)`: the values of storage `keys`, like `{"balance": account}` or
+//!   `{"claim": claim}`, in the state after the best block, as `state_storage`. The first
+//!   notification has all the values, and the next ones only the values which changed.
+//! - `state_subscribeEvents`: the events of every block which joins the best chain, as
+//!   `state_events`. The events are encoded.
+//!
+//! Each has an unsubscribe method, e.g. `chain_unsubscribeNewHeads(id)`, which only the connection
+//! of the subscription can call.
+
+use super::{events, header, json::Value, server::Sink, Error};
+use crate::{
+    chain::Chain,
+    support::{hashing::to_hex, hashing::Hash, RuntimeApi},
+    types, Runtime,
+};
+
+/// A storage item a client can subscribe to.
+enum StorageKey {
+    /// The free balance of an account.
+    Balance(types::AccountId),
+    /// The owner of a claim.
+    Claim(String),
+}
+
+impl StorageKey {
+    /// Parse a key like `{"balance": account}`.
+    fn parse(key: &Value) -> Option<Self> {
+        match key {
+            Value::Object(members) if members.len() == 1 => {
+                let (name, value) = &members[0];
+                let value = value.as_str()?.to_string();
+                match name.as_str() {
+                    "balance" => Some(StorageKey::Balance(value)),
+                    "claim" => Some(StorageKey::Claim(value)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// The value of the item in `state`.
+    fn value(&self, state: &Runtime) -> Value {
+        match self {
+            StorageKey::Balance(account) => state.balance_of(account).into(),
+            StorageKey::Claim(claim) => state.claim_owner(claim).into(),
+        }
+    }
+}
+
+/// What a subscription is notified of.
+enum Kind {
+    NewHeads,
+    /// The finalized block the client was last notified of.
+    FinalizedHeads {
+        last: Option<Hash>,
+    },
+    /// The keys, as the client gave them, and the values the client was last notified of.
+    Storage {
+        keys: Vec<(Value, StorageKey)>,
+        last: Option<Vec<Value>>,
+    },
+    Events,
+}
+
+impl Kind {
+    /// The name of the notifications of the subscription.
+    fn method(&self) -> &'static str {
+        match self {
+            Kind::NewHeads => "chain_newHead",
+            Kind::FinalizedHeads { .. } => "chain_finalizedHead",
+            Kind::Storage { .. } => "state_storage",
+            Kind::Events => "state_events",
+        }
+    }
+}
+
+struct Subscription {
+    id: u64,
+    sink: Sink,
+    kind: Kind,
+}
+
+/// The subscriptions of the clients of the node.
+#[derive(Default)]
+pub struct Subscriptions {
+    next_id: u64,
+    subscriptions: Vec<Subscription>,
+}
+
+impl Subscriptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Subscribe with `method` and its `params` on behalf of the connection of `sink`, if it is a
+    /// WebSocket. Returns `None` if `method` is not a subscription method.
+    pub fn subscribe(
+        &mut self,
+        method: &str,
+        params: &[Value],
+        sink: Option<&Sink>,
+    ) -> Option<Result<Value, Error>> {
+        let kind = match method {
+            "chain_subscribeNewHeads" => Ok(Kind::NewHeads),
+            "chain_subscribeFinalizedHeads" => Ok(Kind::FinalizedHeads { last: None }),
+            "state_subscribeStorage" => {
+                storage_keys(params).map(|keys| Kind::Storage { keys, last: None })
+            }
+            "state_subscribeEvents" => Ok(Kind::Events),
+            _ => return None,
+        };
+        let Some(sink) = sink else {
+            return Some(Err(Error::server(
+                "Subscriptions are only available over WebSocket",
+            )));
+        };
+
+        Some(kind.map(|kind| {
+            let id = self.next_id;
+            self.next_id += 1;
+            self.subscriptions.push(Subscription {
+                id,
+                sink: sink.clone(),
+                kind,
+            });
+            id.into()
+        }))
+    }
+
+    /// Unsubscribe with `method` and the id of the subscription in its `params`, on behalf of the
+    /// connection of `sink`. Returns `None` if `method` is not an unsubscription method.
+    pub fn unsubscribe(
+        &mut self,
+        method: &str,
+        params: &[Value],
+        sink: Option<&Sink>,
+    ) -> Option<Result<Value, Error>> {
+        let subscribe_method = match method {
+            "chain_unsubscribeNewHeads" => "chain_newHead",
+            "chain_unsubscribeFinalizedHeads" => "chain_finalizedHead",
+            "state_unsubscribeStorage" => "state_storage",
+            "state_unsubscribeEvents" => "state_events",
+            _ => return None,
+        };
+        let Some(id) = params.first().and_then(|id| match id {
+            Value::Number(n) => n.parse::<u64>().ok(),
+            _ => None,
+        }) else {
+            return Some(Err(Error::invalid_params("Invalid subscription id")));
+        };
+
+        let len = self.subscriptions.len();
+        self.subscriptions.retain(|subscription| {
+            subscription.id != id
+                || subscription.kind.method() != subscribe_method
+                || !sink.is_some_and(|sink| sink.same_connection(&subscription.sink))
+        });
+        Some(Ok((self.subscriptions.len() != len).into()))
+    }
+
+    /// Notify the subscriptions of the blocks `enacted` in the best chain of `chain`, and of the
+    /// changes of the state and of the finalized block since they were last notified. A new
+    /// subscription is notified of the current values the first time.
+    ///
+    /// The subscriptions whose connection is closed are dropped.
+    pub fn notify(&mut self, chain: &Chain, enacted: &[Hash]) {
+        self.subscriptions.retain_mut(|subscription| {
+            let results = match &mut subscription.kind {
+                Kind::NewHeads => enacted
+                    .iter()
+                    .filter_map(|hash| chain.block(hash))
+                    .map(|block| header(&block.header))
+                    .collect(),
+                Kind::FinalizedHeads { last } => {
+                    let finalized = chain.finalized_hash();
+                    if *last == Some(finalized) {
+                        vec![]
+                    } else {
+                        *last = Some(finalized);
+                        chain
+                            .block(&finalized)
+                            .map(|block| header(&block.header))
+                            .into_iter()
+                            .collect()
+                    }
+                }
+                Kind::Storage { keys, last } => {
+                    let state = chain.best_state();
+                    let values: Vec<Value> = keys.iter().map(|(_, key)| key.value(state)).collect();
+                    let changes: Vec<Value> = keys
+                        .iter()
+                        .zip(&values)
+                        .enumerate()
+                        .filter(|(i, (_, value))| {
+                            last.as_ref().is_none_or(|last| last[*i] != **value)
+                        })
+                        .map(|(_, ((key, _), value))| {
+                            Value::Array(vec![key.clone(), value.clone()])
+                        })
+                        .collect();
+                    *last = Some(values);
+                    if changes.is_empty() {
+                        vec![]
+                    } else {
+                        vec![Value::object([
+                            ("block", to_hex(&chain.best_hash()).into()),
+                            ("changes", Value::Array(changes)),
+                        ])]
+                    }
+                }
+                Kind::Events => enacted
+                    .iter()
+                    .filter_map(|hash| Some((hash, chain.state(hash)?)))
+                    .map(|(hash, state)| {
+                        Value::object([
+                            ("block", to_hex(hash).into()),
+                            ("events", events(state.system.events())),
+                        ])
+                    })
+                    .collect(),
+            };
+
+            let method = subscription.kind.method();
+            results.into_iter().all(|result| {
+                let params =
+                    Value::object([("subscription", subscription.id.into()), ("result", result)]);
+                let notification = Value::object([
+                    ("jsonrpc", "2.0".into()),
+                    ("method", method.into()),
+                    ("params", params),
+                ]);
+                subscription.sink.send(&notification.to_string()).is_ok()
+            })
+        });
+    }
+}
+
+/// The keys of the first param of `state_subscribeStorage`, with the JSON they were given as.
+fn storage_keys(params: &[Value]) -> Result<Vec<(Value, StorageKey)>, Error> {
+    let Some(Value::Array(keys)) = params.first() else {
+        return Err(Error::invalid_params("Invalid storage keys"));
+    };
+    keys.iter()
+        .map(|key| {
+            StorageKey::parse(key)
+                .map(|storage_key| (key.clone(), storage_key))
+                .ok_or(Error::invalid_params("Invalid storage key"))
+        })
+        .collect()
+}
@@ -0,0 +1,315 @@
+//! The WebSocket protocol (RFC 6455), as far as the RPC server needs it: the handshake which
+//! upgrades an HTTP connection, and the frames of the messages which follow.
+//!
+//! The handshake needs SHA-1 and base64, which are implemented here so the node has no
+//! dependencies beyond the standard library.
+
+use std::io::{self, Read, Write};
+
+/// The GUID the key of a client is concatenated with to accept its handshake.
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The maximum length of a message, once its frames are put together.
+pub const MAX_MESSAGE_LENGTH: usize = 1024 * 1024;
+
+const CONTINUATION: u8 = 0x0;
+const TEXT: u8 = 0x1;
+const BINARY: u8 = 0x2;
+const CLOSE: u8 = 0x8;
+const PING: u8 = 0x9;
+const PONG: u8 = 0xa;
+
+/// Hash `data` with SHA-1.
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    let mut state: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+    for chunk in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in chunk.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (i, w) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a827999),
+                20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*w);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (s, v) in state.iter_mut().zip([a, b, c, d, e]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+
+    let mut output = [0u8; 20];
+    for (i, s) in state.iter().enumerate() {
+        output[i * 4..i * 4 + 4].copy_from_slice(&s.to_be_bytes());
+    }
+    output
+}
+
+/// Encode `data` in base64, with padding.
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut output = String::new();
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+/// The `Sec-WebSocket-Accept` header answering the `Sec-WebSocket-Key` header of a client.
+pub fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{}{HANDSHAKE_GUID}", key.trim()).as_bytes()))
+}
+
+fn invalid_data(error: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+/// Read a frame: whether it is the final frame of a message, its opcode and its payload. The
+/// frames of a client must be masked.
+fn read_frame(stream: &mut impl Read) -> io::Result<(bool, u8, Vec<u8>)> {
+    let mut head = [0; 2];
+    stream.read_exact(&mut head)?;
+    let fin = head[0] & 0x80 != 0;
+    let opcode = head[0] & 0x0f;
+    if head[1] & 0x80 == 0 {
+        return Err(invalid_data("Frame of the client is not masked"));
+    }
+
+    let len = match head[1] & 0x7f {
+        126 => {
+            let mut len = [0; 2];
+            stream.read_exact(&mut len)?;
+            u16::from_be_bytes(len) as u64
+        }
+        127 => {
+            let mut len = [0; 8];
+            stream.read_exact(&mut len)?;
+            u64::from_be_bytes(len)
+        }
+        len => len as u64,
+    };
+    if len > MAX_MESSAGE_LENGTH as u64 {
+        return Err(invalid_data("Message is too long"));
+    }
+
+    let mut mask = [0; 4];
+    stream.read_exact(&mut mask)?;
+    let mut payload = vec![0; len as usize];
+    stream.read_exact(&mut payload)?;
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+    Ok((fin, opcode, payload))
+}
+
+/// Read the next text or binary message of a client, putting its frames together, or `None` once
+/// the client closes the connection. Pings are answered on the way, and pongs are skipped.
+pub fn read_message(stream: &mut (impl Read + Write)) -> io::Result<Option<Vec<u8>>> {
+    let mut data: Option<Vec<u8>> = None;
+    loop {
+        let (fin, opcode, payload) = read_frame(stream)?;
+        match opcode {
+            // Control frames can come between the frames of a message.
+            CLOSE => {
+                write_frame(stream, CLOSE, &[])?;
+                return Ok(None);
+            }
+            PING => {
+                write_frame(stream, PONG, &payload)?;
+                continue;
+            }
+            PONG => continue,
+            TEXT | BINARY if data.is_none() => data = Some(payload),
+            CONTINUATION if data.is_some() => {
+                let message = data.as_mut().expect("a message was started");
+                if message.len() + payload.len() > MAX_MESSAGE_LENGTH {
+                    return Err(invalid_data("Message is too long"));
+                }
+                message.extend_from_slice(&payload);
+            }
+            _ => return Err(invalid_data("Unexpected frame")),
+        }
+        if fin {
+            return Ok(data.take());
+        }
+    }
+}
+
+/// Write a single, unmasked, frame with the `opcode` and the `payload`.
+fn write_frame(stream: &mut (impl Write + ?Sized), opcode: u8, payload: &[u8]) -> io::Result<()> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        len if len < 126 => frame.push(len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    stream.write_all(&frame)
+}
+
+/// Send a text message.
+pub fn write_text(stream: &mut (impl Write + ?Sized), text: &str) -> io::Result<()> {
+    write_frame(stream, TEXT, text.as_bytes())
+}
+
+// A frame of a client, masked with a fixed key.
+#[cfg(test)]
+pub fn client_frame(fin: bool, opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mask = [1, 2, 3, 4];
+    let mut frame = vec![if fin { 0x80 | opcode } else { opcode }];
+    if payload.len() < 126 {
+        frame.push(0x80 | payload.len() as u8);
+    } else {
+        frame.push(0x80 | 126);
+        frame.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+    }
+    frame.extend_from_slice(&mask);
+    frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+    frame
+}
+
+// The text messages of the frames written by the server, e.g. through a sink.
+#[cfg(test)]
+pub fn server_messages(mut frames: &[u8]) -> Vec<String> {
+    let mut messages = Vec::new();
+    while !frames.is_empty() {
+        let (len, start) = match frames[1] {
+            126 => (u16::from_be_bytes([frames[2], frames[3]]) as usize, 4),
+            127 => panic!("message is too long for a test"),
+            len => (len as usize, 2),
+        };
+        messages.push(String::from_utf8(frames[start..start + len].to_vec()).unwrap());
+        frames = &frames[start + len..];
+    }
+    messages
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{self, Read, Write};
+
+    use super::{accept_key, base64, client_frame, read_message, sha1, write_text};
+
+    // A connection reading the frames of the client from `input`, and writing to `output`.
+    struct Connection<'a> {
+        input: &'a [u8],
+        output: Vec<u8>,
+    }
+
+    impl Read for Connection<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Connection<'_> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn handshake() {
+        assert_eq!(
+            sha1(b"abc"),
+            [
+                0xa9, 0x99, 0x3e, 0x36, 0x47, 0x06, 0x81, 0x6a, 0xba, 0x3e, 0x25, 0x71, 0x78, 0x50,
+                0xc2, 0x6c, 0x9c, 0xd0, 0xd8, 0x9d
+            ]
+        );
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        // The example of RFC 6455.
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn messages_are_put_together() {
+        let long = "x".repeat(300);
+        let mut input = client_frame(false, 0x1, b"Hel");
+        input.extend(client_frame(true, 0x9, b"ping"));
+        input.extend(client_frame(true, 0x0, b"lo"));
+        input.extend(client_frame(true, 0x1, long.as_bytes()));
+        input.extend(client_frame(true, 0x8, &[]));
+        let mut connection = Connection {
+            input: &input,
+            output: Vec::new(),
+        };
+
+        // The ping in the middle of the first message is answered.
+        assert_eq!(
+            read_message(&mut connection).unwrap(),
+            Some(b"Hello".to_vec())
+        );
+        assert_eq!(connection.output, [&[0x8a, 4][..], b"ping"].concat());
+        assert_eq!(
+            read_message(&mut connection).unwrap(),
+            Some(long.into_bytes())
+        );
+        assert_eq!(read_message(&mut connection).unwrap(), None);
+    }
+
+    #[test]
+    fn unmasked_frames_are_rejected() {
+        let mut frame = Vec::new();
+        write_text(&mut frame, "hello").unwrap();
+        assert_eq!(frame[..2], [0x81, 5]);
+        let mut connection = Connection {
+            input: &frame,
+            output: Vec::new(),
+        };
+        assert!(read_message(&mut connection).is_err());
+    }
+}
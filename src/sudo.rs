@@ -0,0 +1,212 @@
+use crate::support::{
+    codec::{Decode, Encode},
+    ensure_signed,
+    metadata::{FieldMetadata, PalletInfo},
+    Dispatch, DispatchResult, GetDispatchInfo, GetPallet, Hooks, RawOrigin, DEFAULT_WEIGHT,
+};
+
+pub trait Config:
+    crate::system::Config
+    + Sized
+    + GetPallet<Pallet<Self>>
+    + Dispatch<Caller = Self::RuntimeOrigin, Call = <Self as Config>::RuntimeCall>
+{
+    /// The outer call type of the runtime, which the sudo key can dispatch with `Root` origin.
+    type RuntimeCall: GetDispatchInfo;
+}
+
+/// This is the Sudo Module.
+/// It allows a single account, the sudo key, to dispatch privileged calls with `Root` origin.
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+    /// The account allowed to dispatch calls with `Root` origin, if any.
+    pub key: Option<T::AccountId>,
+}
+
+#[macros::call]
+#[errors("Bad origin", "Caller is not the sudo key")]
+impl<T: Config> Pallet<T> {
+    /// Dispatch `call` with `Root` origin on behalf of the signer of `origin`.
+    /// This function will return an error if the signer is not the sudo key.
+    // The `call` is boxed since `Call` would otherwise be a recursive type.
+    #[allow(clippy::boxed_local)]
+    #[call_index(0)]
+    #[weight(DEFAULT_WEIGHT.saturating_add(call.get_dispatch_info().weight))]
+    pub fn sudo(
+        runtime: &mut T,
+        origin: T::RuntimeOrigin,
+        call: Box<T::RuntimeCall>,
+    ) -> DispatchResult {
+        let caller = ensure_signed(origin)?;
+        let sudo: &Pallet<T> = runtime.pallet();
+        sudo.ensure_key(&caller)?;
+
+        runtime.dispatch(RawOrigin::Root.into(), *call)?;
+        Ok(())
+    }
+
+    /// Set `new` as the sudo key.
+    /// This function will return an error if the signer of `origin` is not the current sudo key.
+    #[call_index(1)]
+    pub fn set_key(&mut self, origin: T::RuntimeOrigin, new: T::AccountId) -> DispatchResult {
+        let caller = ensure_signed(origin)?;
+        self.ensure_key(&caller)?;
+
+        self.key = Some(new);
+        Ok(())
+    }
+}
+
+impl<T: Config> Pallet<T> {
+    /// Create a new instance of the Sudo Module, without any sudo key.
+    pub fn new() -> Self {
+        Self { key: None }
+    }
+
+    /// Check that `who` is the sudo key.
+    fn ensure_key(&self, who: &T::AccountId) -> DispatchResult {
+        match &self.key {
+            Some(key) if key == who => Ok(()),
+            _ => Err("Caller is not the sudo key"),
+        }
+    }
+}
+
+impl<T: Config> Encode for Pallet<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.key.encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for Pallet<T>
+where
+    T::AccountId: Decode,
+{
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        Ok(Self {
+            key: Decode::decode_from(input)?,
+        })
+    }
+}
+
+impl<T: Config> PalletInfo for Pallet<T> {
+    fn storage() -> Vec<FieldMetadata> {
+        vec![FieldMetadata::new::<Option<T::AccountId>>("key")]
+    }
+}
+
+impl<T: Config> Hooks<T, T::BlockNumber> for Pallet<T> {}
+
+#[cfg(test)]
+mod test {
+    use crate::support::{
+        Dispatch, DispatchResultWithPostInfo, GetDispatchInfo, GetPallet, RawOrigin, DEFAULT_WEIGHT,
+    };
+
+    struct TestRuntime {
+        sudo: super::Pallet<TestRuntime>,
+        dispatched: Vec<(RawOrigin<String>, &'static str)>,
+    }
+
+    impl super::Config for TestRuntime {
+        type RuntimeCall = &'static str;
+    }
+
+    impl crate::system::Config for TestRuntime {
+        type AccountId = String;
+        type RuntimeOrigin = RawOrigin<String>;
+        type BlockNumber = u32;
+        type Nonce = u32;
+        type RuntimeEvent = crate::system::Event;
+    }
+
+    impl GetPallet<super::Pallet<Self>> for TestRuntime {
+        fn pallet(&self) -> &super::Pallet<Self> {
+            &self.sudo
+        }
+        fn pallet_mut(&mut self) -> &mut super::Pallet<Self> {
+            &mut self.sudo
+        }
+    }
+
+    impl Dispatch for TestRuntime {
+        type Caller = RawOrigin<String>;
+        type Call = &'static str;
+
+        fn dispatch(
+            &mut self,
+            caller: Self::Caller,
+            call: Self::Call,
+        ) -> DispatchResultWithPostInfo {
+            self.dispatched.push((caller, call));
+            Ok(().into())
+        }
+    }
+
+    fn new_runtime() -> TestRuntime {
+        let mut sudo = super::Pallet::new();
+        sudo.key = Some("alice".to_string());
+
+        TestRuntime {
+            sudo,
+            dispatched: vec![],
+        }
+    }
+
+    #[test]
+    fn sudo_dispatches_as_root() {
+        let mut runtime = new_runtime();
+
+        let res = super::Pallet::sudo(
+            &mut runtime,
+            RawOrigin::Signed("alice".to_string()),
+            Box::new("my_call"),
+        );
+
+        assert_eq!(res, Ok(()));
+        assert_eq!(runtime.dispatched, vec![(RawOrigin::Root, "my_call")]);
+    }
+
+    #[test]
+    fn sudo_return_caller_is_not_the_key() {
+        let mut runtime = new_runtime();
+
+        let res = super::Pallet::sudo(
+            &mut runtime,
+            RawOrigin::Signed("bob".to_string()),
+            Box::new("my_call"),
+        );
+
+        assert_eq!(res, Err("Caller is not the sudo key"));
+        assert!(runtime.dispatched.is_empty());
+    }
+
+    #[test]
+    fn set_key() {
+        let mut runtime = new_runtime();
+
+        let res = runtime
+            .sudo
+            .set_key(RawOrigin::Signed("bob".to_string()), "charlie".to_string());
+        assert_eq!(res, Err("Caller is not the sudo key"));
+
+        let res = runtime
+            .sudo
+            .set_key(RawOrigin::Signed("alice".to_string()), "bob".to_string());
+        assert_eq!(res, Ok(()));
+        assert_eq!(runtime.sudo.key, Some("bob".to_string()));
+    }
+
+    #[test]
+    fn sudo_weighs_the_dispatched_call() {
+        let call = super::Call::<TestRuntime>::sudo {
+            call: Box::new("my_call"),
+        };
+        assert_eq!(call.get_dispatch_info().weight, 2 * DEFAULT_WEIGHT);
+
+        let call = super::Call::<TestRuntime>::set_key {
+            new: "bob".to_string(),
+        };
+        assert_eq!(call.get_dispatch_info().weight, DEFAULT_WEIGHT);
+    }
+}
@@ -1,4 +1,10 @@
+pub mod codec;
+pub mod crypto;
+pub mod hashing;
+pub mod metadata;
+
 /// The most primitive representation of a Blockchain block.
+#[derive(Debug, Clone)]
 pub struct Block<Header, Extrinsic> {
     /// The block header contains metadata about the block.
     pub header: Header,
@@ -6,28 +12,510 @@ pub struct Block<Header, Extrinsic> {
     pub extrinsics: Vec<Extrinsic>,
 }
 
-/// We are using an extremely simplified header which only contains the current block number.
-/// On a real blockchain, you would expect to also find:
-/// - parent block hash
-/// - state root
-/// - extrinsics root
-/// - etc...
+impl<Header: codec::Encode, Extrinsic: codec::Encode> codec::Encode for Block<Header, Extrinsic> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.header.encode_to(dest);
+        self.extrinsics.encode_to(dest);
+    }
+}
+
+impl<Header: codec::Decode, Extrinsic: codec::Decode> codec::Decode for Block<Header, Extrinsic> {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        Ok(Self {
+            header: codec::Decode::decode_from(input)?,
+            extrinsics: codec::Decode::decode_from(input)?,
+        })
+    }
+}
+
+/// We are using a simplified header which only contains the current block number, the hash of the
+/// parent block, the state root, the extrinsics root and the digest.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Header<BlockNumber> {
     pub block_number: BlockNumber,
+    /// The hash of the header of the parent block, which the block is built on.
+    pub parent_hash: hashing::Hash,
+    /// The hash of the encoded state of the runtime once the block is executed, before the hash of
+    /// the block itself is recorded in the state.
+    pub state_root: hashing::Hash,
+    /// The `extrinsics_root` of the extrinsics of the block.
+    pub extrinsics_root: hashing::Hash,
+    /// The digest of the block, whose last item is the seal of the consensus engine once the
+    /// block is sealed.
+    pub digest: Digest,
+}
+
+impl<BlockNumber: codec::Encode> Header<BlockNumber> {
+    /// The hash of the header, which identifies the block.
+    pub fn hash(&self) -> hashing::Hash {
+        hashing::hash(&codec::Encode::encode(self))
+    }
+
+    /// The hash of the header without its seal, which is what the consensus engine seals. It is
+    /// the hash of the header once its seal is removed with `take_seal`.
+    pub fn pre_hash(&self) -> hashing::Hash {
+        use codec::Encode;
+
+        let mut data = Vec::new();
+        self.block_number.encode_to(&mut data);
+        self.parent_hash.encode_to(&mut data);
+        self.state_root.encode_to(&mut data);
+        self.extrinsics_root.encode_to(&mut data);
+        self.digest.without_seal().encode_to(&mut data);
+        hashing::hash(&data)
+    }
+
+    /// Remove the seal of the consensus `engine` from the digest, and return it.
+    /// This function will return `None`, and leave the header as is, if the last item of the
+    /// digest is not a seal of `engine`.
+    pub fn take_seal(&mut self, engine: ConsensusEngineId) -> Option<Vec<u8>> {
+        self.digest.seal(engine)?;
+        match self.digest.logs.pop() {
+            Some(DigestItem::Seal(_, seal)) => Some(seal),
+            _ => None,
+        }
+    }
+}
+
+impl<BlockNumber: codec::Encode> codec::Encode for Header<BlockNumber> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.block_number.encode_to(dest);
+        self.parent_hash.encode_to(dest);
+        self.state_root.encode_to(dest);
+        self.extrinsics_root.encode_to(dest);
+        self.digest.encode_to(dest);
+    }
+}
+
+impl<BlockNumber: codec::Decode> codec::Decode for Header<BlockNumber> {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        Ok(Self {
+            block_number: codec::Decode::decode_from(input)?,
+            parent_hash: codec::Decode::decode_from(input)?,
+            state_root: codec::Decode::decode_from(input)?,
+            extrinsics_root: codec::Decode::decode_from(input)?,
+            digest: codec::Decode::decode_from(input)?,
+        })
+    }
+}
+
+/// The id of a consensus engine, which tags the digest items meant for it.
+pub type ConsensusEngineId = [u8; 4];
+
+/// An item of the digest of a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DigestItem {
+    /// Data the block author gives the runtime before the block is executed, e.g. the slot the
+    /// block is authored in.
+    PreRuntime(ConsensusEngineId, Vec<u8>),
+    /// A message from the runtime to the consensus engine, e.g. a change of the authority set.
+    Consensus(ConsensusEngineId, Vec<u8>),
+    /// The seal of the consensus engine, which is added once the block is built, as the last item
+    /// of the digest.
+    Seal(ConsensusEngineId, Vec<u8>),
+    /// The runtime environment changed, so clients must reload it before executing the next
+    /// blocks.
+    RuntimeEnvironmentUpdated,
+}
+
+impl codec::Encode for DigestItem {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            DigestItem::Consensus(id, data) => {
+                dest.push(4);
+                id.encode_to(dest);
+                data.encode_to(dest);
+            }
+            DigestItem::Seal(id, data) => {
+                dest.push(5);
+                id.encode_to(dest);
+                data.encode_to(dest);
+            }
+            DigestItem::PreRuntime(id, data) => {
+                dest.push(6);
+                id.encode_to(dest);
+                data.encode_to(dest);
+            }
+            DigestItem::RuntimeEnvironmentUpdated => dest.push(8),
+        }
+    }
+}
+
+impl codec::Decode for DigestItem {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        use codec::Decode;
+
+        match u8::decode_from(input)? {
+            4 => Ok(DigestItem::Consensus(
+                Decode::decode_from(input)?,
+                Decode::decode_from(input)?,
+            )),
+            5 => Ok(DigestItem::Seal(
+                Decode::decode_from(input)?,
+                Decode::decode_from(input)?,
+            )),
+            6 => Ok(DigestItem::PreRuntime(
+                Decode::decode_from(input)?,
+                Decode::decode_from(input)?,
+            )),
+            8 => Ok(DigestItem::RuntimeEnvironmentUpdated),
+            _ => Err("invalid digest item"),
+        }
+    }
+}
+
+/// The digest of a block: the typed log items of the block author, the runtime and the consensus
+/// engine.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Digest {
+    pub logs: Vec<DigestItem>,
+}
+
+impl Digest {
+    /// Add `item` at the end of the digest.
+    pub fn push(&mut self, item: DigestItem) {
+        self.logs.push(item);
+    }
+
+    /// The data of the pre-runtime item of the consensus `engine`, if any.
+    pub fn pre_runtime(&self, engine: ConsensusEngineId) -> Option<&[u8]> {
+        self.logs.iter().find_map(|item| match item {
+            DigestItem::PreRuntime(id, data) if *id == engine => Some(data.as_slice()),
+            _ => None,
+        })
+    }
+
+    /// The data of the consensus item of the consensus `engine`, if any.
+    pub fn consensus(&self, engine: ConsensusEngineId) -> Option<&[u8]> {
+        self.logs.iter().find_map(|item| match item {
+            DigestItem::Consensus(id, data) if *id == engine => Some(data.as_slice()),
+            _ => None,
+        })
+    }
+
+    /// The data of the seal of the consensus `engine`, if the digest ends with one.
+    pub fn seal(&self, engine: ConsensusEngineId) -> Option<&[u8]> {
+        match self.logs.last() {
+            Some(DigestItem::Seal(id, data)) if *id == engine => Some(data),
+            _ => None,
+        }
+    }
+
+    /// The items of the digest, without the seal at the end, if any.
+    pub fn without_seal(&self) -> &[DigestItem] {
+        match self.logs.split_last() {
+            Some((DigestItem::Seal(..), logs)) => logs,
+            _ => &self.logs,
+        }
+    }
+}
+
+impl codec::Encode for Digest {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.logs.encode_to(dest);
+    }
+}
+
+impl codec::Decode for Digest {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        Ok(Self {
+            logs: codec::Decode::decode_from(input)?,
+        })
+    }
+}
+
+/// The hash committing a header to the extrinsics of its block.
+pub fn extrinsics_root<Extrinsic: codec::Encode>(extrinsics: &[Extrinsic]) -> hashing::Hash {
+    hashing::hash(&codec::Encode::encode(extrinsics))
 }
 
 /// This is an "extrinsic": literally an external message from outside of the blockchain.
 /// This simplified version of an extrinsic tells us who is making the call, and which call they are
 /// making.
-pub struct Extrinsic<Caller, Call> {
-    pub caller: Caller,
+#[derive(Debug, Clone)]
+pub struct Extrinsic<AccountId, Call, Nonce, Signature> {
+    /// The signer of the extrinsic, or `None` for an unsigned extrinsic like an inherent.
+    pub signer: Option<Signer<AccountId, Nonce, Signature>>,
     pub call: Call,
 }
 
+/// The signer of a signed extrinsic, with the nonce and the signature of the extrinsic.
+#[derive(Debug, Clone)]
+pub struct Signer<AccountId, Nonce, Signature> {
+    /// The account which signed the extrinsic.
+    pub who: AccountId,
+    /// The nonce of the account for this extrinsic, which must be its next one.
+    pub nonce: Nonce,
+    /// The signature of the `signing_payload` of the extrinsic by `who`.
+    pub signature: Signature,
+}
+
+impl<AccountId, Call: codec::Encode, Nonce: codec::Encode, Signature>
+    Extrinsic<AccountId, Call, Nonce, Signature>
+{
+    /// An unsigned extrinsic, like an inherent.
+    pub fn new_unsigned(call: Call) -> Self {
+        Self { signer: None, call }
+    }
+
+    /// An extrinsic signed by `who`, whose `signature` is the signature of the `signing_payload`.
+    pub fn new_signed(call: Call, who: AccountId, nonce: Nonce, signature: Signature) -> Self {
+        let signer = Signer {
+            who,
+            nonce,
+            signature,
+        };
+        Self {
+            signer: Some(signer),
+            call,
+        }
+    }
+
+    /// What the signer of an extrinsic signs: the call and the nonce.
+    pub fn signing_payload(call: &Call, nonce: &Nonce) -> Vec<u8> {
+        let mut payload = call.encode();
+        nonce.encode_to(&mut payload);
+        payload
+    }
+}
+
+impl<AccountId, Call, Nonce, Signature> Extrinsic<AccountId, Call, Nonce, Signature>
+where
+    Call: codec::Encode,
+    Nonce: codec::Encode,
+    Signature: Verify<Signer = AccountId>,
+{
+    /// Check that the signature of a signed extrinsic is valid. Unsigned extrinsics have nothing
+    /// to check.
+    pub fn check_signature(&self) -> DispatchResult {
+        match &self.signer {
+            Some(signer) => {
+                let payload = Self::signing_payload(&self.call, &signer.nonce);
+                if !signer.signature.verify(&payload, &signer.who) {
+                    return Err("invalid transaction signature");
+                }
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// The encoded length of an extrinsic is used to charge a fee for the space it takes in a block.
+impl<AccountId, Call, Nonce, Signature> codec::Encode
+    for Extrinsic<AccountId, Call, Nonce, Signature>
+where
+    AccountId: codec::Encode,
+    Call: codec::Encode,
+    Nonce: codec::Encode,
+    Signature: codec::Encode,
+{
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match &self.signer {
+            Some(signer) => {
+                dest.push(1);
+                signer.who.encode_to(dest);
+                signer.nonce.encode_to(dest);
+                signer.signature.encode_to(dest);
+            }
+            None => dest.push(0),
+        }
+        self.call.encode_to(dest);
+    }
+}
+
+impl<AccountId, Call, Nonce, Signature> codec::Decode
+    for Extrinsic<AccountId, Call, Nonce, Signature>
+where
+    AccountId: codec::Decode,
+    Call: codec::Decode,
+    Nonce: codec::Decode,
+    Signature: codec::Decode,
+{
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        use codec::Decode;
+
+        let signer = match u8::decode_from(input)? {
+            0 => None,
+            1 => Some(Signer {
+                who: Decode::decode_from(input)?,
+                nonce: Decode::decode_from(input)?,
+                signature: Decode::decode_from(input)?,
+            }),
+            _ => return Err("invalid extrinsic signer"),
+        };
+        Ok(Self {
+            signer,
+            call: Decode::decode_from(input)?,
+        })
+    }
+}
+
+/// Types which can verify that a message was signed by an account.
+pub trait Verify {
+    /// The type used to identify the signer of the message.
+    type Signer;
+
+    /// Whether `self` is a valid signature of `message` by `signer`.
+    fn verify(&self, message: &[u8], signer: &Self::Signer) -> bool;
+}
+
+/// Accounts identified by a `String` are the hexadecimal `crypto::Public` key of their signer.
+impl Verify for crypto::Signature {
+    type Signer = String;
+
+    fn verify(&self, message: &[u8], signer: &String) -> bool {
+        crypto::Public::from_hex(signer)
+            .is_some_and(|public| crypto::Signature::verify(self, message, &public))
+    }
+}
+
+/// The origin of a call, i.e. on whose behalf it is being dispatched.
+/// Most calls come from a signed extrinsic, but privileged calls (e.g. those dispatched by the
+/// `sudo` pallet) are dispatched with the `Root` origin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RawOrigin<AccountId> {
+    /// The system itself ordained this dispatch to happen: this is the highest privilege level.
+    Root,
+    /// It is signed by some account.
+    Signed(AccountId),
+    /// It is signed by nobody, e.g. an unsigned extrinsic.
+    None,
+}
+
+/// A `RawOrigin` can be used directly as the `RuntimeOrigin` of a runtime, which is handy for
+/// testing pallets in isolation.
+impl<AccountId> From<RawOrigin<AccountId>> for Result<RawOrigin<AccountId>, RawOrigin<AccountId>> {
+    fn from(origin: RawOrigin<AccountId>) -> Self {
+        Ok(origin)
+    }
+}
+
+/// Types which can be derived deterministically from a hash. This is used for accounts which are
+/// not controlled by a single signer, like the account of a multisig.
+pub trait FromEntropy {
+    /// Derive a value from `entropy`.
+    fn from_entropy(entropy: &hashing::Hash) -> Self;
+}
+
+/// `String` account ids are derived as the hex representation of the hash.
+impl FromEntropy for String {
+    fn from_entropy(entropy: &hashing::Hash) -> Self {
+        hashing::to_hex(entropy)
+    }
+}
+
 /// The Result type for our runtime. When everything completes successfully, we return `Ok(())`,
 /// otherwise return a static error message.
 pub type DispatchResult = Result<(), &'static str>;
 
+/// The weight of a call: a measure of the computation it takes to execute it.
+pub type Weight = u64;
+
+/// The weight of a call which does not declare one with `#[weight(...)]`.
+pub const DEFAULT_WEIGHT: Weight = 10_000;
+
+/// Information about a call which is known before dispatching it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DispatchInfo {
+    /// The weight of the call, which is the most it can take to execute.
+    pub weight: Weight,
+}
+
+/// Types which know their `DispatchInfo`, like the `Call` enum of each pallet and `RuntimeCall`.
+pub trait GetDispatchInfo {
+    /// Get the `DispatchInfo` of the call.
+    fn get_dispatch_info(&self) -> DispatchInfo;
+}
+
+/// The pallet tests use plain strings as the calls of their mock runtimes.
+#[cfg(test)]
+impl GetDispatchInfo for &'static str {
+    fn get_dispatch_info(&self) -> DispatchInfo {
+        DispatchInfo {
+            weight: DEFAULT_WEIGHT,
+        }
+    }
+}
+
+/// Information about a call which is only known after dispatching it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PostDispatchInfo {
+    /// The weight the call actually took, if it is known to be less than its `DispatchInfo`.
+    pub actual_weight: Option<Weight>,
+}
+
+impl PostDispatchInfo {
+    /// The weight the call actually took, which can not be more than the weight in `info`.
+    pub fn calc_actual_weight(&self, info: &DispatchInfo) -> Weight {
+        match self.actual_weight {
+            Some(actual_weight) => actual_weight.min(info.weight),
+            None => info.weight,
+        }
+    }
+}
+
+/// Calls returning a `DispatchResult` took their full weight.
+impl From<()> for PostDispatchInfo {
+    fn from(_: ()) -> Self {
+        Self::default()
+    }
+}
+
+/// The result of a call which can report the weight it actually took, so the unused weight can be
+/// refunded.
+pub type DispatchResultWithPostInfo = Result<PostDispatchInfo, &'static str>;
+
+/// The result of applying an extrinsic. The outer error means that the extrinsic is invalid, e.g.
+/// its nonce is wrong, and it can not be included in a block. Otherwise, the extrinsic was
+/// dispatched with the inner result.
+pub type ApplyExtrinsicResult = Result<DispatchResultWithPostInfo, &'static str>;
+
+/// What applying an extrinsic would do, without doing it: see `Runtime::dry_run`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DryRunResult<Event> {
+    /// The result of applying the extrinsic.
+    pub result: ApplyExtrinsicResult,
+    /// The events deposited while applying the extrinsic, including its outcome.
+    pub events: Vec<EventRecord<Event>>,
+    /// The weight the call would take, which is its full weight unless it succeeds with less.
+    pub weight: Weight,
+}
+
+/// Ensure that the origin `o` represents a signed extrinsic, and return the account which signed
+/// it. Otherwise return an error.
+pub fn ensure_signed<OuterOrigin, AccountId>(o: OuterOrigin) -> Result<AccountId, &'static str>
+where
+    OuterOrigin: Into<Result<RawOrigin<AccountId>, OuterOrigin>>,
+{
+    match o.into() {
+        Ok(RawOrigin::Signed(who)) => Ok(who),
+        _ => Err("Bad origin"),
+    }
+}
+
+/// Ensure that the origin `o` represents the root. Otherwise return an error.
+pub fn ensure_root<OuterOrigin, AccountId>(o: OuterOrigin) -> DispatchResult
+where
+    OuterOrigin: Into<Result<RawOrigin<AccountId>, OuterOrigin>>,
+{
+    match o.into() {
+        Ok(RawOrigin::Root) => Ok(()),
+        _ => Err("Bad origin"),
+    }
+}
+
+/// Ensure that the origin `o` represents an unsigned extrinsic. Otherwise return an error.
+pub fn ensure_none<OuterOrigin, AccountId>(o: OuterOrigin) -> DispatchResult
+where
+    OuterOrigin: Into<Result<RawOrigin<AccountId>, OuterOrigin>>,
+{
+    match o.into() {
+        Ok(RawOrigin::None) => Ok(()),
+        _ => Err("Bad origin"),
+    }
+}
+
 /// A trait which allows us to dispatch an incoming extrinsic to the appropriate state transition
 /// function call.
 pub trait Dispatch {
@@ -38,5 +526,263 @@ pub trait Dispatch {
 
     /// This function takes a `caller` and the `call` they want to make, and returns a `Result`
     /// based on the outcome of that function call.
-    fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> DispatchResult;
+    fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> DispatchResultWithPostInfo;
+}
+
+/// The pallet level counterpart of `Dispatch`, implemented by `#[macros::call]` for every pallet.
+///
+/// Pallets only own their own state, so a pallet call is dispatched with access to the whole
+/// `Runtime`. This allows calls which need more than their own pallet, for example `sudo`, which
+/// dispatches another call of the runtime.
+pub trait PalletDispatch<Runtime> {
+    /// The type used to identify the caller of the function.
+    type Caller;
+    /// The state transition function call the caller is trying to access.
+    type Call;
+
+    /// Dispatch the `call` of the pallet on behalf of `caller`, using the `runtime` the pallet is
+    /// part of.
+    fn dispatch(
+        runtime: &mut Runtime,
+        caller: Self::Caller,
+        call: Self::Call,
+    ) -> DispatchResultWithPostInfo;
+}
+
+/// Checks and side effects applied around the dispatch of every signed extrinsic, like charging a
+/// fee. `#[macros::runtime]` applies the one the runtime configures as `types::SignedExtra`.
+pub trait SignedExtension<Runtime> {
+    /// The type used to identify the signer of the extrinsic.
+    type AccountId;
+    /// Data passed from `pre_dispatch` to `post_dispatch`, like the fee which was paid.
+    type Pre;
+
+    /// Check that an extrinsic signed by `who`, whose call has `info` and which has an encoded
+    /// length of `len`, could be dispatched in the current state, e.g. before accepting it in the
+    /// transaction pool. Returns the priority of the extrinsic.
+    fn validate(
+        runtime: &Runtime,
+        who: &Self::AccountId,
+        info: &DispatchInfo,
+        len: usize,
+    ) -> Result<TransactionPriority, &'static str>;
+
+    /// Called before dispatching an extrinsic signed by `who`, whose call has `info` and which has
+    /// an encoded length of `len`. The extrinsic is not dispatched if this returns an error.
+    fn pre_dispatch(
+        runtime: &mut Runtime,
+        who: &Self::AccountId,
+        info: &DispatchInfo,
+        len: usize,
+    ) -> Result<Self::Pre, &'static str>;
+
+    /// Called after dispatching the extrinsic, with what the call reported in `post_info`. The
+    /// nonce was incremented and the call dispatched already, so this cannot fail.
+    fn post_dispatch(
+        runtime: &mut Runtime,
+        pre: Self::Pre,
+        info: &DispatchInfo,
+        post_info: &PostDispatchInfo,
+        len: usize,
+    );
+}
+
+/// The priority of a transaction in the transaction pool: the higher, the sooner it is included in
+/// a block.
+pub type TransactionPriority = u64;
+
+/// An opaque tag of a transaction, e.g. the encoded signer and nonce of the transaction.
+pub type TransactionTag = Vec<u8>;
+
+/// The information about a transaction which is valid in the current state, used by the
+/// transaction pool to order transactions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidTransaction {
+    /// The priority of the transaction.
+    pub priority: TransactionPriority,
+    /// The tags which must be provided by other transactions before this one can be included, e.g.
+    /// the previous nonce of the signer.
+    pub requires: Vec<TransactionTag>,
+    /// The tags this transaction provides once it is included.
+    pub provides: Vec<TransactionTag>,
+}
+
+/// Validates transactions against the current state, before they are included in a block.
+/// `#[macros::runtime]` implements this for the `Runtime`.
+pub trait ValidateTransaction {
+    /// The type of the extrinsics.
+    type Extrinsic;
+
+    /// Check that `extrinsic` can be included in a block built on the current state, now or once
+    /// the transactions it requires are included.
+    fn validate_transaction(
+        &self,
+        extrinsic: &Self::Extrinsic,
+    ) -> Result<ValidTransaction, &'static str>;
+}
+
+/// The weight and the fee of a transaction, as `RuntimeApi::query_fee_info` predicts them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeInfo<Balance> {
+    /// The weight of the call of the transaction.
+    pub weight: Weight,
+    /// The fee charged before the call is dispatched, which is partially refunded if the call
+    /// takes less than its weight.
+    pub partial_fee: Balance,
+}
+
+/// The read-only calls clients can make to the runtime, e.g. through the RPC server. They only
+/// read the state they are called on, so they can be answered with the state after any block
+/// the node keeps, not only the best one.
+pub trait RuntimeApi {
+    type AccountId;
+    type Balance;
+    type Nonce;
+    /// The content of a claim, which may be unsized, like `str`.
+    type Claim: ?Sized;
+    type Extrinsic;
+
+    /// The nonce the next transaction of `who` must have.
+    fn account_nonce(&self, who: &Self::AccountId) -> Self::Nonce;
+
+    /// The free balance of `who`.
+    fn balance_of(&self, who: &Self::AccountId) -> Self::Balance;
+
+    /// The owner of `claim`, if it is claimed.
+    fn claim_owner(&self, claim: &Self::Claim) -> Option<Self::AccountId>;
+
+    /// The weight and the fee of `extrinsic`, if it was included in a block on this state.
+    fn query_fee_info(
+        &self,
+        extrinsic: &Self::Extrinsic,
+    ) -> Result<FeeInfo<Self::Balance>, &'static str>;
+}
+
+/// Gives access to a pallet instance owned by the runtime. `#[macros::runtime]` implements this
+/// for every pallet included in the `Runtime` struct.
+pub trait GetPallet<Pallet> {
+    /// Get a reference to the pallet.
+    fn pallet(&self) -> &Pallet;
+    /// Get a mutable reference to the pallet.
+    fn pallet_mut(&mut self) -> &mut Pallet;
+}
+
+/// A filter on calls, for example the kind of a proxy, which restricts the calls the proxy can make
+/// on behalf of another account.
+pub trait InstanceFilter<Call> {
+    /// Whether `call` is allowed by the filter.
+    fn filter(&self, call: &Call) -> bool;
+}
+
+/// Implemented by pallets which expect the block author to include some data in the block, like the
+/// timestamp, in the form of an "inherent": an unsigned extrinsic at the start of the block.
+///
+/// `#[macros::runtime]` checks the inherents of every block for the pallets marked `#[inherent]`.
+pub trait ProvideInherent {
+    /// The call type of the pallet.
+    type Call;
+
+    /// Whether `call` is an inherent of the pallet.
+    fn is_inherent(call: &Self::Call) -> bool;
+
+    /// Whether every block must include an inherent of the pallet.
+    fn is_inherent_required() -> bool {
+        false
+    }
+}
+
+/// Implemented by pallets which deposit events, like a transfer of the balances pallet, so clients
+/// can follow what happens on chain. A pallet keeps its events until the runtime takes them.
+///
+/// `#[macros::runtime]` takes the events of the pallets marked `#[events]` once every extrinsic is
+/// applied, and deposits them in the system pallet as part of its `RuntimeEvent`.
+pub trait PalletEvents {
+    /// The event type of the pallet.
+    type Event;
+
+    /// Take the events deposited by the pallet since they were last taken.
+    fn take_events(&mut self) -> Vec<Self::Event>;
+}
+
+/// The per-block logic of a pallet, like expiring some of its storage or distributing rewards.
+/// Every pallet of the runtime implements it, and has nothing to do by default.
+///
+/// `#[macros::runtime]` calls the hooks of every pallet in declaration order, starting with
+/// system: `on_initialize` at the start of a block, before its extrinsics, then `on_idle` and
+/// `on_finalize` once its extrinsics are applied. The events the pallets marked `#[events]`
+/// deposit in their hooks are recorded in the `Initialization` and `Finalization` phases.
+pub trait Hooks<Runtime, BlockNumber> {
+    /// Run at the start of block `n`, using the `runtime` the pallet is part of. Returns the
+    /// weight it consumed, which counts towards the weight of the block.
+    fn on_initialize(_runtime: &mut Runtime, _n: BlockNumber) -> Weight {
+        0
+    }
+
+    /// Run at the end of block `n`, after `on_idle`.
+    fn on_finalize(_runtime: &mut Runtime, _n: BlockNumber) {}
+
+    /// Run at the end of a block, before `on_finalize`, with the weight the block has left, e.g.
+    /// for work which can wait for a block with room for it. Returns the weight it consumed,
+    /// which should not exceed `remaining_weight`.
+    fn on_idle(_runtime: &mut Runtime, _remaining_weight: Weight) -> Weight {
+        0
+    }
+
+    /// Check the configuration of the pallet, e.g. that its constants make sense, and panic if it
+    /// does not. It is run once, before the runtime is used.
+    fn integrity_test() {}
+}
+
+/// The part of the execution of a block an event was deposited in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// While applying the extrinsic with this index in the block.
+    ApplyExtrinsic(u32),
+    /// While running the `on_idle` and `on_finalize` hooks, after the extrinsics.
+    Finalization,
+    /// While running the `on_initialize` hooks, before the extrinsics.
+    Initialization,
+}
+
+impl codec::Encode for Phase {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            Phase::ApplyExtrinsic(index) => {
+                dest.push(0);
+                index.encode_to(dest);
+            }
+            Phase::Finalization => dest.push(1),
+            Phase::Initialization => dest.push(2),
+        }
+    }
+}
+
+/// An event deposited while executing a block, with the phase it was deposited in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventRecord<Event> {
+    pub phase: Phase,
+    pub event: Event,
+}
+
+impl<Event: codec::Encode> codec::Encode for EventRecord<Event> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.phase.encode_to(dest);
+        self.event.encode_to(dest);
+    }
+}
+
+/// A conversion between two types, which the runtime configures for its pallets, e.g. from a block
+/// number into a balance.
+pub trait Convert<A, B> {
+    /// Convert `a` into `B`.
+    fn convert(a: A) -> B;
+}
+
+/// A `Convert` implementation for types which implement `Into`.
+pub struct ConvertInto;
+
+impl<A: Into<B>, B> Convert<A, B> for ConvertInto {
+    fn convert(a: A) -> B {
+        a.into()
+    }
 }
@@ -0,0 +1,291 @@
+//! A very simple binary encoding for the types used in the runtime, inspired by SCALE.
+//!
+//! Integers are encoded as little endian, collections are prefixed with their length as a `u32`,
+//! and enum variants are prefixed with their index as a `u8`. `#[macros::call]` and
+//! `#[macros::runtime]` implement `Encode` and `Decode` for `Call` and `RuntimeCall`, and the
+//! runtime macro also for the state of the `Runtime`, which is the state of its pallets.
+
+use std::collections::BTreeMap;
+
+/// A type which can be encoded into bytes.
+pub trait Encode {
+    /// Append the encoding of `self` to `dest`.
+    fn encode_to(&self, dest: &mut Vec<u8>);
+
+    /// Encode `self` into a new vector of bytes.
+    fn encode(&self) -> Vec<u8> {
+        let mut dest = Vec::new();
+        self.encode_to(&mut dest);
+        dest
+    }
+}
+
+macro_rules! impl_encode_for_int {
+    ($($t:ty),*) => {
+        $(
+            impl Encode for $t {
+                fn encode_to(&self, dest: &mut Vec<u8>) {
+                    dest.extend_from_slice(&self.to_le_bytes());
+                }
+            }
+        )*
+    };
+}
+
+impl_encode_for_int!(u8, u16, u32, u64, u128);
+
+impl Encode for bool {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        dest.push(*self as u8);
+    }
+}
+
+impl Encode for str {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.as_bytes().encode_to(dest);
+    }
+}
+
+impl Encode for String {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.as_str().encode_to(dest);
+    }
+}
+
+impl<T: Encode> Encode for [T] {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        (self.len() as u32).encode_to(dest);
+        for item in self {
+            item.encode_to(dest);
+        }
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.as_slice().encode_to(dest);
+    }
+}
+
+/// Fixed size arrays, like hashes, are encoded without a length prefix.
+impl<T: Encode, const N: usize> Encode for [T; N] {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        for item in self {
+            item.encode_to(dest);
+        }
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            None => dest.push(0),
+            Some(value) => {
+                dest.push(1);
+                value.encode_to(dest);
+            }
+        }
+    }
+}
+
+impl<T: Encode + ?Sized> Encode for &T {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        (**self).encode_to(dest);
+    }
+}
+
+impl<T: Encode + ?Sized> Encode for Box<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        (**self).encode_to(dest);
+    }
+}
+
+/// Maps are encoded as the list of their entries, ordered by key.
+impl<K: Encode, V: Encode> Encode for BTreeMap<K, V> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        (self.len() as u32).encode_to(dest);
+        for (key, value) in self {
+            key.encode_to(dest);
+            value.encode_to(dest);
+        }
+    }
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.0.encode_to(dest);
+        self.1.encode_to(dest);
+    }
+}
+
+/// A type which can be decoded from the bytes of its `Encode` implementation.
+pub trait Decode: Sized {
+    /// Decode a value from the start of `input`, and advance `input` past it.
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str>;
+
+    /// Decode a value from `input`, which must contain nothing else.
+    fn decode(mut input: &[u8]) -> Result<Self, &'static str> {
+        let value = Self::decode_from(&mut input)?;
+        if !input.is_empty() {
+            return Err("input has bytes left after decoding");
+        }
+        Ok(value)
+    }
+}
+
+/// Take the first `len` bytes of `input`.
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], &'static str> {
+    if input.len() < len {
+        return Err("input is too short to decode");
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Ok(bytes)
+}
+
+macro_rules! impl_decode_for_int {
+    ($($t:ty),*) => {
+        $(
+            impl Decode for $t {
+                fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+                    let bytes = take(input, std::mem::size_of::<$t>())?;
+                    Ok(<$t>::from_le_bytes(bytes.try_into().expect("bytes have the size of the int")))
+                }
+            }
+        )*
+    };
+}
+
+impl_decode_for_int!(u8, u16, u32, u64, u128);
+
+impl Decode for bool {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        match u8::decode_from(input)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err("invalid bool"),
+        }
+    }
+}
+
+impl Decode for String {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        let len = u32::decode_from(input)? as usize;
+        let bytes = take(input, len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| "invalid utf-8 string")
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        let len = u32::decode_from(input)? as usize;
+        // The length is not trusted to reserve memory, since every item takes at least a byte
+        // anyway.
+        let mut items = Vec::with_capacity(len.min(input.len()));
+        for _ in 0..len {
+            items.push(T::decode_from(input)?);
+        }
+        Ok(items)
+    }
+}
+
+impl<T: Decode, const N: usize> Decode for [T; N] {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        let mut items = Vec::with_capacity(N);
+        for _ in 0..N {
+            items.push(T::decode_from(input)?);
+        }
+        items
+            .try_into()
+            .map_err(|_| "array has the wrong number of items")
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        match u8::decode_from(input)? {
+            0 => Ok(None),
+            1 => T::decode_from(input).map(Some),
+            _ => Err("invalid option"),
+        }
+    }
+}
+
+impl<T: Decode> Decode for Box<T> {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        T::decode_from(input).map(Box::new)
+    }
+}
+
+impl<K: Decode + Ord, V: Decode> Decode for BTreeMap<K, V> {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        let entries = Vec::<(K, V)>::decode_from(input)?;
+        let len = entries.len();
+        let map = entries.into_iter().collect::<BTreeMap<_, _>>();
+        if map.len() != len {
+            return Err("map has duplicate keys");
+        }
+        Ok(map)
+    }
+}
+
+impl<A: Decode, B: Decode> Decode for (A, B) {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        Ok((A::decode_from(input)?, B::decode_from(input)?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Decode, Encode};
+
+    #[test]
+    fn encode_integers() {
+        assert_eq!(1u8.encode(), vec![1]);
+        assert_eq!(258u16.encode(), vec![2, 1]);
+        assert_eq!(1u128.encode().len(), 16);
+    }
+
+    #[test]
+    fn encode_collections() {
+        assert_eq!("ab".encode(), vec![2, 0, 0, 0, b'a', b'b']);
+        assert_eq!(vec![1u8, 2].encode(), vec![2, 0, 0, 0, 1, 2]);
+        assert_eq!([1u8, 2].encode(), vec![1, 2]);
+        assert_eq!(Some(1u8).encode(), vec![1, 1]);
+        assert_eq!(None::<u8>.encode(), vec![0]);
+    }
+
+    #[test]
+    fn decode_encoded() {
+        assert_eq!(u128::decode(&42u128.encode()), Ok(42));
+        assert_eq!(String::decode(&"ab".encode()), Ok("ab".to_string()));
+        assert_eq!(Vec::<u16>::decode(&vec![1u16, 2].encode()), Ok(vec![1, 2]));
+        assert_eq!(<[u8; 2]>::decode(&[1u8, 2].encode()), Ok([1, 2]));
+        assert_eq!(Option::<bool>::decode(&Some(true).encode()), Ok(Some(true)));
+        assert_eq!(
+            <(u8, Box<u8>)>::decode(&(1u8, Box::new(2u8)).encode()),
+            Ok((1, Box::new(2)))
+        );
+        let map = std::collections::BTreeMap::from([(2u8, true), (1, false)]);
+        assert_eq!(map.encode(), vec![2, 0, 0, 0, 1, 0, 2, 1]);
+        assert_eq!(std::collections::BTreeMap::decode(&map.encode()), Ok(map));
+    }
+
+    #[test]
+    fn decode_rejects_invalid_input() {
+        assert_eq!(u32::decode(&[1, 2]), Err("input is too short to decode"));
+        assert_eq!(
+            u8::decode(&[1, 2]),
+            Err("input has bytes left after decoding")
+        );
+        assert_eq!(bool::decode(&[2]), Err("invalid bool"));
+        assert_eq!(
+            std::collections::BTreeMap::<u8, u8>::decode(&[2, 0, 0, 0, 1, 1, 1, 2]),
+            Err("map has duplicate keys")
+        );
+        assert_eq!(
+            Vec::<u8>::decode(&u32::MAX.encode()),
+            Err("input is too short to decode")
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! Signatures for the runtime: a Schnorr signature scheme over the group of quadratic residues
+//! modulo a 256 bits safe prime, implemented here with `num::BigUint`.
+//!
+//! This keeps the runtime free of cryptography dependencies, but the group is far too small to be
+//! secure: it must only be used to experiment with the state machine.
+
+use num::{BigUint, One, Zero};
+
+use super::{
+    codec::{Decode, Encode},
+    hashing::{from_hex, hash, to_hex, Hash},
+};
+
+/// The safe prime `P = 2 * Q + 1` the group is defined modulo.
+const P: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x72, 0xef,
+];
+
+/// The generator of the group of quadratic residues modulo `P`, whose order is `Q`.
+const G: u32 = 4;
+
+fn p() -> BigUint {
+    BigUint::from_bytes_be(&P)
+}
+
+fn q() -> BigUint {
+    (p() - BigUint::one()) >> 1
+}
+
+/// Reduce `hash` to a scalar modulo `Q`.
+fn scalar(hash: &Hash) -> BigUint {
+    BigUint::from_bytes_be(hash) % q()
+}
+
+/// Encode `n`, which is lower than `P`, as 32 big endian bytes.
+fn to_bytes(n: &BigUint) -> Hash {
+    let bytes = n.to_bytes_be();
+    let mut output = [0u8; 32];
+    output[32 - bytes.len()..].copy_from_slice(&bytes);
+    output
+}
+
+/// The challenge of a signature of `message` by `public`, with the commitment `r`.
+fn challenge(r: &BigUint, public: &Public, message: &[u8]) -> BigUint {
+    let mut data = Vec::new();
+    to_bytes(r).encode_to(&mut data);
+    public.0.encode_to(&mut data);
+    data.extend_from_slice(message);
+    scalar(&hash(&data))
+}
+
+/// A public key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Public(pub Hash);
+
+impl Public {
+    /// Format the public key as hexadecimal, which is how accounts are identified.
+    pub fn to_hex(self) -> String {
+        to_hex(&self.0)
+    }
+
+    /// Parse a public key formatted by `to_hex`.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        from_hex(hex).map(Self)
+    }
+}
+
+/// A signature of a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signature {
+    /// The challenge the signature answers.
+    pub e: Hash,
+    /// The response to the challenge.
+    pub s: Hash,
+}
+
+impl Encode for Signature {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.e.encode_to(dest);
+        self.s.encode_to(dest);
+    }
+}
+
+impl Decode for Signature {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        Ok(Self {
+            e: Decode::decode_from(input)?,
+            s: Decode::decode_from(input)?,
+        })
+    }
+}
+
+impl Signature {
+    /// Whether this is a valid signature of `message` by `public`.
+    pub fn verify(&self, message: &[u8], public: &Public) -> bool {
+        let (p, q) = (p(), q());
+        let (e, s) = (
+            BigUint::from_bytes_be(&self.e),
+            BigUint::from_bytes_be(&self.s),
+        );
+        let y = BigUint::from_bytes_be(&public.0);
+        // A public key must be in the group, which excludes `0` and `1`.
+        if e >= q || s >= q || y <= BigUint::one() || y >= p || !y.modpow(&q, &p).is_one() {
+            return false;
+        }
+
+        // `r = g^s * y^e = g^(k - x * e) * g^(x * e) = g^k`, the commitment of the signer.
+        let r = BigUint::from(G).modpow(&s, &p) * y.modpow(&e, &p) % &p;
+        challenge(&r, public, message) == e
+    }
+}
+
+/// A key pair, which signs messages.
+#[derive(Debug, Clone)]
+pub struct Pair {
+    secret: BigUint,
+    public: Public,
+}
+
+impl Pair {
+    /// Derive a key pair from `seed`, e.g. `"alice"` for a development account.
+    pub fn from_seed(seed: &str) -> Self {
+        let mut secret = scalar(&hash(seed.as_bytes()));
+        if secret.is_zero() {
+            secret = BigUint::one();
+        }
+        let public = Public(to_bytes(&BigUint::from(G).modpow(&secret, &p())));
+        Self { secret, public }
+    }
+
+    /// The public key of the pair.
+    pub fn public(&self) -> Public {
+        self.public
+    }
+
+    /// Sign `message`.
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        let (p, q) = (p(), q());
+        // The nonce is derived from the secret and the message, so it is never reused for a
+        // different message.
+        let mut data = to_bytes(&self.secret).to_vec();
+        data.extend_from_slice(message);
+        let k = scalar(&hash(&data)) + BigUint::one();
+
+        let r = BigUint::from(G).modpow(&k, &p);
+        let e = challenge(&r, &self.public, message);
+        let s = (k + &q - (&self.secret * &e) % &q) % &q;
+        Signature {
+            e: to_bytes(&e),
+            s: to_bytes(&s),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Pair, Public};
+
+    #[test]
+    fn sign_and_verify() {
+        let alice = Pair::from_seed("alice");
+        let bob = Pair::from_seed("bob");
+        let signature = alice.sign(b"message");
+
+        assert!(signature.verify(b"message", &alice.public()));
+        assert!(!signature.verify(b"other message", &alice.public()));
+        assert!(!signature.verify(b"message", &bob.public()));
+
+        let mut forged = signature;
+        forged.s[31] ^= 1;
+        assert!(!forged.verify(b"message", &alice.public()));
+    }
+
+    #[test]
+    fn public_hex() {
+        let public = Pair::from_seed("alice").public();
+
+        assert_eq!(Public::from_hex(&public.to_hex()), Some(public));
+        assert_eq!(Public::from_hex("alice"), None);
+    }
+}
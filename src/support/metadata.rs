@@ -0,0 +1,139 @@
+//! The metadata of the runtime, which describes its pallets so that clients do not need to
+//! hard-code them: their calls and the names and types of their arguments, their events, errors,
+//! storage items and constants.
+//!
+//! `#[macros::call]` describes the calls and the errors of a pallet, and `#[macros::runtime]` puts
+//! together the metadata of the runtime, with the other parts each pallet describes itself.
+
+use core::fmt::Debug;
+
+/// The metadata of the runtime.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeMetadata {
+    /// The pallets of the runtime, in the order they are declared in, starting with system.
+    pub pallets: Vec<PalletMetadata>,
+}
+
+/// The metadata of a pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PalletMetadata {
+    /// The name of the field of the pallet in the runtime, which is also the name of the variant of
+    /// `RuntimeCall` and `RuntimeEvent`.
+    pub name: &'static str,
+    /// The index of the pallet, which calls and events are encoded with.
+    pub index: u8,
+    pub calls: Vec<VariantMetadata>,
+    pub events: Vec<VariantMetadata>,
+    /// The errors the calls of the pallet can fail with.
+    pub errors: Vec<&'static str>,
+    pub storage: Vec<FieldMetadata>,
+    pub constants: Vec<ConstantMetadata>,
+}
+
+/// A variant of an enum, like a call or an event, with its fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariantMetadata {
+    pub name: &'static str,
+    /// The index the variant is encoded with.
+    pub index: u8,
+    pub fields: Vec<FieldMetadata>,
+}
+
+/// A named value, like the argument of a call or a storage item, and its type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldMetadata {
+    pub name: &'static str,
+    pub type_name: String,
+}
+
+impl FieldMetadata {
+    /// The field `name` of type `T`.
+    pub fn new<T: ?Sized>(name: &'static str) -> Self {
+        Self {
+            name,
+            type_name: type_name::<T>(),
+        }
+    }
+}
+
+/// A constant of the configuration of a pallet, with its value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstantMetadata {
+    pub name: &'static str,
+    pub type_name: String,
+    /// The value, formatted with `Debug`.
+    pub value: String,
+}
+
+impl ConstantMetadata {
+    /// The constant `name`, with `value`.
+    pub fn new<T: Debug>(name: &'static str, value: T) -> Self {
+        Self {
+            name,
+            type_name: type_name::<T>(),
+            value: format!("{value:?}"),
+        }
+    }
+}
+
+/// Describes the calls of a pallet. `#[macros::call]` implements this for the pallet.
+pub trait PalletCalls {
+    /// The callable functions of the pallet, with the index they are encoded with.
+    fn calls() -> Vec<VariantMetadata>;
+
+    /// The errors the callable functions can fail with, as declared with `#[errors(...)]`. The
+    /// errors of the calls a function dispatches are the ones of the pallets of these calls.
+    fn errors() -> Vec<&'static str>;
+}
+
+/// Describes the parts of a pallet which are not callable. Each pallet implements this itself,
+/// like `Encode`, since its events, storage and configuration are plain Rust items.
+pub trait PalletInfo {
+    fn events() -> Vec<VariantMetadata> {
+        Vec::new()
+    }
+
+    fn storage() -> Vec<FieldMetadata> {
+        Vec::new()
+    }
+
+    fn constants() -> Vec<ConstantMetadata> {
+        Vec::new()
+    }
+}
+
+/// The name of the type `T`, in the runtime, without the paths of the types, e.g. `Vec<String>`
+/// rather than `alloc::vec::Vec<alloc::string::String>`.
+pub fn type_name<T: ?Sized>() -> String {
+    let full_name = core::any::type_name::<T>();
+    let mut name = String::new();
+    let mut segment = String::new();
+    for c in full_name.chars() {
+        if c.is_alphanumeric() || c == '_' || c == ':' {
+            segment.push(c);
+        } else {
+            name.push_str(segment.rsplit("::").next().unwrap_or_default());
+            segment.clear();
+            name.push(c);
+        }
+    }
+    name.push_str(segment.rsplit("::").next().unwrap_or_default());
+    name
+}
+
+#[cfg(test)]
+mod test {
+    use super::type_name;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn type_names_are_short() {
+        assert_eq!(type_name::<u128>(), "u128");
+        assert_eq!(type_name::<&'static str>(), "&str");
+        assert_eq!(
+            type_name::<BTreeMap<String, Vec<(u32, [u8; 8])>>>(),
+            "BTreeMap<String, Vec<(u32, [u8; 8])>>"
+        );
+        assert_eq!(type_name::<Box<crate::RuntimeCall>>(), "Box<RuntimeCall>");
+    }
+}
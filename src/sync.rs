@@ -0,0 +1,730 @@
+//! The sync of the chain of a node with the chains of its peers.
+//!
+//! A node learns about the best block of a peer from its handshake and its block announces. When
+//! the block is unknown, the node downloads the headers of the block and of its ancestors from the
+//! peer, down to the first block it knows: the common ancestor of both chains, even when they are
+//! on different forks. It then downloads the bodies of the blocks, and imports them in order with
+//! the consensus engine, which executes them.
+//!
+//! With fast sync, a node which only knows the genesis downloads the headers of the finalized block
+//! of a peer down to the genesis, then a snapshot of the state after the finalized block, instead
+//! of executing every block. The headers must link to the genesis, and the consensus engine checks
+//! their seals from the genesis on, so the state root of the finalized header is authentic. The
+//! snapshot is downloaded in chunks of at most `MAX_STATE_CHUNK` bytes, so that it fits in
+//! messages, and is checked against this state root once complete. The blocks after it are then
+//! synced as usual.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::{
+    chain::{Chain, ImportedBlock},
+    consensus::Consensus,
+    network::{Message, PeerId, StateChunk, Status},
+    support::{
+        self,
+        codec::{Decode, Encode},
+        hashing::Hash,
+    },
+    types, Runtime,
+};
+
+/// The maximum number of headers of a `HeadersRequest`.
+pub const MAX_HEADERS: u32 = 64;
+/// The maximum number of bodies of a `BodiesRequest`.
+pub const MAX_BODIES: usize = 16;
+/// The maximum length of the encoded state in a `StateResponse`, which leaves room for its block
+/// in the message.
+pub const MAX_STATE_CHUNK: u32 = 512 * 1024;
+
+/// How a node syncs the blocks it does not know yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncMode {
+    /// Download and execute every block.
+    Full,
+    /// Download the state at the finalized block of a peer when the node only knows the genesis,
+    /// then the blocks after it.
+    Fast,
+}
+
+/// The request in flight to a peer.
+enum Request {
+    /// The headers of the block `target` and of its ancestors, down to a known block. `headers`
+    /// are the ones received so far, from the highest down. With `fast`, the state of `target` is
+    /// downloaded instead of the bodies of the blocks.
+    Headers {
+        target: Hash,
+        headers: Vec<types::Header>,
+        fast: bool,
+    },
+    /// The bodies of the blocks of the `headers`, in ascending order, of which the first
+    /// `requested` were requested.
+    Bodies {
+        headers: VecDeque<types::Header>,
+        requested: usize,
+    },
+    /// The state after the block of the `header`, of which `state` is the encoded part received so
+    /// far.
+    State {
+        header: types::Header,
+        state: Vec<u8>,
+    },
+}
+
+impl Request {
+    /// The message asking the peer for what the request is waiting for.
+    fn message(&self) -> Message {
+        match self {
+            Request::Headers {
+                target, headers, ..
+            } => Message::HeadersRequest {
+                from: headers.last().map_or(*target, |header| header.parent_hash),
+                max: MAX_HEADERS,
+            },
+            Request::Bodies { headers, requested } => Message::BodiesRequest {
+                hashes: headers
+                    .iter()
+                    .take(*requested)
+                    .map(|header| header.hash())
+                    .collect(),
+            },
+            Request::State { header, state } => Message::StateRequest {
+                hash: header.hash(),
+                start: state.len() as u32,
+            },
+        }
+    }
+}
+
+/// A peer the node syncs with.
+struct Peer {
+    /// The status of the peer, updated by its block announces.
+    status: Status,
+    /// The request in flight to the peer. There is at most one, so the next response of the peer
+    /// answers it.
+    request: Option<Request>,
+}
+
+/// What a response of a peer led to.
+#[derive(Default)]
+pub struct SyncProgress {
+    /// The blocks imported from the response, in order.
+    pub imported: Vec<ImportedBlock>,
+    /// The block whose state was imported from a snapshot, if any.
+    pub state: Option<Hash>,
+    /// The next request to send to the peer, if any.
+    pub request: Option<Message>,
+    /// Why the response was rejected, if it was. The request is then dropped, until the peer
+    /// announces another block.
+    pub error: Option<&'static str>,
+}
+
+/// This is the Chain Sync.
+/// It keeps track of the status of the peers, and of the request in flight to each of them.
+pub struct ChainSync {
+    mode: SyncMode,
+    peers: BTreeMap<PeerId, Peer>,
+}
+
+impl ChainSync {
+    /// Create the sync of a node, without any peer.
+    pub fn new(mode: SyncMode) -> Self {
+        Self {
+            mode,
+            peers: BTreeMap::new(),
+        }
+    }
+
+    /// Start syncing with a peer which just connected with the `status`, if it knows blocks the
+    /// node does not. Returns the request to send to the peer.
+    pub fn peer_connected(
+        &mut self,
+        chain: &Chain,
+        peer: PeerId,
+        status: Status,
+    ) -> Option<Message> {
+        let request = None;
+        self.peers.insert(peer, Peer { status, request });
+        self.next_request(chain, peer)
+    }
+
+    /// Forget a peer which disconnected, and its request in flight.
+    pub fn peer_disconnected(&mut self, peer: PeerId) {
+        self.peers.remove(&peer);
+    }
+
+    /// Record the block `peer` announced as its best block, and start syncing with it if the block
+    /// is unknown. Returns the request to send to the peer.
+    pub fn block_announced(
+        &mut self,
+        chain: &Chain,
+        peer: PeerId,
+        header: &types::Header,
+    ) -> Option<Message> {
+        let status = &mut self.peers.get_mut(&peer)?.status;
+        status.best_number = header.block_number;
+        status.best_hash = header.hash();
+        self.next_request(chain, peer)
+    }
+
+    /// Handle the response of `peer` to its request in flight. The blocks it completes are
+    /// imported with the consensus `engine`, in order.
+    pub fn on_response(
+        &mut self,
+        chain: &mut Chain,
+        engine: &dyn Consensus,
+        peer: PeerId,
+        response: Message,
+    ) -> SyncProgress {
+        let mut progress = SyncProgress::default();
+        let Some(state) = self.peers.get_mut(&peer) else {
+            progress.error = Some("Peer is unknown");
+            return progress;
+        };
+
+        let next = match (state.request.take(), response) {
+            (
+                Some(Request::Headers {
+                    target,
+                    headers,
+                    fast,
+                }),
+                Message::Headers { headers: received },
+            ) => on_headers(chain, engine, target, headers, fast, received),
+            (Some(Request::Bodies { headers, requested }), Message::Bodies { bodies }) => {
+                on_bodies(chain, engine, headers, requested, bodies, &mut progress)
+            }
+            (Some(Request::State { header, state }), Message::StateResponse { snapshot }) => {
+                on_state(chain, header, state, snapshot, &mut progress)
+            }
+            _ => Err("Response does not answer the request to the peer"),
+        };
+
+        match next {
+            Ok(Some(request)) => {
+                progress.request = Some(request.message());
+                state.request = Some(request);
+            }
+            // The sync with the peer goes on with its best block, if it is still unknown.
+            Ok(None) => progress.request = self.next_request(chain, peer),
+            Err(e) => progress.error = Some(e),
+        }
+        progress
+    }
+
+    /// Start a request to `peer` if there is none in flight, and it knows blocks the node does
+    /// not: its finalized block when fast syncing from the genesis, or its best block.
+    fn next_request(&mut self, chain: &Chain, peer: PeerId) -> Option<Message> {
+        let state = self.peers.get_mut(&peer)?;
+        if state.request.is_some() {
+            return None;
+        }
+
+        let status = state.status;
+        let at_genesis = chain.best_state().system.block_number() == 0;
+        let request = if self.mode == SyncMode::Fast && at_genesis && status.finalized_number > 0 {
+            Request::Headers {
+                target: status.finalized_hash,
+                headers: Vec::new(),
+                fast: true,
+            }
+        } else if chain.block(&status.best_hash).is_none() {
+            Request::Headers {
+                target: status.best_hash,
+                headers: Vec::new(),
+                fast: false,
+            }
+        } else {
+            return None;
+        };
+        let message = request.message();
+        state.request = Some(request);
+        Some(message)
+    }
+}
+
+/// Add the `received` headers to the `headers` of `target` received so far, and return the next
+/// request: more headers until a known block is reached, then the bodies of the blocks, or the
+/// state of `target` when `fast`, once the consensus `engine` verified the seals of the headers.
+fn on_headers(
+    chain: &Chain,
+    engine: &dyn Consensus,
+    target: Hash,
+    mut headers: Vec<types::Header>,
+    fast: bool,
+    received: Vec<types::Header>,
+) -> Result<Option<Request>, &'static str> {
+    if received.is_empty() || received.len() > MAX_HEADERS as usize {
+        return Err("Headers response is empty or too long");
+    }
+
+    let mut expected = headers.last().map_or(target, |header| header.parent_hash);
+    for header in received {
+        // The block may have been imported from another peer in the meantime.
+        if chain.block(&expected).is_some() {
+            break;
+        }
+        if header.hash() != expected {
+            return Err("Headers do not form a chain");
+        }
+        if header.block_number == 0 {
+            return Err("Headers do not lead to a known block");
+        }
+        expected = header.parent_hash;
+        headers.push(header);
+    }
+    if chain.block(&expected).is_none() {
+        return Ok(Some(Request::Headers {
+            target,
+            headers,
+            fast,
+        }));
+    }
+
+    headers.reverse();
+    if fast {
+        // The blocks are not executed, so their headers are the only proof of the state.
+        engine.verify_headers(chain, &headers)?;
+        if let Some(header) = headers.pop() {
+            return Ok(Some(Request::State {
+                header,
+                state: Vec::new(),
+            }));
+        }
+    }
+    if headers.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(Request::Bodies {
+        requested: headers.len().min(MAX_BODIES),
+        headers: headers.into(),
+    }))
+}
+
+/// Import the blocks of the `headers` with their received `bodies`, and return the request for
+/// the bodies of the remaining blocks.
+fn on_bodies(
+    chain: &mut Chain,
+    engine: &dyn Consensus,
+    mut headers: VecDeque<types::Header>,
+    requested: usize,
+    bodies: Vec<Vec<types::Extrinsic>>,
+    progress: &mut SyncProgress,
+) -> Result<Option<Request>, &'static str> {
+    // The peer may have pruned some of the blocks, if they left its best chain.
+    if bodies.is_empty() || bodies.len() > requested {
+        return Err("Bodies response is empty or too long");
+    }
+
+    for extrinsics in bodies {
+        let header = headers
+            .pop_front()
+            .expect("there is a header for each requested body");
+        if header.extrinsics_root != support::extrinsics_root(&extrinsics) {
+            return Err("Block body does not match its header");
+        }
+        let block = types::Block { header, extrinsics };
+        if chain.block(&block.header.hash()).is_none() {
+            progress.imported.push(engine.import_block(chain, block)?);
+        }
+    }
+
+    if headers.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(Request::Bodies {
+        requested: headers.len().min(MAX_BODIES),
+        headers,
+    }))
+}
+
+/// Add the received `snapshot` chunk to the `state` after the block of the `header`, and import
+/// the state once it is complete, or return the request for its next chunk.
+fn on_state(
+    chain: &mut Chain,
+    header: types::Header,
+    mut state: Vec<u8>,
+    snapshot: Option<StateChunk>,
+    progress: &mut SyncProgress,
+) -> Result<Option<Request>, &'static str> {
+    let chunk = snapshot.ok_or("Peer does not have the state of the block")?;
+    if chunk.block.header != header {
+        return Err("State snapshot is not the one of the requested block");
+    }
+    if header.extrinsics_root != support::extrinsics_root(&chunk.block.extrinsics) {
+        return Err("Block body does not match its header");
+    }
+    if chunk.data.len() > MAX_STATE_CHUNK as usize
+        || state.len() + chunk.data.len() > chunk.total as usize
+    {
+        return Err("State snapshot chunk is too long");
+    }
+    state.extend_from_slice(&chunk.data);
+    if state.len() < chunk.total as usize {
+        if chunk.data.is_empty() {
+            return Err("State snapshot chunk is empty");
+        }
+        return Ok(Some(Request::State { header, state }));
+    }
+    let state = verify_state(&header, &state)?;
+
+    let hash = header.hash();
+    chain.import_state(chunk.block, state)?;
+    progress.state = Some(hash);
+    Ok(None)
+}
+
+/// Decode the encoded `state` after the block of the `header`, and check it against the state root
+/// of the header.
+fn verify_state(header: &types::Header, state: &[u8]) -> Result<Runtime, &'static str> {
+    let state = Runtime::decode(state).map_err(|_| "State snapshot can not be decoded")?;
+    if state.system.block_number() != header.block_number
+        || state.system.block_hash() != header.hash()
+    {
+        return Err("State snapshot is not the state after the block");
+    }
+
+    // The state root commits to the state before the hash of the block is recorded.
+    let mut executed = state.clone();
+    executed.system.set_block_hash(header.parent_hash);
+    if executed.state_root() != header.state_root {
+        return Err("State snapshot does not match the state root of the block");
+    }
+    Ok(state)
+}
+
+/// The status of the `chain`, which the node sends to its peers.
+pub fn status(chain: &Chain) -> Status {
+    let finalized_hash = chain.finalized_hash();
+    let finalized = chain
+        .block(&finalized_hash)
+        .expect("the finalized block is known");
+    Status {
+        best_number: chain.best_state().system.block_number(),
+        best_hash: chain.best_hash(),
+        finalized_number: finalized.header.block_number,
+        finalized_hash,
+    }
+}
+
+/// Answer a sync request of a peer from the `chain`, or return `None` if the message is not a
+/// sync request.
+pub fn respond(chain: &Chain, request: &Message) -> Option<Message> {
+    match request {
+        Message::HeadersRequest { from, max } => {
+            let mut headers = Vec::new();
+            let mut hash = *from;
+            while headers.len() < (*max).min(MAX_HEADERS) as usize {
+                let Some(block) = chain.block(&hash) else {
+                    break;
+                };
+                hash = block.header.parent_hash;
+                headers.push(block.header.clone());
+            }
+            Some(Message::Headers { headers })
+        }
+        Message::BodiesRequest { hashes } => {
+            let bodies = hashes
+                .iter()
+                .take(MAX_BODIES)
+                .map_while(|hash| chain.block(hash))
+                .map(|block| block.extrinsics.clone())
+                .collect();
+            Some(Message::Bodies { bodies })
+        }
+        Message::StateRequest { hash, start } => {
+            let snapshot = chain
+                .block(hash)
+                .zip(chain.state(hash))
+                .map(|(block, state)| {
+                    let state = state.encode();
+                    let start = (*start as usize).min(state.len());
+                    let end = state.len().min(start + MAX_STATE_CHUNK as usize);
+                    StateChunk {
+                        block: block.clone(),
+                        total: state.len() as u32,
+                        data: state[start..end].to_vec(),
+                    }
+                });
+            Some(Message::StateResponse { snapshot })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{respond, status, ChainSync, SyncMode, MAX_BODIES, MAX_HEADERS, MAX_STATE_CHUNK};
+    use crate::{
+        aura::{self, Slot, AURA_ENGINE_ID},
+        chain::{Chain, ChainConfig, ImportedBlock},
+        consensus::aura::Aura,
+        network::{Message, MAX_MESSAGE_LENGTH},
+        support::{
+            codec::{Decode, Encode},
+            crypto::Pair,
+            DigestItem, RawOrigin,
+        },
+        transaction_pool::TransactionPool,
+        DevAccount, Runtime,
+    };
+
+    // The engine of both authorities of the development chain, alice and bob.
+    fn engine() -> Aura {
+        Aura::new(vec![Pair::from_seed("alice"), Pair::from_seed("bob")])
+    }
+
+    // A chain of the development genesis, where blocks two blocks under the best block are
+    // finalized.
+    fn new_chain() -> Chain {
+        let config = ChainConfig {
+            finality_depth: Some(2),
+            state_pruning: None,
+        };
+        Chain::new(crate::dev_genesis(), config)
+    }
+
+    // Author a block on the best block of `chain` in each of the `slots`.
+    fn author(chain: &mut Chain, slots: impl IntoIterator<Item = Slot>) {
+        let mut pool = TransactionPool::new();
+        for slot in slots {
+            let now = slot * <Runtime as aura::Config>::SLOT_DURATION;
+            crate::author_block(chain, &mut pool, &engine(), crate::inherents(now)).unwrap();
+        }
+    }
+
+    // Sync `chain` with the chain of a peer until there is nothing left to request, and return
+    // the imported blocks.
+    fn sync_with(
+        sync: &mut ChainSync,
+        chain: &mut Chain,
+        peer_chain: &Chain,
+    ) -> Vec<ImportedBlock> {
+        let mut imported = Vec::new();
+        let mut request = sync.peer_connected(chain, 0, status(peer_chain));
+        while let Some(message) = request {
+            let response = respond(peer_chain, &message).unwrap();
+            let progress = sync.on_response(chain, &engine(), 0, response);
+            assert_eq!(progress.error, None);
+            imported.extend(progress.imported);
+            request = progress.request;
+        }
+        imported
+    }
+
+    #[test]
+    fn full_sync_imports_the_blocks_of_a_peer() {
+        let mut peer_chain = new_chain();
+        let blocks = MAX_HEADERS as u64 + MAX_BODIES as u64;
+        author(&mut peer_chain, 1..=blocks);
+
+        let mut chain = new_chain();
+        let mut sync = ChainSync::new(SyncMode::Full);
+        let imported = sync_with(&mut sync, &mut chain, &peer_chain);
+
+        assert_eq!(imported.len() as u64, blocks);
+        assert_eq!(chain.best_hash(), peer_chain.best_hash());
+        assert_eq!(chain.finalized_hash(), peer_chain.finalized_hash());
+
+        // There is nothing to sync once the chains are the same.
+        assert!(sync
+            .peer_connected(&chain, 1, status(&peer_chain))
+            .is_none());
+    }
+
+    #[test]
+    fn sync_switches_to_the_longer_fork_of_a_peer() {
+        let mut peer_chain = new_chain();
+        author(&mut peer_chain, [1]);
+        let mut chain = new_chain();
+        let common = peer_chain.block(&peer_chain.best_hash()).unwrap().clone();
+        chain.import_block(common).unwrap();
+
+        // Both chains fork after the first block, and the fork of the peer is longer.
+        author(&mut chain, [3]);
+        let fork = chain.best_hash();
+        author(&mut peer_chain, [4, 5]);
+
+        let mut sync = ChainSync::new(SyncMode::Full);
+        let imported = sync_with(&mut sync, &mut chain, &peer_chain);
+        assert_eq!(imported.len(), 2);
+        let retracted: Vec<_> = imported[1]
+            .retracted
+            .iter()
+            .map(|block| block.header.hash())
+            .collect();
+        assert_eq!(retracted, vec![fork]);
+        assert_eq!(chain.best_hash(), peer_chain.best_hash());
+    }
+
+    #[test]
+    fn fast_sync_imports_the_state_of_a_finalized_block() {
+        let mut peer_chain = new_chain();
+        author(&mut peer_chain, 1..=6);
+        let genesis_hash = new_chain().best_hash();
+
+        let mut chain = new_chain();
+        let mut sync = ChainSync::new(SyncMode::Fast);
+        let imported = sync_with(&mut sync, &mut chain, &peer_chain);
+
+        // The state of the finalized block of the peer is imported, then the blocks after it are
+        // executed, which finalizes the next block.
+        assert_eq!(imported.len(), 2);
+        assert!(chain.block(&genesis_hash).is_none());
+        assert_eq!(chain.best_hash(), peer_chain.best_hash());
+        assert_eq!(chain.finalized_hash(), peer_chain.finalized_hash());
+        assert_eq!(
+            chain.best_state().encode(),
+            peer_chain.best_state().encode()
+        );
+    }
+
+    #[test]
+    fn fast_sync_downloads_a_large_state_in_chunks() {
+        // The state of the genesis is too large for a single message.
+        let mut genesis = crate::dev_genesis();
+        for i in 0..20_000 {
+            genesis
+                .balances
+                .set_balance(RawOrigin::Root.into(), format!("account-{i}"), 1)
+                .unwrap();
+        }
+        let config = ChainConfig {
+            finality_depth: Some(2),
+            state_pruning: None,
+        };
+        let mut peer_chain = Chain::new(genesis.clone(), config);
+        author(&mut peer_chain, 1..=4);
+        let state = peer_chain.state(&peer_chain.finalized_hash()).unwrap();
+        assert!(state.encode().len() > MAX_STATE_CHUNK as usize);
+
+        let mut chain = Chain::new(genesis, config);
+        let mut sync = ChainSync::new(SyncMode::Fast);
+        let mut request = sync.peer_connected(&chain, 0, status(&peer_chain));
+        let mut chunks = 0;
+        while let Some(message) = request {
+            let response = respond(&peer_chain, &message).unwrap();
+            if let Message::StateResponse { snapshot } = &response {
+                let chunk = snapshot.as_ref().unwrap();
+                assert!(chunk.data.len() <= MAX_STATE_CHUNK as usize);
+                assert!(response.encode().len() <= MAX_MESSAGE_LENGTH as usize);
+                chunks += 1;
+            }
+            let progress = sync.on_response(&mut chain, &engine(), 0, response);
+            assert_eq!(progress.error, None);
+            request = progress.request;
+        }
+
+        assert!(chunks > 1);
+        assert_eq!(chain.best_hash(), peer_chain.best_hash());
+        assert_eq!(
+            chain.best_state().encode(),
+            peer_chain.best_state().encode()
+        );
+    }
+
+    #[test]
+    fn fast_sync_rejects_a_forged_state() {
+        let mut peer_chain = new_chain();
+        author(&mut peer_chain, 1..=4);
+        let mut chain = new_chain();
+        let mut sync = ChainSync::new(SyncMode::Fast);
+
+        let request = sync.peer_connected(&chain, 0, status(&peer_chain)).unwrap();
+        let response = respond(&peer_chain, &request).unwrap();
+        let request = sync.on_response(&mut chain, &engine(), 0, response).request;
+        let Some(Message::StateResponse {
+            snapshot: Some(mut chunk),
+        }) = respond(&peer_chain, &request.unwrap())
+        else {
+            panic!("expected a snapshot");
+        };
+
+        // Change the balance of alice in the snapshot.
+        let mut forged = Runtime::decode(&chunk.data).unwrap();
+        let alice = DevAccount::new("alice").id();
+        forged
+            .balances
+            .set_balance(RawOrigin::Root.into(), alice, 1)
+            .unwrap();
+        chunk.data = forged.encode();
+        chunk.total = chunk.data.len() as u32;
+        let snapshot = Some(chunk);
+        let progress = sync.on_response(
+            &mut chain,
+            &engine(),
+            0,
+            Message::StateResponse { snapshot },
+        );
+        assert_eq!(
+            progress.error,
+            Some("State snapshot does not match the state root of the block")
+        );
+        assert_eq!(chain.best_state().system.block_number(), 0);
+    }
+
+    #[test]
+    fn fast_sync_rejects_a_forged_header() {
+        let mut peer_chain = new_chain();
+        author(&mut peer_chain, 1..=4);
+        let mut chain = new_chain();
+        let mut sync = ChainSync::new(SyncMode::Fast);
+
+        // A peer which is not an authority signs a header with another state root, and advertises
+        // it as its finalized block.
+        let finalized = peer_chain.block(&peer_chain.finalized_hash()).unwrap();
+        let parent = peer_chain.block(&finalized.header.parent_hash).unwrap();
+        let mut forged = finalized.header.clone();
+        forged.take_seal(AURA_ENGINE_ID);
+        forged.state_root = [1; 32];
+        let signature = Pair::from_seed("mallory").sign(&forged.pre_hash());
+        forged
+            .digest
+            .push(DigestItem::Seal(AURA_ENGINE_ID, signature.encode()));
+        let mut peer_status = status(&peer_chain);
+        peer_status.finalized_hash = forged.hash();
+
+        let request = sync.peer_connected(&chain, 0, peer_status);
+        assert!(matches!(
+            request,
+            Some(Message::HeadersRequest { from, .. }) if from == forged.hash()
+        ));
+        let headers = vec![forged, parent.header.clone()];
+        let progress = sync.on_response(&mut chain, &engine(), 0, Message::Headers { headers });
+        assert_eq!(
+            progress.error,
+            Some("Block seal is not signed by the authority of its slot")
+        );
+        assert!(progress.request.is_none());
+        assert_eq!(chain.best_state().system.block_number(), 0);
+    }
+
+    #[test]
+    fn responses_are_checked() {
+        let mut peer_chain = new_chain();
+        author(&mut peer_chain, 1..=2);
+        let mut chain = new_chain();
+        let mut sync = ChainSync::new(SyncMode::Full);
+
+        // A response without a request is rejected.
+        let bodies = Message::Bodies { bodies: vec![] };
+        let progress = sync.on_response(&mut chain, &engine(), 0, bodies);
+        assert_eq!(progress.error, Some("Peer is unknown"));
+
+        // The headers must be the ones of the best block of the peer and of its ancestors.
+        sync.peer_connected(&chain, 0, status(&peer_chain)).unwrap();
+        let best = peer_chain.block(&peer_chain.best_hash()).unwrap();
+        let parent = peer_chain.block(&best.header.parent_hash).unwrap();
+        let headers = vec![parent.header.clone(), best.header.clone()];
+        let progress = sync.on_response(&mut chain, &engine(), 0, Message::Headers { headers });
+        assert_eq!(progress.error, Some("Headers do not form a chain"));
+
+        // The failed request is dropped, until the peer announces a block.
+        let bodies = Message::Bodies { bodies: vec![] };
+        let progress = sync.on_response(&mut chain, &engine(), 0, bodies);
+        assert_eq!(
+            progress.error,
+            Some("Response does not answer the request to the peer")
+        );
+        assert!(sync.block_announced(&chain, 0, &best.header).is_some());
+    }
+}
@@ -1,18 +1,89 @@
-use num::traits::{One, Zero};
+use core::fmt::Debug;
+use num::traits::{CheckedSub, One, Zero};
 use std::{collections::BTreeMap, ops::AddAssign};
 
+use crate::support::{
+    codec::{Decode, Encode},
+    hashing::Hash,
+    metadata::{ConstantMetadata, FieldMetadata, PalletInfo, VariantMetadata},
+    Digest, DigestItem, DispatchInfo, DispatchResultWithPostInfo, EventRecord, FromEntropy, Hooks,
+    Phase, RawOrigin, Weight,
+};
+
+/// The limits on the weight of a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockWeights {
+    /// The weight every extrinsic takes on top of its call, e.g. to check and apply it.
+    pub base_extrinsic: Weight,
+    /// The maximum total weight of the extrinsics of a block.
+    pub max_block: Weight,
+}
+
 pub trait Config {
-    type AccountId: Ord + Clone;
-    type BlockNumber: Zero + One + AddAssign + Copy;
-    type Nonce: Zero + One + Copy;
+    type AccountId: Ord + Clone + Encode + FromEntropy;
+    /// The outer origin type of the runtime, which the origin of every call is converted into.
+    /// It can be converted back to a `RawOrigin` with the `ensure_*` functions of `support`.
+    type RuntimeOrigin: From<RawOrigin<Self::AccountId>>
+        + Into<Result<RawOrigin<Self::AccountId>, Self::RuntimeOrigin>>;
+    type BlockNumber: Zero + One + AddAssign + Copy + PartialEq;
+    type Nonce: Zero + One + CheckedSub + Ord + Copy + Encode;
+    /// The outer event type of the runtime, which the events of every pallet are converted into.
+    type RuntimeEvent: From<Event> + Debug + Clone;
+    /// The limits on the weight of a block, which are not enforced by default.
+    const BLOCK_WEIGHTS: BlockWeights = BlockWeights {
+        base_extrinsic: 0,
+        max_block: Weight::MAX,
+    };
+    /// The maximum length of the extrinsics of a block once encoded, in bytes, which is not
+    /// enforced by default.
+    const MAX_BLOCK_LENGTH: u32 = u32::MAX;
+    /// The maximum number of extrinsics in a block, which is not enforced by default.
+    const MAX_EXTRINSICS: u32 = u32::MAX;
+}
+
+/// The events of the system pallet, which record the outcome of every extrinsic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// An extrinsic was applied, and its call succeeded, taking `weight`.
+    ExtrinsicSuccess { weight: Weight },
+    /// An extrinsic was applied, but its call failed with `error`.
+    ExtrinsicFailed { error: &'static str },
+}
+
+impl Encode for Event {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            Event::ExtrinsicSuccess { weight } => {
+                dest.push(0);
+                weight.encode_to(dest);
+            }
+            Event::ExtrinsicFailed { error } => {
+                dest.push(1);
+                error.encode_to(dest);
+            }
+        }
+    }
 }
 
 /// This is the System Pallet.
 /// It handles low level state needed for your blockchain.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Pallet<T: Config> {
     pub block_number: T::BlockNumber,
     pub nonce: BTreeMap<T::AccountId, T::Nonce>,
+    /// The hash of the last executed block, which is the parent of the next block.
+    pub block_hash: Hash,
+    /// The digest of the current block: the pre-runtime items of its author, followed by the items
+    /// deposited by the pallets while executing it.
+    pub digest: Digest,
+    /// The events deposited while executing the current block. They are not part of the encoded
+    /// state, as they only describe how the state changed.
+    events: Vec<EventRecord<T::RuntimeEvent>>,
+    /// The index of the extrinsic being applied in the current block.
+    extrinsic_index: u32,
+    /// The weight consumed by the current block so far, by its extrinsics and the hooks of the
+    /// pallets. It is not part of the encoded state either.
+    block_weight: Weight,
 }
 
 impl<T: Config> Pallet<T> {
@@ -20,6 +91,11 @@ impl<T: Config> Pallet<T> {
         Self {
             block_number: T::BlockNumber::zero(),
             nonce: BTreeMap::new(),
+            block_hash: Hash::default(),
+            digest: Digest::default(),
+            events: Vec::new(),
+            extrinsic_index: 0,
+            block_weight: 0,
         }
     }
 
@@ -35,6 +111,100 @@ impl<T: Config> Pallet<T> {
         self.block_number += T::BlockNumber::one();
     }
 
+    /// Get the hash of the last executed block.
+    pub fn block_hash(&self) -> Hash {
+        self.block_hash
+    }
+
+    /// Record `hash` as the hash of the last executed block.
+    pub fn set_block_hash(&mut self, hash: Hash) {
+        self.block_hash = hash;
+    }
+
+    /// Get the digest of the current block.
+    pub fn digest(&self) -> &Digest {
+        &self.digest
+    }
+
+    /// Start the digest of the current block with the pre-runtime items of `digest`, which is the
+    /// digest of the block author.
+    pub fn initialize_digest(&mut self, digest: &Digest) {
+        let logs = digest
+            .logs
+            .iter()
+            .filter(|item| matches!(item, DigestItem::PreRuntime(..)))
+            .cloned()
+            .collect();
+        self.digest = Digest { logs };
+    }
+
+    /// Deposit `item` in the digest of the current block.
+    pub fn deposit_log(&mut self, item: DigestItem) {
+        self.digest.push(item);
+    }
+
+    /// Get the events deposited while executing the current block.
+    pub fn events(&self) -> &[EventRecord<T::RuntimeEvent>] {
+        &self.events
+    }
+
+    /// Clear the events and the weight of the previous block, before executing a new one.
+    pub fn reset_events(&mut self) {
+        self.events.clear();
+        self.extrinsic_index = 0;
+        self.block_weight = 0;
+    }
+
+    /// Deposit `event` in the phase of the extrinsic being applied.
+    pub fn deposit_event(&mut self, event: impl Into<T::RuntimeEvent>) {
+        self.deposit_event_in_phase(Phase::ApplyExtrinsic(self.extrinsic_index), event);
+    }
+
+    /// Deposit `event` in `phase`, e.g. for the events of the hooks of the pallets.
+    pub fn deposit_event_in_phase(&mut self, phase: Phase, event: impl Into<T::RuntimeEvent>) {
+        self.events.push(EventRecord {
+            phase,
+            event: event.into(),
+        });
+    }
+
+    /// Get the weight consumed by the current block so far.
+    pub fn block_weight(&self) -> Weight {
+        self.block_weight
+    }
+
+    /// Get the weight the current block has left, under the `max_block` of `BLOCK_WEIGHTS`.
+    pub fn remaining_weight(&self) -> Weight {
+        T::BLOCK_WEIGHTS.max_block.saturating_sub(self.block_weight)
+    }
+
+    /// Record `weight` as consumed by the current block outside of its extrinsics, e.g. by the
+    /// hooks of the pallets.
+    pub fn register_extra_weight(&mut self, weight: Weight) {
+        self.block_weight = self.block_weight.saturating_add(weight);
+    }
+
+    /// Deposit the outcome of the extrinsic being applied, whose call has `info` and returned
+    /// `result`, record the weight it took, and move on to the next extrinsic.
+    pub fn note_applied_extrinsic(
+        &mut self,
+        result: &DispatchResultWithPostInfo,
+        info: &DispatchInfo,
+    ) {
+        let (weight, event) = match result {
+            Ok(post_info) => {
+                let weight = post_info.calc_actual_weight(info);
+                (weight, Event::ExtrinsicSuccess { weight })
+            }
+            // A call which failed took its full weight.
+            Err(error) => (info.weight, Event::ExtrinsicFailed { error }),
+        };
+        // Every extrinsic also takes the base weight of an extrinsic.
+        self.register_extra_weight(T::BLOCK_WEIGHTS.base_extrinsic.saturating_add(weight));
+        self.deposit_event(event);
+        self.extrinsic_index += 1;
+    }
+
     // Increment the nonce of an account. This helps us keep track of how many transactions each
     // account has made.
     pub fn inc_nonce(&mut self, who: &T::AccountId) {
@@ -48,6 +218,83 @@ impl<T: Config> Pallet<T> {
     }
 }
 
+impl<T: Config> Encode for Pallet<T>
+where
+    T::BlockNumber: Encode,
+{
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.block_number.encode_to(dest);
+        self.nonce.encode_to(dest);
+        self.block_hash.encode_to(dest);
+        self.digest.encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for Pallet<T>
+where
+    T::AccountId: Decode,
+    T::BlockNumber: Decode,
+    T::Nonce: Decode,
+{
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        Ok(Self {
+            block_number: Decode::decode_from(input)?,
+            nonce: Decode::decode_from(input)?,
+            block_hash: Decode::decode_from(input)?,
+            digest: Decode::decode_from(input)?,
+            events: Vec::new(),
+            extrinsic_index: 0,
+            block_weight: 0,
+        })
+    }
+}
+
+impl<T: Config> PalletInfo for Pallet<T>
+where
+    T::BlockNumber: Debug,
+{
+    fn events() -> Vec<VariantMetadata> {
+        vec![
+            VariantMetadata {
+                name: "ExtrinsicSuccess",
+                index: 0,
+                fields: vec![FieldMetadata::new::<Weight>("weight")],
+            },
+            VariantMetadata {
+                name: "ExtrinsicFailed",
+                index: 1,
+                fields: vec![FieldMetadata::new::<&'static str>("error")],
+            },
+        ]
+    }
+
+    fn storage() -> Vec<FieldMetadata> {
+        vec![
+            FieldMetadata::new::<T::BlockNumber>("block_number"),
+            FieldMetadata::new::<BTreeMap<T::AccountId, T::Nonce>>("nonce"),
+            FieldMetadata::new::<Hash>("block_hash"),
+            FieldMetadata::new::<Digest>("digest"),
+        ]
+    }
+
+    fn constants() -> Vec<ConstantMetadata> {
+        vec![
+            ConstantMetadata::new("BLOCK_WEIGHTS", T::BLOCK_WEIGHTS),
+            ConstantMetadata::new("MAX_BLOCK_LENGTH", T::MAX_BLOCK_LENGTH),
+            ConstantMetadata::new("MAX_EXTRINSICS", T::MAX_EXTRINSICS),
+        ]
+    }
+}
+
+impl<T: Config> Hooks<T, T::BlockNumber> for Pallet<T> {
+    fn integrity_test() {
+        assert!(
+            T::BLOCK_WEIGHTS.base_extrinsic < T::BLOCK_WEIGHTS.max_block,
+            "the base weight of an extrinsic must be less than the maximum block weight"
+        );
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::collections::BTreeMap;
@@ -56,8 +303,10 @@ mod test {
 
     impl super::Config for TestConfig {
         type AccountId = String;
+        type RuntimeOrigin = crate::support::RawOrigin<String>;
         type BlockNumber = u32;
         type Nonce = u32;
+        type RuntimeEvent = super::Event;
     }
 
     #[test]
@@ -76,6 +325,98 @@ mod test {
         assert_eq!(pallet.block_number(), 1);
     }
 
+    #[test]
+    fn digest() {
+        use crate::support::{Digest, DigestItem};
+
+        let mut pallet = super::Pallet::<TestConfig>::new();
+        pallet.deposit_log(DigestItem::RuntimeEnvironmentUpdated);
+
+        // Only the pre-runtime items of the block author are kept for the next block.
+        let author_digest = Digest {
+            logs: vec![
+                DigestItem::PreRuntime(*b"test", vec![1]),
+                DigestItem::Seal(*b"test", vec![2]),
+            ],
+        };
+        pallet.initialize_digest(&author_digest);
+        pallet.deposit_log(DigestItem::Consensus(*b"test", vec![3]));
+
+        assert_eq!(
+            pallet.digest().logs,
+            vec![
+                DigestItem::PreRuntime(*b"test", vec![1]),
+                DigestItem::Consensus(*b"test", vec![3]),
+            ]
+        );
+    }
+
+    #[test]
+    fn events() {
+        use super::Event;
+        use crate::support::{DispatchInfo, EventRecord, Phase, PostDispatchInfo};
+
+        let mut pallet = super::Pallet::<TestConfig>::new();
+        let info = DispatchInfo { weight: 10 };
+        let post_info = PostDispatchInfo {
+            actual_weight: Some(4),
+        };
+        pallet.note_applied_extrinsic(&Ok(post_info), &info);
+        pallet.deposit_event(Event::ExtrinsicFailed { error: "test" });
+        pallet.note_applied_extrinsic(&Err("Call failed"), &info);
+
+        assert_eq!(
+            pallet.events(),
+            [
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: Event::ExtrinsicSuccess { weight: 4 },
+                },
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(1),
+                    event: Event::ExtrinsicFailed { error: "test" },
+                },
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(1),
+                    event: Event::ExtrinsicFailed {
+                        error: "Call failed"
+                    },
+                },
+            ]
+        );
+
+        pallet.reset_events();
+        assert!(pallet.events().is_empty());
+        pallet.deposit_event(Event::ExtrinsicSuccess { weight: 1 });
+        assert_eq!(pallet.events()[0].phase, Phase::ApplyExtrinsic(0));
+    }
+
+    #[test]
+    fn block_weight() {
+        use super::Event;
+        use crate::support::{DispatchInfo, Phase, PostDispatchInfo};
+
+        let mut pallet = super::Pallet::<TestConfig>::new();
+        let info = DispatchInfo { weight: 10 };
+        pallet.register_extra_weight(3);
+        pallet.note_applied_extrinsic(
+            &Ok(PostDispatchInfo {
+                actual_weight: Some(4),
+            }),
+            &info,
+        );
+        // A call which failed takes its full weight.
+        pallet.note_applied_extrinsic(&Err("Call failed"), &info);
+        assert_eq!(pallet.block_weight(), 17);
+        assert_eq!(pallet.remaining_weight(), u64::MAX - 17);
+
+        pallet.deposit_event_in_phase(Phase::Finalization, Event::ExtrinsicSuccess { weight: 0 });
+        assert_eq!(pallet.events()[2].phase, Phase::Finalization);
+
+        pallet.reset_events();
+        assert_eq!(pallet.block_weight(), 0);
+    }
+
     #[test]
     fn inc_nonce() {
         let mut expected_nonce: BTreeMap<String, u32> = BTreeMap::new();
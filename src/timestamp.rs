@@ -0,0 +1,228 @@
+use core::fmt::Debug;
+use num::traits::{CheckedAdd, Zero};
+
+use crate::support::{
+    codec::{Decode, Encode},
+    ensure_none,
+    metadata::{ConstantMetadata, FieldMetadata, PalletInfo},
+    DispatchResult, GetPallet, Hooks, ProvideInherent,
+};
+
+pub trait Config:
+    crate::system::Config + Sized + GetPallet<Pallet<Self>> + GetPallet<crate::system::Pallet<Self>>
+{
+    /// The type used to store the time, e.g. milliseconds since the unix epoch.
+    type Moment: Zero + CheckedAdd + Ord + Copy + Encode;
+    /// The minimum period between the timestamps of two sequential blocks.
+    const MINIMUM_PERIOD: Self::Moment;
+}
+
+/// This is the Timestamp Module.
+/// It keeps track of the time of the current block, which the block author sets with an inherent
+/// at the start of every block.
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+    /// The timestamp of the current block.
+    now: T::Moment,
+    /// The block in which the timestamp was last set, to allow a single update per block.
+    updated_at: Option<T::BlockNumber>,
+}
+
+#[macros::call]
+#[errors(
+    "Bad origin",
+    "Timestamp must be updated only once in the block",
+    "Overflow to add timestamp",
+    "Timestamp must increment by at least MINIMUM_PERIOD"
+)]
+impl<T: Config> Pallet<T> {
+    /// Set the timestamp of the current block to `now`.
+    /// This is an inherent: it can only be dispatched unsigned, once per block, and `now` must be
+    /// at least `MINIMUM_PERIOD` after the timestamp of the previous block.
+    #[call_index(0)]
+    pub fn set(runtime: &mut T, origin: T::RuntimeOrigin, now: T::Moment) -> DispatchResult {
+        ensure_none(origin)?;
+
+        let system: &crate::system::Pallet<T> = runtime.pallet();
+        let block_number = system.block_number();
+
+        let pallet: &mut Pallet<T> = runtime.pallet_mut();
+        if pallet.updated_at == Some(block_number) {
+            return Err("Timestamp must be updated only once in the block");
+        }
+        // There is no previous timestamp to compare with in the first block.
+        if !pallet.now.is_zero() {
+            let earliest = pallet
+                .now
+                .checked_add(&T::MINIMUM_PERIOD)
+                .ok_or("Overflow to add timestamp")?;
+            if now < earliest {
+                return Err("Timestamp must increment by at least MINIMUM_PERIOD");
+            }
+        }
+
+        pallet.now = now;
+        pallet.updated_at = Some(block_number);
+        Ok(())
+    }
+}
+
+impl<T: Config> Pallet<T> {
+    /// Create a new instance of the Timestamp Module.
+    pub fn new() -> Self {
+        Self {
+            now: T::Moment::zero(),
+            updated_at: None,
+        }
+    }
+
+    /// Get the timestamp of the current block.
+    pub fn now(&self) -> T::Moment {
+        self.now
+    }
+}
+
+impl<T: Config> Encode for Pallet<T>
+where
+    T::BlockNumber: Encode,
+{
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.now.encode_to(dest);
+        self.updated_at.encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for Pallet<T>
+where
+    T::Moment: Decode,
+    T::BlockNumber: Decode,
+{
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        Ok(Self {
+            now: Decode::decode_from(input)?,
+            updated_at: Decode::decode_from(input)?,
+        })
+    }
+}
+
+/// The timestamp must be set in every block.
+impl<T: Config> ProvideInherent for Pallet<T> {
+    type Call = Call<T>;
+
+    fn is_inherent(call: &Self::Call) -> bool {
+        matches!(call, Call::set { .. })
+    }
+
+    fn is_inherent_required() -> bool {
+        true
+    }
+}
+
+impl<T: Config> PalletInfo for Pallet<T>
+where
+    T::Moment: Debug,
+{
+    fn storage() -> Vec<FieldMetadata> {
+        vec![
+            FieldMetadata::new::<T::Moment>("now"),
+            FieldMetadata::new::<Option<T::BlockNumber>>("updated_at"),
+        ]
+    }
+
+    fn constants() -> Vec<ConstantMetadata> {
+        vec![ConstantMetadata::new("MINIMUM_PERIOD", T::MINIMUM_PERIOD)]
+    }
+}
+
+impl<T: Config> Hooks<T, T::BlockNumber> for Pallet<T> {
+    fn integrity_test() {
+        assert!(
+            T::MINIMUM_PERIOD > T::Moment::zero(),
+            "the minimum period between two timestamps must not be zero"
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::support::{GetPallet, RawOrigin};
+
+    struct TestRuntime {
+        system: crate::system::Pallet<TestRuntime>,
+        timestamp: super::Pallet<TestRuntime>,
+    }
+
+    impl super::Config for TestRuntime {
+        type Moment = u64;
+        const MINIMUM_PERIOD: u64 = 5;
+    }
+
+    impl crate::system::Config for TestRuntime {
+        type AccountId = String;
+        type RuntimeOrigin = RawOrigin<String>;
+        type BlockNumber = u32;
+        type Nonce = u32;
+        type RuntimeEvent = crate::system::Event;
+    }
+
+    impl GetPallet<crate::system::Pallet<Self>> for TestRuntime {
+        fn pallet(&self) -> &crate::system::Pallet<Self> {
+            &self.system
+        }
+        fn pallet_mut(&mut self) -> &mut crate::system::Pallet<Self> {
+            &mut self.system
+        }
+    }
+
+    impl GetPallet<super::Pallet<Self>> for TestRuntime {
+        fn pallet(&self) -> &super::Pallet<Self> {
+            &self.timestamp
+        }
+        fn pallet_mut(&mut self) -> &mut super::Pallet<Self> {
+            &mut self.timestamp
+        }
+    }
+
+    fn new_runtime() -> TestRuntime {
+        TestRuntime {
+            system: crate::system::Pallet::new(),
+            timestamp: super::Pallet::new(),
+        }
+    }
+
+    #[test]
+    fn set_timestamp() {
+        let mut runtime = new_runtime();
+        assert_eq!(runtime.timestamp.now(), 0);
+
+        runtime.system.inc_block_number();
+        let res = super::Pallet::set(&mut runtime, RawOrigin::None, 100);
+        assert_eq!(res, Ok(()));
+        assert_eq!(runtime.timestamp.now(), 100);
+
+        let res = super::Pallet::set(&mut runtime, RawOrigin::None, 200);
+        assert_eq!(res, Err("Timestamp must be updated only once in the block"));
+
+        runtime.system.inc_block_number();
+        let res = super::Pallet::set(&mut runtime, RawOrigin::None, 104);
+        assert_eq!(
+            res,
+            Err("Timestamp must increment by at least MINIMUM_PERIOD")
+        );
+        let res = super::Pallet::set(&mut runtime, RawOrigin::None, 105);
+        assert_eq!(res, Ok(()));
+        assert_eq!(runtime.timestamp.now(), 105);
+    }
+
+    #[test]
+    fn set_timestamp_requires_none_origin() {
+        let mut runtime = new_runtime();
+        runtime.system.inc_block_number();
+
+        let res = super::Pallet::set(&mut runtime, RawOrigin::Signed("alice".to_string()), 100);
+        assert_eq!(res, Err("Bad origin"));
+        let res = super::Pallet::set(&mut runtime, RawOrigin::Root, 100);
+        assert_eq!(res, Err("Bad origin"));
+        assert_eq!(runtime.timestamp.now(), 0);
+    }
+}
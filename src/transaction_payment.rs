@@ -0,0 +1,250 @@
+use std::marker::PhantomData;
+
+use num::traits::{CheckedAdd, CheckedSub, ToPrimitive, Zero};
+
+use crate::support::{
+    Convert, DispatchInfo, DispatchResult, GetPallet, PostDispatchInfo, SignedExtension,
+    TransactionPriority, Weight,
+};
+
+pub trait Config:
+    crate::balances::Config + Sized + GetPallet<crate::balances::Pallet<Self>>
+{
+    /// Converts the weight of a call into the fee paid for it.
+    type WeightToFee: Convert<Weight, Self::Balance>;
+    /// Converts the encoded length of an extrinsic, in bytes, into the fee paid for it.
+    type LengthToFee: Convert<u32, Self::Balance>;
+    /// Where the fees go once they are paid.
+    type FeeDestination: FeeDestination<Self>;
+}
+
+/// Handles the fees paid by transactions, e.g. by giving them to some account.
+pub trait FeeDestination<T: Config> {
+    /// Handle `fee`, which was withdrawn from the signer of a transaction.
+    fn on_fee(runtime: &mut T, fee: T::Balance) -> DispatchResult;
+}
+
+/// Burn the fees: nobody receives them.
+impl<T: Config> FeeDestination<T> for () {
+    fn on_fee(_runtime: &mut T, _fee: T::Balance) -> DispatchResult {
+        Ok(())
+    }
+}
+
+/// This is the Transaction Payment Module.
+/// It charges the signer of every extrinsic a fee for the weight of its call and its encoded
+/// length, before the call is dispatched. The fee for the weight the call did not use is refunded
+/// afterwards.
+///
+/// It is applied by the runtime as its `types::SignedExtra`.
+pub struct ChargeTransactionPayment<T: Config>(PhantomData<T>);
+
+impl<T: Config> ChargeTransactionPayment<T> {
+    /// The fee of an extrinsic whose call has the weight `weight`, and which has an encoded length
+    /// of `len`. The weight of the extrinsic includes the `base_extrinsic` weight of the system
+    /// pallet, so no extrinsic is free.
+    pub fn compute_fee(len: usize, weight: Weight) -> Result<T::Balance, &'static str> {
+        let len = u32::try_from(len).map_err(|_| "Extrinsic is too long")?;
+        let weight = T::BLOCK_WEIGHTS.base_extrinsic.saturating_add(weight);
+
+        T::WeightToFee::convert(weight)
+            .checked_add(&T::LengthToFee::convert(len))
+            .ok_or("Overflow to add fee")
+    }
+}
+
+impl<T: Config> SignedExtension<T> for ChargeTransactionPayment<T> {
+    type AccountId = T::AccountId;
+    /// The account which paid the fee, and the fee it paid.
+    type Pre = (T::AccountId, T::Balance);
+
+    /// The signer must be able to pay the fee, which is the priority of the transaction.
+    fn validate(
+        runtime: &T,
+        who: &Self::AccountId,
+        info: &DispatchInfo,
+        len: usize,
+    ) -> Result<TransactionPriority, &'static str> {
+        let fee = Self::compute_fee(len, info.weight)?;
+
+        let balances: &crate::balances::Pallet<T> = runtime.pallet();
+        balances
+            .can_withdraw(who, fee)
+            .map_err(|_| "Inability to pay some fees")?;
+
+        Ok(fee.to_u64().unwrap_or(TransactionPriority::MAX))
+    }
+
+    fn pre_dispatch(
+        runtime: &mut T,
+        who: &Self::AccountId,
+        info: &DispatchInfo,
+        len: usize,
+    ) -> Result<Self::Pre, &'static str> {
+        let fee = Self::compute_fee(len, info.weight)?;
+
+        let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+        balances
+            .withdraw(who, fee)
+            .map_err(|_| "Inability to pay some fees")?;
+
+        Ok((who.clone(), fee))
+    }
+
+    fn post_dispatch(
+        runtime: &mut T,
+        (who, paid): Self::Pre,
+        info: &DispatchInfo,
+        post_info: &PostDispatchInfo,
+        len: usize,
+    ) {
+        // The call already happened: the signer is never charged more than what it paid.
+        let actual_fee = Self::compute_fee(len, post_info.calc_actual_weight(info))
+            .unwrap_or(paid)
+            .min(paid);
+        let refund = paid.checked_sub(&actual_fee).unwrap_or(T::Balance::zero());
+
+        // The fee goes back to the signer when its destination can not take it, e.g. when the
+        // balance of the destination would overflow.
+        let refund = match T::FeeDestination::on_fee(runtime, actual_fee) {
+            Ok(()) => refund,
+            Err(_) => paid,
+        };
+        // The signer paid the refund just before, so it is only burned if the call credited the
+        // signer up to the maximum balance.
+        let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+        balances.deposit_saturating(&who, refund);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::support::{
+        ConvertInto, DispatchInfo, DispatchResult, GetPallet, PostDispatchInfo, RawOrigin,
+        SignedExtension,
+    };
+
+    type ChargeTransactionPayment = super::ChargeTransactionPayment<TestRuntime>;
+
+    struct TestRuntime {
+        balances: crate::balances::Pallet<TestRuntime>,
+    }
+
+    // Give the fees to the account "author".
+    struct ToAuthor;
+
+    impl super::FeeDestination<TestRuntime> for ToAuthor {
+        fn on_fee(runtime: &mut TestRuntime, fee: u64) -> DispatchResult {
+            runtime.balances.deposit(&"author".to_string(), fee)
+        }
+    }
+
+    impl super::Config for TestRuntime {
+        type WeightToFee = ConvertInto;
+        type LengthToFee = ConvertInto;
+        type FeeDestination = ToAuthor;
+    }
+
+    impl crate::balances::Config for TestRuntime {
+        type Balance = u64;
+    }
+
+    impl crate::system::Config for TestRuntime {
+        type AccountId = String;
+        type RuntimeOrigin = RawOrigin<String>;
+        type BlockNumber = u32;
+        type Nonce = u32;
+        type RuntimeEvent = crate::system::Event;
+    }
+
+    impl GetPallet<crate::balances::Pallet<Self>> for TestRuntime {
+        fn pallet(&self) -> &crate::balances::Pallet<Self> {
+            &self.balances
+        }
+        fn pallet_mut(&mut self) -> &mut crate::balances::Pallet<Self> {
+            &mut self.balances
+        }
+    }
+
+    fn new_runtime() -> TestRuntime {
+        let mut runtime = TestRuntime {
+            balances: crate::balances::Pallet::new(),
+        };
+        let _ = runtime
+            .balances
+            .set_balance(RawOrigin::Root, "alice".to_string(), 100);
+        runtime
+    }
+
+    #[test]
+    fn compute_fee() {
+        assert_eq!(ChargeTransactionPayment::compute_fee(10, 20), Ok(30));
+        assert_eq!(
+            ChargeTransactionPayment::compute_fee(10, u64::MAX),
+            Err("Overflow to add fee")
+        );
+
+        // A call without weight still pays for the base weight of an extrinsic.
+        let base_extrinsic =
+            <crate::Runtime as crate::system::Config>::BLOCK_WEIGHTS.base_extrinsic;
+        assert_eq!(
+            super::ChargeTransactionPayment::<crate::Runtime>::compute_fee(0, 0),
+            Ok(base_extrinsic.into())
+        );
+    }
+
+    #[test]
+    fn charge_and_refund_fee() {
+        let mut runtime = new_runtime();
+        let alice = "alice".to_string();
+        let info = DispatchInfo { weight: 50 };
+
+        let pre = ChargeTransactionPayment::pre_dispatch(&mut runtime, &alice, &info, 10);
+        assert_eq!(pre, Ok((alice.clone(), 60)));
+        assert_eq!(runtime.balances.balance(&alice), 40);
+
+        let post_info = PostDispatchInfo {
+            actual_weight: Some(20),
+        };
+        ChargeTransactionPayment::post_dispatch(&mut runtime, pre.unwrap(), &info, &post_info, 10);
+        assert_eq!(runtime.balances.balance(&alice), 70);
+        assert_eq!(runtime.balances.balance(&"author".to_string()), 30);
+    }
+
+    #[test]
+    fn refund_fee_when_fee_destination_fails_to_take_it() {
+        let mut runtime = new_runtime();
+        let alice = "alice".to_string();
+        let author = "author".to_string();
+        let info = DispatchInfo { weight: 50 };
+        // The author cannot receive the fee.
+        let _ = runtime
+            .balances
+            .set_balance(RawOrigin::Root, author.clone(), u64::MAX);
+
+        let pre = ChargeTransactionPayment::pre_dispatch(&mut runtime, &alice, &info, 10);
+        let post_info = PostDispatchInfo {
+            actual_weight: Some(20),
+        };
+        ChargeTransactionPayment::post_dispatch(&mut runtime, pre.unwrap(), &info, &post_info, 10);
+        // The fee the author can not receive goes back to alice.
+        assert_eq!(runtime.balances.balance(&alice), 100);
+        assert_eq!(runtime.balances.balance(&author), u64::MAX);
+    }
+
+    #[test]
+    fn charge_fee_return_inability_to_pay() {
+        let mut runtime = new_runtime();
+        let alice = "alice".to_string();
+        let info = DispatchInfo { weight: 100 };
+
+        let priority = ChargeTransactionPayment::validate(&runtime, &alice, &info, 10);
+        assert_eq!(priority, Err("Inability to pay some fees"));
+        let priority = ChargeTransactionPayment::validate(&runtime, &alice, &info, 0);
+        assert_eq!(priority, Ok(100));
+
+        let pre = ChargeTransactionPayment::pre_dispatch(&mut runtime, &alice, &info, 10);
+        assert_eq!(pre, Err("Inability to pay some fees"));
+        assert_eq!(runtime.balances.balance(&alice), 100);
+    }
+}
@@ -0,0 +1,310 @@
+use std::collections::BTreeSet;
+
+use crate::support::{
+    codec::Encode,
+    hashing::{hash, Hash},
+    TransactionTag, ValidTransaction, ValidateTransaction,
+};
+
+/// A transaction in the pool, with what its validation returned.
+#[derive(Debug, Clone)]
+struct PoolTransaction<Extrinsic> {
+    hash: Hash,
+    extrinsic: Extrinsic,
+    valid: ValidTransaction,
+}
+
+/// This is the Transaction Pool.
+/// It holds the signed extrinsics submitted to the chain until they are included in a block.
+///
+/// Transactions are validated against the current state of the runtime when they are submitted.
+/// A transaction whose requirements (e.g. the previous nonce of its signer) are provided by the
+/// state or by other transactions of the pool is ready to be included in the next block. Otherwise
+/// it waits in the future queue until the transactions it requires are submitted.
+#[derive(Debug)]
+pub struct TransactionPool<R: ValidateTransaction> {
+    /// The transactions which can be included in the next block.
+    ready: Vec<PoolTransaction<R::Extrinsic>>,
+    /// The transactions which require tags no transaction of the pool provides yet.
+    future: Vec<PoolTransaction<R::Extrinsic>>,
+}
+
+impl<R> TransactionPool<R>
+where
+    R: ValidateTransaction,
+    R::Extrinsic: Encode + Clone,
+{
+    /// Create a new, empty, transaction pool.
+    pub fn new() -> Self {
+        Self {
+            ready: Vec::new(),
+            future: Vec::new(),
+        }
+    }
+
+    /// Validate `extrinsic` against the current state of `runtime`, and add it to the pool.
+    /// Returns the hash of the transaction.
+    ///
+    /// A transaction which provides the same tag as a transaction of the pool, e.g. the same nonce
+    /// of the same signer, replaces it only if it has a higher priority.
+    pub fn submit(&mut self, runtime: &R, extrinsic: R::Extrinsic) -> Result<Hash, &'static str> {
+        let hash = hash(&extrinsic.encode());
+        if self.transactions().any(|tx| tx.hash == hash) {
+            return Err("Transaction is already in the pool");
+        }
+
+        let valid = runtime.validate_transaction(&extrinsic)?;
+        let replaced: Vec<Hash> = self
+            .transactions()
+            .filter(|tx| {
+                tx.valid
+                    .provides
+                    .iter()
+                    .any(|tag| valid.provides.contains(tag))
+            })
+            .map(|tx| tx.hash)
+            .collect();
+        if self
+            .transactions()
+            .any(|tx| replaced.contains(&tx.hash) && tx.valid.priority >= valid.priority)
+        {
+            return Err("Priority is too low to replace a transaction");
+        }
+
+        self.ready.retain(|tx| !replaced.contains(&tx.hash));
+        self.future.retain(|tx| !replaced.contains(&tx.hash));
+        self.insert(PoolTransaction {
+            hash,
+            extrinsic,
+            valid,
+        });
+        Ok(hash)
+    }
+
+    /// The ready transactions, in the order they should be included in a block: the transaction
+    /// with the highest priority goes first, once the transactions it requires were included.
+    pub fn ready(&self) -> Vec<R::Extrinsic> {
+        let mut pending: Vec<&PoolTransaction<R::Extrinsic>> = self.ready.iter().collect();
+        let mut provided = BTreeSet::new();
+        let mut ordered = Vec::new();
+
+        while let Some(best) = pending
+            .iter()
+            .enumerate()
+            .filter(|(_, tx)| Self::is_satisfied(tx, &provided))
+            .max_by(|(a_index, a), (b_index, b)| {
+                // On equal priorities, the transaction submitted first goes first.
+                a.valid
+                    .priority
+                    .cmp(&b.valid.priority)
+                    .then(b_index.cmp(a_index))
+            })
+            .map(|(index, _)| index)
+        {
+            let tx = pending.remove(best);
+            provided.extend(tx.valid.provides.iter());
+            ordered.push(tx.extrinsic.clone());
+        }
+        ordered
+    }
+
+    /// Remove the `included` extrinsics of an executed block from the pool, and validate the
+    /// remaining transactions against the new state of `runtime`. Transactions which are not valid
+    /// anymore, e.g. whose nonce is outdated, are dropped.
+    pub fn prune(&mut self, runtime: &R, included: &[R::Extrinsic]) {
+        let included: Vec<Hash> = included.iter().map(|ext| hash(&ext.encode())).collect();
+        let remaining = std::mem::take(&mut self.ready)
+            .into_iter()
+            .chain(std::mem::take(&mut self.future))
+            .filter(|tx| !included.contains(&tx.hash));
+
+        for tx in remaining {
+            if let Ok(valid) = runtime.validate_transaction(&tx.extrinsic) {
+                self.insert(PoolTransaction { valid, ..tx });
+            }
+        }
+    }
+
+    /// All the transactions of the pool.
+    fn transactions(&self) -> impl Iterator<Item = &PoolTransaction<R::Extrinsic>> {
+        self.ready.iter().chain(self.future.iter())
+    }
+
+    /// Whether all the tags `tx` requires are in `provided`.
+    fn is_satisfied(
+        tx: &PoolTransaction<R::Extrinsic>,
+        provided: &BTreeSet<&TransactionTag>,
+    ) -> bool {
+        tx.valid.requires.iter().all(|tag| provided.contains(tag))
+    }
+
+    /// Add `tx` to the future queue, then move to the ready queue every transaction whose
+    /// requirements are provided by the ready transactions.
+    fn insert(&mut self, tx: PoolTransaction<R::Extrinsic>) {
+        self.future.push(tx);
+
+        loop {
+            let provided: BTreeSet<&TransactionTag> = self
+                .ready
+                .iter()
+                .flat_map(|tx| tx.valid.provides.iter())
+                .collect();
+            let Some(index) = self
+                .future
+                .iter()
+                .position(|tx| Self::is_satisfied(tx, &provided))
+            else {
+                break;
+            };
+            let tx = self.future.remove(index);
+            self.ready.push(tx);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use crate::support::{codec::Encode, ValidTransaction, ValidateTransaction};
+
+    // An extrinsic of the test runtime, which is only a signer, a nonce and a priority.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct TestExtrinsic {
+        who: &'static str,
+        nonce: u32,
+        priority: u64,
+    }
+
+    impl Encode for TestExtrinsic {
+        fn encode_to(&self, dest: &mut Vec<u8>) {
+            self.who.encode_to(dest);
+            self.nonce.encode_to(dest);
+            self.priority.encode_to(dest);
+        }
+    }
+
+    // A runtime which only tracks the next nonce of each account.
+    struct TestRuntime {
+        nonces: BTreeMap<&'static str, u32>,
+    }
+
+    impl ValidateTransaction for TestRuntime {
+        type Extrinsic = TestExtrinsic;
+
+        fn validate_transaction(
+            &self,
+            extrinsic: &TestExtrinsic,
+        ) -> Result<ValidTransaction, &'static str> {
+            let next_nonce = self.nonces.get(extrinsic.who).copied().unwrap_or(0);
+            if extrinsic.nonce < next_nonce {
+                return Err("transaction nonce is outdated");
+            }
+
+            let requires = if extrinsic.nonce > next_nonce {
+                vec![(extrinsic.who, extrinsic.nonce - 1).encode()]
+            } else {
+                vec![]
+            };
+            Ok(ValidTransaction {
+                priority: extrinsic.priority,
+                requires,
+                provides: vec![(extrinsic.who, extrinsic.nonce).encode()],
+            })
+        }
+    }
+
+    fn tx(who: &'static str, nonce: u32, priority: u64) -> TestExtrinsic {
+        TestExtrinsic {
+            who,
+            nonce,
+            priority,
+        }
+    }
+
+    fn new_runtime() -> TestRuntime {
+        TestRuntime {
+            nonces: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn ready_orders_by_priority_and_nonce() {
+        let runtime = new_runtime();
+        let mut pool = super::TransactionPool::new();
+
+        for ext in [tx("alice", 0, 1), tx("bob", 0, 5), tx("alice", 1, 10)] {
+            assert!(pool.submit(&runtime, ext).is_ok());
+        }
+
+        // The second transaction of alice has the highest priority, but requires her first one.
+        assert_eq!(
+            pool.ready(),
+            vec![tx("bob", 0, 5), tx("alice", 0, 1), tx("alice", 1, 10)]
+        );
+    }
+
+    #[test]
+    fn future_transactions_wait_for_their_requirements() {
+        let runtime = new_runtime();
+        let mut pool = super::TransactionPool::new();
+
+        assert!(pool.submit(&runtime, tx("alice", 2, 1)).is_ok());
+        assert!(pool.submit(&runtime, tx("alice", 1, 1)).is_ok());
+        assert!(pool.ready().is_empty());
+        assert_eq!(pool.future.len(), 2);
+
+        assert!(pool.submit(&runtime, tx("alice", 0, 1)).is_ok());
+        assert_eq!(
+            pool.ready(),
+            vec![tx("alice", 0, 1), tx("alice", 1, 1), tx("alice", 2, 1)]
+        );
+        assert!(pool.future.is_empty());
+    }
+
+    #[test]
+    fn submit_rejects_invalid_and_duplicate_transactions() {
+        let mut runtime = new_runtime();
+        runtime.nonces.insert("alice", 1);
+        let mut pool = super::TransactionPool::new();
+
+        let res = pool.submit(&runtime, tx("alice", 0, 1));
+        assert_eq!(res, Err("transaction nonce is outdated"));
+
+        assert!(pool.submit(&runtime, tx("alice", 1, 1)).is_ok());
+        let res = pool.submit(&runtime, tx("alice", 1, 1));
+        assert_eq!(res, Err("Transaction is already in the pool"));
+    }
+
+    #[test]
+    fn submit_replaces_transaction_with_higher_priority() {
+        let runtime = new_runtime();
+        let mut pool = super::TransactionPool::new();
+
+        assert!(pool.submit(&runtime, tx("alice", 0, 5)).is_ok());
+        let res = pool.submit(&runtime, tx("alice", 0, 4));
+        assert_eq!(res, Err("Priority is too low to replace a transaction"));
+
+        assert!(pool.submit(&runtime, tx("alice", 0, 10)).is_ok());
+        assert_eq!(pool.ready(), vec![tx("alice", 0, 10)]);
+    }
+
+    #[test]
+    fn prune_removes_included_and_outdated_transactions() {
+        let mut runtime = new_runtime();
+        let mut pool = super::TransactionPool::new();
+
+        for ext in [tx("alice", 0, 1), tx("alice", 1, 1), tx("bob", 0, 1)] {
+            assert!(pool.submit(&runtime, ext).is_ok());
+        }
+
+        // A block included the first transaction of alice, and another transaction of bob with
+        // the same nonce, which outdates the one in the pool.
+        runtime.nonces.insert("alice", 1);
+        runtime.nonces.insert("bob", 1);
+        pool.prune(&runtime, &[tx("alice", 0, 1), tx("bob", 0, 2)]);
+
+        assert_eq!(pool.ready(), vec![tx("alice", 1, 1)]);
+        assert!(pool.future.is_empty());
+    }
+}
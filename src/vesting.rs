@@ -0,0 +1,458 @@
+use core::fmt::Debug;
+use std::collections::BTreeMap;
+
+use num::traits::{CheckedAdd, CheckedMul, CheckedSub, Zero};
+
+use crate::balances::LockIdentifier;
+use crate::support::{
+    codec::{Decode, Encode},
+    ensure_signed,
+    metadata::{ConstantMetadata, FieldMetadata, PalletInfo},
+    Convert, DispatchResult, GetPallet, Hooks, RawOrigin,
+};
+
+/// The identifier of the lock vesting puts on the balance of an account.
+const VESTING_ID: LockIdentifier = *b"vesting ";
+
+pub trait Config:
+    crate::balances::Config
+    + Sized
+    + GetPallet<Pallet<Self>>
+    + GetPallet<crate::balances::Pallet<Self>>
+    + GetPallet<crate::system::Pallet<Self>>
+{
+    /// Converts a number of blocks into a balance, to compute how much a schedule unlocked.
+    type BlockNumberToBalance: Convert<Self::BlockNumber, Self::Balance>;
+    /// The minimum amount which can be transferred with `vested_transfer`.
+    const MIN_VESTED_TRANSFER: Self::Balance;
+    /// The maximum number of vesting schedules an account can have.
+    const MAX_VESTING_SCHEDULES: u32;
+}
+
+/// A vesting schedule: `locked` is unlocked linearly, `per_block` for each block after
+/// `starting_block`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VestingInfo<Balance, BlockNumber> {
+    /// The amount locked when the schedule starts.
+    pub locked: Balance,
+    /// The amount unlocked for each block after `starting_block`.
+    pub per_block: Balance,
+    /// The block from which the amount starts to unlock.
+    pub starting_block: BlockNumber,
+}
+
+impl<Balance: Encode, BlockNumber: Encode> Encode for VestingInfo<Balance, BlockNumber> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.locked.encode_to(dest);
+        self.per_block.encode_to(dest);
+        self.starting_block.encode_to(dest);
+    }
+}
+
+impl<Balance: Decode, BlockNumber: Decode> Decode for VestingInfo<Balance, BlockNumber> {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        Ok(Self {
+            locked: Decode::decode_from(input)?,
+            per_block: Decode::decode_from(input)?,
+            starting_block: Decode::decode_from(input)?,
+        })
+    }
+}
+
+/// This is the Vesting Module.
+/// It locks balances which are unlocked linearly over time, e.g. tokens distributed to
+/// contributors.
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+    /// The vesting schedules of each account.
+    #[allow(clippy::type_complexity)]
+    vesting: BTreeMap<T::AccountId, Vec<VestingInfo<T::Balance, T::BlockNumber>>>,
+}
+
+#[macros::call]
+#[errors(
+    "Bad origin",
+    "Account has no vesting schedule",
+    "Amount is lower than the minimum vested transfer",
+    "Vesting schedule does not unlock any amount",
+    "Too many vesting schedules",
+    "Overflow to add locked balance",
+    "Balance not enough for the transfer",
+    "Balance is locked",
+    "Overflow to add balance"
+)]
+impl<T: Config> Pallet<T> {
+    /// Unlock the vested funds of the signer of `origin`.
+    /// This function will return an error if the signer has no vesting schedule.
+    #[call_index(0)]
+    pub fn vest(runtime: &mut T, origin: T::RuntimeOrigin) -> DispatchResult {
+        let who = ensure_signed(origin)?;
+
+        let pallet: &Pallet<T> = runtime.pallet();
+        if pallet.vesting(&who).is_empty() {
+            return Err("Account has no vesting schedule");
+        }
+
+        Self::update_lock(runtime, &who)
+    }
+
+    /// Transfer `schedule.locked` from the signer of `origin` to `target`, and add `schedule` to
+    /// the vesting schedules of `target`, which locks the transferred funds.
+    #[call_index(1)]
+    pub fn vested_transfer(
+        runtime: &mut T,
+        origin: T::RuntimeOrigin,
+        target: T::AccountId,
+        schedule: VestingInfo<T::Balance, T::BlockNumber>,
+    ) -> DispatchResult {
+        let who = ensure_signed(origin)?;
+        if schedule.locked < T::MIN_VESTED_TRANSFER {
+            return Err("Amount is lower than the minimum vested transfer");
+        }
+        if schedule.per_block.is_zero() {
+            return Err("Vesting schedule does not unlock any amount");
+        }
+
+        let pallet: &Pallet<T> = runtime.pallet();
+        let mut schedules = pallet.vesting(&target).to_vec();
+        if schedules.len() >= T::MAX_VESTING_SCHEDULES as usize {
+            return Err("Too many vesting schedules");
+        }
+        // The schedules of `target` must still be able to lock their total once the transfer is
+        // done.
+        schedules.push(schedule);
+        let system: &crate::system::Pallet<T> = runtime.pallet();
+        Self::total_locked(&schedules, system.block_number())?;
+
+        let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+        balances.transfer(
+            RawOrigin::Signed(who).into(),
+            target.clone(),
+            schedule.locked,
+        )?;
+
+        let pallet: &mut Pallet<T> = runtime.pallet_mut();
+        pallet
+            .vesting
+            .entry(target.clone())
+            .or_default()
+            .push(schedule);
+
+        Self::update_lock(runtime, &target)
+    }
+}
+
+impl<T: Config> Pallet<T> {
+    /// Create a new instance of the Vesting Module.
+    pub fn new() -> Self {
+        Self {
+            vesting: BTreeMap::new(),
+        }
+    }
+
+    /// Get the vesting schedules of `who`.
+    pub fn vesting(&self, who: &T::AccountId) -> &[VestingInfo<T::Balance, T::BlockNumber>] {
+        self.vesting.get(who).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// The amount of `schedule` still locked at block `n`.
+    pub fn locked_at(
+        schedule: &VestingInfo<T::Balance, T::BlockNumber>,
+        n: T::BlockNumber,
+    ) -> T::Balance {
+        let now = T::BlockNumberToBalance::convert(n);
+        let start = T::BlockNumberToBalance::convert(schedule.starting_block);
+
+        // Nothing is unlocked before the schedule starts.
+        let Some(elapsed) = now.checked_sub(&start) else {
+            return schedule.locked;
+        };
+        // Everything is unlocked if the unlocked amount overflows.
+        let Some(unlocked) = schedule.per_block.checked_mul(&elapsed) else {
+            return T::Balance::zero();
+        };
+
+        schedule
+            .locked
+            .checked_sub(&unlocked)
+            .unwrap_or(T::Balance::zero())
+    }
+
+    /// The total amount `schedules` still lock at block `n`.
+    /// This function will return an error if the total overflows.
+    fn total_locked(
+        schedules: &[VestingInfo<T::Balance, T::BlockNumber>],
+        n: T::BlockNumber,
+    ) -> Result<T::Balance, &'static str> {
+        schedules
+            .iter()
+            .try_fold(T::Balance::zero(), |locked, schedule| {
+                locked.checked_add(&Self::locked_at(schedule, n))
+            })
+            .ok_or("Overflow to add locked balance")
+    }
+
+    /// Update the vesting lock on the balance of `who` to what its schedules still lock at the
+    /// current block, and remove the schedules which are fully unlocked.
+    /// This function will return an error, and change nothing, if the locked amount overflows.
+    fn update_lock(runtime: &mut T, who: &T::AccountId) -> DispatchResult {
+        let system: &crate::system::Pallet<T> = runtime.pallet();
+        let now = system.block_number();
+
+        let pallet: &mut Pallet<T> = runtime.pallet_mut();
+        let locked = Self::total_locked(pallet.vesting(who), now)?;
+        let schedules = pallet.vesting.remove(who).unwrap_or_default();
+        let remaining: Vec<_> = schedules
+            .into_iter()
+            .filter(|schedule| !Self::locked_at(schedule, now).is_zero())
+            .collect();
+        if !remaining.is_empty() {
+            pallet.vesting.insert(who.clone(), remaining);
+        }
+
+        let balances: &mut crate::balances::Pallet<T> = runtime.pallet_mut();
+        if locked.is_zero() {
+            balances.remove_lock(VESTING_ID, who);
+        } else {
+            balances.set_lock(VESTING_ID, who, locked);
+        }
+        Ok(())
+    }
+}
+
+impl<T: Config> Encode for Pallet<T>
+where
+    T::Balance: Encode,
+    T::BlockNumber: Encode,
+{
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.vesting.encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for Pallet<T>
+where
+    T::AccountId: Decode,
+    T::Balance: Decode,
+    T::BlockNumber: Decode,
+{
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        Ok(Self {
+            vesting: Decode::decode_from(input)?,
+        })
+    }
+}
+
+impl<T: Config> PalletInfo for Pallet<T>
+where
+    T::Balance: Debug,
+{
+    fn storage() -> Vec<FieldMetadata> {
+        vec![FieldMetadata::new::<
+            BTreeMap<T::AccountId, Vec<VestingInfo<T::Balance, T::BlockNumber>>>,
+        >("vesting")]
+    }
+
+    fn constants() -> Vec<ConstantMetadata> {
+        vec![
+            ConstantMetadata::new("MIN_VESTED_TRANSFER", T::MIN_VESTED_TRANSFER),
+            ConstantMetadata::new("MAX_VESTING_SCHEDULES", T::MAX_VESTING_SCHEDULES),
+        ]
+    }
+}
+
+impl<T: Config> Hooks<T, T::BlockNumber> for Pallet<T> {
+    fn integrity_test() {
+        assert!(
+            T::MAX_VESTING_SCHEDULES > 0,
+            "an account must be able to have a vesting schedule"
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::support::{ConvertInto, GetPallet, RawOrigin};
+
+    struct TestRuntime {
+        system: crate::system::Pallet<TestRuntime>,
+        balances: crate::balances::Pallet<TestRuntime>,
+        vesting: super::Pallet<TestRuntime>,
+    }
+
+    impl super::Config for TestRuntime {
+        type BlockNumberToBalance = ConvertInto;
+        const MIN_VESTED_TRANSFER: u32 = 10;
+        const MAX_VESTING_SCHEDULES: u32 = 2;
+    }
+
+    impl crate::balances::Config for TestRuntime {
+        type Balance = u32;
+    }
+
+    impl crate::system::Config for TestRuntime {
+        type AccountId = String;
+        type RuntimeOrigin = RawOrigin<String>;
+        type BlockNumber = u32;
+        type Nonce = u32;
+        type RuntimeEvent = crate::system::Event;
+    }
+
+    impl GetPallet<crate::system::Pallet<Self>> for TestRuntime {
+        fn pallet(&self) -> &crate::system::Pallet<Self> {
+            &self.system
+        }
+        fn pallet_mut(&mut self) -> &mut crate::system::Pallet<Self> {
+            &mut self.system
+        }
+    }
+
+    impl GetPallet<crate::balances::Pallet<Self>> for TestRuntime {
+        fn pallet(&self) -> &crate::balances::Pallet<Self> {
+            &self.balances
+        }
+        fn pallet_mut(&mut self) -> &mut crate::balances::Pallet<Self> {
+            &mut self.balances
+        }
+    }
+
+    impl GetPallet<super::Pallet<Self>> for TestRuntime {
+        fn pallet(&self) -> &super::Pallet<Self> {
+            &self.vesting
+        }
+        fn pallet_mut(&mut self) -> &mut super::Pallet<Self> {
+            &mut self.vesting
+        }
+    }
+
+    fn new_runtime() -> TestRuntime {
+        let mut runtime = TestRuntime {
+            system: crate::system::Pallet::new(),
+            balances: crate::balances::Pallet::new(),
+            vesting: super::Pallet::new(),
+        };
+        let _ = runtime
+            .balances
+            .set_balance(RawOrigin::Root, "alice".to_string(), 100);
+        runtime
+    }
+
+    fn schedule(locked: u32, per_block: u32, starting_block: u32) -> super::VestingInfo<u32, u32> {
+        super::VestingInfo {
+            locked,
+            per_block,
+            starting_block,
+        }
+    }
+
+    #[test]
+    fn locked_at() {
+        let schedule = schedule(30, 10, 2);
+
+        assert_eq!(super::Pallet::<TestRuntime>::locked_at(&schedule, 0), 30);
+        assert_eq!(super::Pallet::<TestRuntime>::locked_at(&schedule, 2), 30);
+        assert_eq!(super::Pallet::<TestRuntime>::locked_at(&schedule, 3), 20);
+        assert_eq!(super::Pallet::<TestRuntime>::locked_at(&schedule, 5), 0);
+        assert_eq!(super::Pallet::<TestRuntime>::locked_at(&schedule, 100), 0);
+    }
+
+    #[test]
+    fn vested_transfer_and_vest() {
+        let mut runtime = new_runtime();
+        let bob = "bob".to_string();
+
+        let res = super::Pallet::vested_transfer(
+            &mut runtime,
+            RawOrigin::Signed("alice".to_string()),
+            bob.clone(),
+            schedule(30, 10, 1),
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(runtime.balances.balance(&bob), 30);
+        assert_eq!(runtime.balances.locked_balance(&bob), 30);
+        assert_eq!(runtime.vesting.vesting(&bob), &[schedule(30, 10, 1)]);
+
+        let res =
+            runtime
+                .balances
+                .transfer(RawOrigin::Signed(bob.clone()), "charlie".to_string(), 10);
+        assert_eq!(res, Err("Balance is locked"));
+
+        runtime.system.inc_block_number();
+        runtime.system.inc_block_number();
+        let res = super::Pallet::vest(&mut runtime, RawOrigin::Signed(bob.clone()));
+        assert_eq!(res, Ok(()));
+        assert_eq!(runtime.balances.locked_balance(&bob), 20);
+
+        runtime.system.inc_block_number();
+        runtime.system.inc_block_number();
+        let _ = super::Pallet::vest(&mut runtime, RawOrigin::Signed(bob.clone()));
+        assert_eq!(runtime.balances.locked_balance(&bob), 0);
+        assert!(runtime.vesting.vesting(&bob).is_empty());
+
+        let res = super::Pallet::vest(&mut runtime, RawOrigin::Signed(bob.clone()));
+        assert_eq!(res, Err("Account has no vesting schedule"));
+    }
+
+    #[test]
+    fn vested_transfer_return_invalid_schedule() {
+        let mut runtime = new_runtime();
+        let mut vested_transfer = |schedule| {
+            super::Pallet::vested_transfer(
+                &mut runtime,
+                RawOrigin::Signed("alice".to_string()),
+                "bob".to_string(),
+                schedule,
+            )
+        };
+
+        assert_eq!(
+            vested_transfer(schedule(5, 1, 0)),
+            Err("Amount is lower than the minimum vested transfer")
+        );
+        assert_eq!(
+            vested_transfer(schedule(10, 0, 0)),
+            Err("Vesting schedule does not unlock any amount")
+        );
+        assert_eq!(vested_transfer(schedule(10, 1, 0)), Ok(()));
+        assert_eq!(vested_transfer(schedule(10, 1, 0)), Ok(()));
+        assert_eq!(
+            vested_transfer(schedule(10, 1, 0)),
+            Err("Too many vesting schedules")
+        );
+    }
+
+    #[test]
+    fn vested_transfer_locked_overflow() {
+        let mut runtime = new_runtime();
+        let (alice, bob, charlie) = (
+            "alice".to_string(),
+            "bob".to_string(),
+            "charlie".to_string(),
+        );
+        let _ = runtime
+            .balances
+            .set_balance(RawOrigin::Root, alice.clone(), u32::MAX);
+        let _ = runtime
+            .balances
+            .set_balance(RawOrigin::Root, charlie.clone(), 20);
+
+        let res = super::Pallet::vested_transfer(
+            &mut runtime,
+            RawOrigin::Signed(alice),
+            bob.clone(),
+            schedule(u32::MAX - 10, 1, 0),
+        );
+        assert_eq!(res, Ok(()));
+        let res = super::Pallet::vested_transfer(
+            &mut runtime,
+            RawOrigin::Signed(charlie.clone()),
+            bob.clone(),
+            schedule(20, 1, 0),
+        );
+        assert_eq!(res, Err("Overflow to add locked balance"));
+        assert_eq!(runtime.balances.balance(&charlie), 20);
+        assert_eq!(runtime.vesting.vesting(&bob).len(), 1);
+        assert_eq!(runtime.balances.locked_balance(&bob), u32::MAX - 10);
+    }
+}